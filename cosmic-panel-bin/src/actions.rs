@@ -0,0 +1,26 @@
+// Session bus interface on which the panel emits the signals of its
+// configured click actions
+
+use zbus::{connection::Builder, interface, Connection, SignalContext};
+
+pub const ACTIONS_PATH: &str = "/com/system76/CosmicPanel/Actions";
+
+struct Actions;
+
+#[interface(name = "com.system76.CosmicPanel.Actions")]
+impl Actions {
+    /// emitted when a click action of a panel emits a named signal
+    #[zbus(signal)]
+    async fn action(ctxt: &SignalContext<'_>, panel: &str, name: &str) -> zbus::Result<()>;
+}
+
+/// serve the action interface on the connection of the panel
+pub fn serve_actions(builder: Builder<'_>) -> zbus::Result<Builder<'_>> {
+    builder.serve_at(ACTIONS_PATH, Actions)
+}
+
+/// emit the signal of a click action of the panel
+pub async fn emit_action(conn: &Connection, panel: &str, name: &str) -> zbus::Result<()> {
+    let ctxt = SignalContext::new(conn, ACTIONS_PATH)?;
+    Actions::action(&ctxt, panel, name).await
+}
@@ -0,0 +1,91 @@
+// Discovery of applets from a directory of desktop entries
+
+use std::{fs, path::Path};
+
+use cosmic_panel_config::CosmicPanelConfig;
+use freedesktop_desktop_entry::DesktopEntry;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use smithay::reexports::calloop::channel::Sender;
+use tracing::{error, warn};
+
+use crate::PanelCalloopMsg;
+
+/// the applets found in the applets directory of the config which it does not
+/// list, for the start, center and end sections
+///
+/// Every desktop entry with an `Exec` key in the directory is an applet, named
+/// after the file stem. The section is read from `X-CosmicPanelSection`,
+/// which may be `start`, `center`, or `end`, and defaults to `end`. Applets
+/// which are already listed are left where they are. Discovered applets are
+/// only added when the applets are spawned, so the config is never saved with
+/// them.
+pub fn discover_applets(config: &CosmicPanelConfig) -> [Vec<String>; 3] {
+    let mut discovered: [Vec<String>; 3] = Default::default();
+    let Some(dir) = config.applets_dir.as_ref() else {
+        return discovered;
+    };
+    let mut paths = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.extension().is_some_and(|e| e == "desktop"))
+            .collect::<Vec<_>>(),
+        Err(err) => {
+            warn!("Failed to read applets directory {:?}: {}", dir, err);
+            return discovered;
+        },
+    };
+    paths.sort();
+
+    let locales = freedesktop_desktop_entry::get_languages_from_env();
+    let listed = [config.plugins_left(), config.plugins_center(), config.plugins_right()]
+        .into_iter()
+        .flatten()
+        .flatten()
+        .collect::<Vec<_>>();
+    for path in paths {
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()).map(str::to_string) else {
+            continue;
+        };
+        if listed.contains(&name) || discovered.iter().any(|s| s.contains(&name)) {
+            continue;
+        }
+        let Ok(bytes) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let Ok(entry) = DesktopEntry::from_str(&path, &bytes, &locales) else {
+            warn!("Failed to parse applet entry {:?}", path);
+            continue;
+        };
+        if entry.exec().is_none() {
+            continue;
+        }
+        let section = match entry.desktop_entry("X-CosmicPanelSection") {
+            Some("start") => 0,
+            Some("center") => 1,
+            _ => 2,
+        };
+        discovered[section].push(name);
+    }
+    discovered
+}
+
+/// watch the applets directory of a panel, requesting an update of the panel
+/// when applets are added or removed
+pub fn watch_applets_dir(
+    name: String,
+    dir: &Path,
+    panel_tx: Sender<PanelCalloopMsg>,
+) -> notify::Result<RecommendedWatcher> {
+    let mut watcher =
+        notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+            Ok(event) if event.kind.is_create() || event.kind.is_remove() => {
+                if let Err(err) = panel_tx.send(PanelCalloopMsg::AppletsChanged(name.clone())) {
+                    error!("Failed to send applets update: {}", err);
+                }
+            },
+            Ok(_) => {},
+            Err(err) => error!("Applets directory watch error: {:?}", err),
+        })?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+    Ok(watcher)
+}
@@ -0,0 +1,36 @@
+// Session bus interface which lets applets report a count shown in a badge
+// drawn by the panel
+
+use smithay::reexports::calloop::channel::Sender;
+use tracing::error;
+use zbus::{connection::Builder, interface};
+
+use crate::PanelCalloopMsg;
+
+pub const BADGES_PATH: &str = "/com/system76/CosmicPanel/Badges";
+
+struct Badges {
+    panel_tx: Sender<PanelCalloopMsg>,
+}
+
+#[interface(name = "com.system76.CosmicPanel.Badges")]
+impl Badges {
+    /// set the count shown in the badge of an applet, by applet name
+    ///
+    /// Instances after the first of an applet listed more than once are named
+    /// by their name and the index in `COSMIC_PANEL_APPLET_INSTANCE`, e.g.
+    /// `com.system76.CosmicAppList#1`. A count of zero clears the badge.
+    fn set_count(&self, applet: String, count: u32) {
+        if let Err(err) = self.panel_tx.send(PanelCalloopMsg::AppletBadge { applet, count }) {
+            error!("Failed to send applet badge: {}", err);
+        }
+    }
+}
+
+/// serve the badge interface on the connection of the panel
+pub fn serve_badges(
+    builder: Builder<'_>,
+    panel_tx: Sender<PanelCalloopMsg>,
+) -> zbus::Result<Builder<'_>> {
+    builder.serve_at(BADGES_PATH, Badges { panel_tx })
+}
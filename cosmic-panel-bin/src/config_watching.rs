@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{cell::RefCell, collections::HashMap, rc::Rc, time::Duration};
 
 use crate::xdg_shell_wrapper::shared_state::GlobalState;
 use anyhow::anyhow;
@@ -9,23 +9,28 @@ use cosmic::{
 use cosmic_panel_config::{CosmicPanelConfig, CosmicPanelContainerConfig};
 use cosmic_theme::{Theme, ThemeMode};
 use notify::RecommendedWatcher;
-use smithay::reexports::calloop::{channel, LoopHandle};
+use smithay::reexports::calloop::{
+    channel,
+    timer::{TimeoutAction, Timer},
+    LoopHandle, RegistrationToken,
+};
 use tracing::{error, info};
 
 #[derive(Debug, Clone)]
 enum ConfigUpdate {
     Entries(Vec<String>),
     EntryChanged(CosmicPanelConfig),
+    PrimaryOutput(Option<String>),
 }
 
 #[derive(Debug, Clone)]
 enum ThemeUpdate {
     /// is the theme light or dark
     Mode(bool),
-    /// dark theme bg change,
-    Dark(theme::CosmicTheme),
-    /// light theme bg change,
-    Light(theme::CosmicTheme),
+    /// dark theme bg change, and whether the theme failed to load
+    Dark(theme::CosmicTheme, bool),
+    /// light theme bg change, and whether the theme failed to load
+    Light(theme::CosmicTheme, bool),
 }
 
 pub fn watch_cosmic_theme(
@@ -38,14 +43,14 @@ pub fn watch_cosmic_theme(
 
     handle.insert_source(entries_rx, move |event, _, state| {
         match event {
-            channel::Event::Msg(ThemeUpdate::Dark(theme)) => {
-                state.space.set_dark(theme);
+            channel::Event::Msg(ThemeUpdate::Dark(theme, failed)) => {
+                state.space.set_dark(theme, failed);
             },
             channel::Event::Msg(ThemeUpdate::Mode(is_dark)) => {
                 state.space.set_theme_mode(is_dark);
             },
-            channel::Event::Msg(ThemeUpdate::Light(theme)) => {
-                state.space.set_light(theme);
+            channel::Event::Msg(ThemeUpdate::Light(theme, failed)) => {
+                state.space.set_light(theme, failed);
             },
             channel::Event::Closed => {},
         };
@@ -70,13 +75,13 @@ pub fn watch_cosmic_theme(
     let theme_watcher_light = config_light_helper
         .watch(move |helper, _keys| match Theme::get_entry(helper) {
             Ok(entry) => {
-                entries_tx_clone.send(ThemeUpdate::Light(entry)).unwrap();
+                entries_tx_clone.send(ThemeUpdate::Light(entry, false)).unwrap();
             },
             Err((err, entry)) => {
                 for e in err {
                     error!("Failed to get theme entry value: {:?}", e);
                 }
-                entries_tx_clone.send(ThemeUpdate::Light(entry)).unwrap();
+                entries_tx_clone.send(ThemeUpdate::Light(entry, true)).unwrap();
             },
         })
         .map_err(|e| anyhow!(format!("{:?}", e)))?;
@@ -85,13 +90,13 @@ pub fn watch_cosmic_theme(
     let theme_watcher_dark = config_dark_helper
         .watch(move |helper, _keys| match Theme::get_entry(helper) {
             Ok(entry) => {
-                entries_tx_clone.send(ThemeUpdate::Dark(entry)).unwrap();
+                entries_tx_clone.send(ThemeUpdate::Dark(entry, false)).unwrap();
             },
             Err((err, entry)) => {
                 for e in err {
                     error!("Failed to get theme entry value: {:?}", e);
                 }
-                entries_tx_clone.send(ThemeUpdate::Dark(entry)).unwrap();
+                entries_tx_clone.send(ThemeUpdate::Dark(entry, true)).unwrap();
             },
         })
         .map_err(|e| anyhow!(format!("{:?}", e)))?;
@@ -99,6 +104,20 @@ pub fn watch_cosmic_theme(
     Ok(vec![theme_watcher_dark, theme_watcher_light, theme_watcher_mode])
 }
 
+/// recreate or update the panel of a changed config
+fn apply_entry_change(state: &mut GlobalState, config: CosmicPanelConfig) {
+    state.space.update_space(
+        config,
+        &state.client_state.compositor_state,
+        state.client_state.fractional_scaling_manager.as_ref(),
+        state.client_state.viewporter_state.as_ref(),
+        &mut state.client_state.layer_state,
+        &state.client_state.queue_handle,
+        None,
+        state.client_state.overlap_notify.clone(),
+    );
+}
+
 pub fn watch_config(
     config: &CosmicPanelContainerConfig,
     handle: LoopHandle<GlobalState>,
@@ -106,6 +125,10 @@ pub fn watch_config(
     let (entries_tx, entries_rx) = channel::sync_channel::<ConfigUpdate>(30);
 
     let entries_tx_clone = entries_tx.clone();
+    // changes waiting for the debounce to elapse, by panel name
+    let pending: Rc<RefCell<HashMap<String, (CosmicPanelConfig, RegistrationToken)>>> =
+        Rc::default();
+    let timer_handle = handle.clone();
 
     handle.insert_source(entries_rx, move |event, _, state| {
         match event {
@@ -178,20 +201,48 @@ pub fn watch_config(
                     .map(|c| c.name.clone())
                     .collect::<Vec<String>>();
                 for entry in to_remove {
+                    // a pending change must not recreate the removed panel
+                    if let Some((_, token)) = pending.borrow_mut().remove(&entry) {
+                        timer_handle.remove(token);
+                    }
                     state.space.remove_space(entry);
                 }
             },
             channel::Event::Msg(ConfigUpdate::EntryChanged(config)) => {
-                state.space.update_space(
-                    config,
-                    &state.client_state.compositor_state,
-                    state.client_state.fractional_scaling_manager.as_ref(),
-                    state.client_state.viewporter_state.as_ref(),
-                    &mut state.client_state.layer_state,
-                    &state.client_state.queue_handle,
-                    None,
-                    state.client_state.overlap_notify.clone(),
-                );
+                let debounce = state.space.config.config_debounce;
+                if debounce == 0 {
+                    apply_entry_change(state, config);
+                    return;
+                }
+                // restart the debounce of the panel with the latest config
+                let mut pending_guard = pending.borrow_mut();
+                if let Some((_, token)) = pending_guard.remove(&config.name) {
+                    timer_handle.remove(token);
+                }
+                let pending_clone = pending.clone();
+                let name = config.name.clone();
+                match timer_handle.insert_source(
+                    Timer::from_duration(Duration::from_millis(debounce.into())),
+                    move |_, _, state| {
+                        let config = pending_clone.borrow_mut().remove(&name);
+                        if let Some((config, _)) = config {
+                            apply_entry_change(state, config);
+                        }
+                        TimeoutAction::Drop
+                    },
+                ) {
+                    Ok(token) => {
+                        pending_guard.insert(config.name.clone(), (config, token));
+                    },
+                    Err(err) => {
+                        error!("Failed to debounce config change: {}", err);
+                        drop(pending_guard);
+                        apply_entry_change(state, config);
+                    },
+                }
+            },
+            channel::Event::Msg(ConfigUpdate::PrimaryOutput(name)) => {
+                state.space.set_primary_output(name);
             },
             channel::Event::Closed => {},
         };
@@ -203,15 +254,27 @@ pub fn watch_config(
 
     let entries_tx_clone = entries_tx.clone();
     let entries_watcher = cosmic_config_entries
-        .watch(move |helper, keys| match helper.get::<Vec<String>>(&keys[0]) {
-            Ok(entries) => {
+        .watch(move |helper, keys| {
+            if keys.iter().any(|k| k == "primary_output") {
+                let primary_output =
+                    helper.get::<Option<String>>("primary_output").unwrap_or_default();
                 entries_tx_clone
-                    .send(ConfigUpdate::Entries(entries))
-                    .expect("Failed to send entries");
-            },
-            Err(err) => {
-                error!("Failed to get entries: {:?}", err);
-            },
+                    .send(ConfigUpdate::PrimaryOutput(primary_output))
+                    .expect("Failed to send primary output");
+            }
+            if !keys.iter().any(|k| k == "entries") {
+                return;
+            }
+            match helper.get::<Vec<String>>("entries") {
+                Ok(entries) => {
+                    entries_tx_clone
+                        .send(ConfigUpdate::Entries(entries))
+                        .expect("Failed to send entries");
+                },
+                Err(err) => {
+                    error!("Failed to get entries: {:?}", err);
+                },
+            }
         })
         .expect("Failed to watch cosmic config");
 
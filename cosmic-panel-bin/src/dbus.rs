@@ -0,0 +1,27 @@
+// Session bus connection of the panel, serving its interfaces under a single
+// well-known name
+
+use smithay::reexports::calloop::channel::Sender;
+use zbus::{connection::Builder, Connection};
+
+use crate::{
+    actions::serve_actions, badges::serve_badges, keyboard_focus::serve_keyboard_focus,
+    progress::serve_progress, status::serve_status, PanelCalloopMsg,
+};
+
+pub const PANEL_BUS_NAME: &str = "com.system76.CosmicPanel";
+
+/// serve the interfaces of the panel on the session bus
+///
+/// The interfaces are served for as long as the returned connection is alive.
+pub async fn panel_conn(panel_tx: Sender<PanelCalloopMsg>) -> zbus::Result<Connection> {
+    let builder = Builder::session()?.name(PANEL_BUS_NAME)?;
+    let builder = serve_actions(builder)?;
+    let builder = serve_badges(builder, panel_tx.clone())?;
+    let builder = serve_progress(builder, panel_tx.clone())?;
+    let builder = serve_keyboard_focus(builder, panel_tx.clone())?;
+    let builder = serve_status(builder, panel_tx.clone())?;
+    #[cfg(feature = "headless")]
+    let builder = crate::headless::serve_headless(builder, panel_tx)?;
+    builder.build().await
+}
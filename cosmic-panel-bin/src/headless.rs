@@ -0,0 +1,38 @@
+// Session bus interface which saves offscreen renders of the panels, for
+// tests and for inspecting the panels without a compositor
+
+use std::path::PathBuf;
+
+use smithay::reexports::calloop::channel::Sender;
+use tokio::sync::oneshot;
+use zbus::{connection::Builder, fdo, interface};
+
+use crate::PanelCalloopMsg;
+
+pub const HEADLESS_PATH: &str = "/com/system76/CosmicPanel/Headless";
+
+struct Headless {
+    panel_tx: Sender<PanelCalloopMsg>,
+}
+
+#[interface(name = "com.system76.CosmicPanel.Headless")]
+impl Headless {
+    /// render each panel offscreen and save it to the directory as a PNG,
+    /// returning the paths of the saved files
+    async fn save_renders(&self, dir: String) -> fdo::Result<Vec<String>> {
+        let (tx, rx) = oneshot::channel();
+        self.panel_tx
+            .send(PanelCalloopMsg::SaveRenders(PathBuf::from(dir), tx))
+            .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+        let saved = rx.await.map_err(|err| fdo::Error::Failed(err.to_string()))?;
+        Ok(saved.into_iter().map(|p| p.to_string_lossy().into_owned()).collect())
+    }
+}
+
+/// serve the headless interface on the connection of the panel
+pub fn serve_headless(
+    builder: Builder<'_>,
+    panel_tx: Sender<PanelCalloopMsg>,
+) -> zbus::Result<Builder<'_>> {
+    builder.serve_at(HEADLESS_PATH, Headless { panel_tx })
+}
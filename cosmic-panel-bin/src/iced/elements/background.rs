@@ -8,6 +8,7 @@ use cosmic::{
     widget::horizontal_space,
     Theme,
 };
+use cosmic_panel_config::Border;
 
 use crate::{
     iced::{Element, IcedElement, Program},
@@ -26,6 +27,7 @@ pub fn background_element(
     panel_id: usize,
     logical_pos: [f32; 2],
     color: [f32; 4],
+    border: Option<Border>,
 ) -> BackgroundElement {
     IcedElement::new(
         Background {
@@ -35,6 +37,7 @@ pub fn background_element(
             radius,
             logical_pos: (logical_pos[0].round() as i32, logical_pos[1].round() as i32),
             color,
+            border,
         },
         (logical_width, logical_height),
         loop_handle,
@@ -51,6 +54,7 @@ pub struct Background {
     pub radius: [f32; 4],
     pub logical_pos: (i32, i32),
     pub color: [f32; 4],
+    pub border: Option<Border>,
 }
 
 impl Program for Background {
@@ -62,6 +66,7 @@ impl Program for Background {
         let radius_arr: [f32; 4] = self.radius.clone();
 
         let color = self.color;
+        let border = self.border;
         Element::from(
             cosmic::widget::container(horizontal_space().width(Length::Fixed(width)))
                 .width(Length::Fixed(width))
@@ -74,8 +79,10 @@ impl Program for Background {
                         background: Some(Color::from(color).into()),
                         border: cosmic::iced::Border {
                             radius: radius_arr.into(),
-                            width: 0.,
-                            color: cosmic.background.divider.into(),
+                            width: border.map(|b| b.width).unwrap_or_default(),
+                            color: border
+                                .map(|b| Color::from(b.color))
+                                .unwrap_or_else(|| cosmic.background.divider.into()),
                         },
                         shadow: Shadow::default(),
                         icon_color: Some(cosmic.background.on.into()),
@@ -0,0 +1,73 @@
+// Element for rendering the count reported by an applet over it
+
+use calloop::LoopHandle;
+use cosmic::{
+    iced::{Alignment, Color, Length},
+    iced_core::Shadow,
+    theme,
+    widget::{container, text},
+    Theme,
+};
+use smithay::utils::{Logical, Size};
+
+use crate::{
+    iced::{Element, IcedElement, Program},
+    xdg_shell_wrapper::shared_state::GlobalState,
+};
+
+pub type BadgeElement = IcedElement<Badge>;
+
+/// largest count shown, larger counts are shown as this count followed by a
+/// plus
+const MAX_COUNT: u32 = 99;
+
+pub fn badge_element(
+    count: u32,
+    icon_size: u32,
+    loop_handle: LoopHandle<'static, GlobalState>,
+    theme: Theme,
+    panel_id: usize,
+) -> BadgeElement {
+    let label = if count > MAX_COUNT { format!("{MAX_COUNT}+") } else { count.to_string() };
+    // a circle for a single digit, a pill for more
+    let height = (icon_size as f32 / 2.).round().max(12.);
+    let width = height + (label.len() - 1) as f32 * height * 0.4;
+    let size = Size::from((width.round() as i32, height as i32));
+    IcedElement::new(Badge { count, label, size }, size, loop_handle, theme, panel_id, false)
+}
+
+pub struct Badge {
+    pub count: u32,
+    label: String,
+    pub size: Size<i32, Logical>,
+}
+
+impl Program for Badge {
+    type Message = ();
+
+    fn view(&self) -> Element<'_, ()> {
+        let width = self.size.w as f32;
+        let height = self.size.h as f32;
+        Element::from(
+            container(text(self.label.as_str()).size(height * 0.7))
+                .align_x(Alignment::Center)
+                .align_y(Alignment::Center)
+                .width(Length::Fixed(width))
+                .height(Length::Fixed(height))
+                .class(theme::Container::custom(move |theme| {
+                    let cosmic = theme.cosmic();
+
+                    container::Style {
+                        text_color: Some(cosmic.accent.on.into()),
+                        background: Some(Color::from(cosmic.accent_color()).into()),
+                        border: cosmic::iced::Border {
+                            radius: (height / 2.).into(),
+                            ..Default::default()
+                        },
+                        shadow: Shadow::default(),
+                        icon_color: Some(cosmic.accent.on.into()),
+                    }
+                })),
+        )
+    }
+}
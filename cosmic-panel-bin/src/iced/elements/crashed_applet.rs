@@ -0,0 +1,77 @@
+// Element for rendering a placeholder in the slot of an applet which crashed
+// too often to be restarted, or which is not started yet
+
+use std::rc::Rc;
+
+use calloop::LoopHandle;
+use cosmic::{
+    iced::{Alignment, Length},
+    theme,
+    widget::{container, horizontal_space, icon},
+    Theme,
+};
+use smithay::utils::{Logical, Size};
+
+use crate::{
+    iced::{Element, IcedElement, Program},
+    xdg_shell_wrapper::shared_state::GlobalState,
+};
+
+pub type CrashedAppletElement = IcedElement<CrashedApplet>;
+
+const ICON: &str = "dialog-error-symbolic";
+
+pub fn crashed_applet_element(
+    crashed: bool,
+    icon_size: u32,
+    padding: u32,
+    loop_handle: LoopHandle<'static, GlobalState>,
+    theme: Theme,
+    panel_id: usize,
+) -> CrashedAppletElement {
+    let side = (icon_size + padding * 2) as i32;
+    let size = Size::from((side, side));
+    IcedElement::new(
+        CrashedApplet { crashed, icon_size, size },
+        size,
+        loop_handle,
+        theme,
+        panel_id,
+        false,
+    )
+}
+
+pub struct CrashedApplet {
+    /// whether the applet crashed, otherwise the slot is kept empty
+    pub crashed: bool,
+    icon_size: u32,
+    pub size: Size<i32, Logical>,
+}
+
+impl Program for CrashedApplet {
+    type Message = ();
+
+    fn view(&self) -> Element<'_, ()> {
+        if !self.crashed {
+            return Element::from(
+                container(horizontal_space())
+                    .width(Length::Fixed(self.size.w as f32))
+                    .height(Length::Fixed(self.size.h as f32)),
+            );
+        }
+        Element::from(
+            container(
+                icon(icon::from_name(ICON).into())
+                    .class(theme::Svg::Custom(Rc::new(|theme| cosmic::iced_widget::svg::Style {
+                        color: Some(theme.cosmic().destructive_color().into()),
+                    })))
+                    .width(Length::Fixed(self.icon_size as f32))
+                    .height(Length::Fixed(self.icon_size as f32)),
+            )
+            .align_x(Alignment::Center)
+            .align_y(Alignment::Center)
+            .width(Length::Fixed(self.size.w as f32))
+            .height(Length::Fixed(self.size.h as f32)),
+        )
+    }
+}
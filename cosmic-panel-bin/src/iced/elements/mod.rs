@@ -1,6 +1,9 @@
 pub mod background;
+pub mod badge;
+pub mod crashed_applet;
 pub mod overflow_button;
 pub mod overflow_popup;
+pub mod start_button;
 pub mod target;
 
 use std::borrow::Cow;
@@ -13,11 +16,13 @@ use smithay::{
     space_elements,
     wayland::{seat::WaylandFocus, shell::xdg::ToplevelSurface},
 };
+use start_button::StartButtonElement;
 
 space_elements! {
     #[derive(Debug, Clone, PartialEq, Eq, Hash)]
     pub CosmicMappedInternal;
     OverflowButton=OverflowButtonElement,
+    StartButton=StartButtonElement,
     Window=Window,
     Background=BackgroundElement
 }
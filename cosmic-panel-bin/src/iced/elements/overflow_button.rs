@@ -15,7 +15,7 @@ use cosmic::{
     iced::{Length, Padding},
     iced_core::id,
     theme::{self, Button},
-    widget::{button, layer_container, Id},
+    widget::{button, layer_container, mouse_area, Id},
     Element,
 };
 use smithay::utils::{Logical, Point, Size};
@@ -68,6 +68,7 @@ pub fn with_id<T>(b: &OverflowButtonElement, f: impl Fn(&Id) -> T) -> T {
 pub enum Message {
     TogglePopup,
     HidePopup,
+    ToggleCollapsed,
 }
 
 #[derive(Debug, Clone)]
@@ -94,6 +95,12 @@ impl OverflowButton {
     ) -> Self {
         Self { id, pos, icon_size, button_padding, selected, icon, panel_id }
     }
+
+    /// mark the button as selected while its popup is open
+    /// returns true if the state changed
+    pub fn set_selected(&self, selected: bool) -> bool {
+        self.selected.swap(selected, Ordering::Relaxed) != selected
+    }
 }
 
 impl PartialEq for OverflowButton {
@@ -163,31 +170,36 @@ impl Program for OverflowButton {
                     );
                 });
             },
+            Message::ToggleCollapsed => {
+                let panel_id = self.panel_id;
+
+                _ = loop_handle.insert_idle(move |state| {
+                    state.space.toggle_collapsed(panel_id);
+                });
+            },
         }
         cosmic::Task::none()
     }
 
     fn view(&self) -> crate::iced::Element<'_, Self::Message> {
-        Element::from(
-            button::custom(
-                layer_container(
-                    cosmic::widget::icon(cosmic::widget::icon::from_name(self.icon.clone()).into())
-                        .class(theme::Svg::Custom(Rc::new(|theme| {
-                            cosmic::iced_widget::svg::Style {
-                                color: Some(theme.cosmic().background.on.into()),
-                            }
-                        })))
-                        .width(Length::Fixed(self.icon_size as f32))
-                        .height(Length::Fixed(self.icon_size as f32)),
-                )
-                .align_x(cosmic::iced::Alignment::Center)
-                .align_y(cosmic::iced::Alignment::Center)
-                .width(Length::Fixed(self.icon_size as f32 + self.button_padding.horizontal()))
-                .height(Length::Fixed(self.icon_size as f32 + self.button_padding.horizontal())),
+        let button = button::custom(
+            layer_container(
+                cosmic::widget::icon(cosmic::widget::icon::from_name(self.icon.clone()).into())
+                    .class(theme::Svg::Custom(Rc::new(|theme| cosmic::iced_widget::svg::Style {
+                        color: Some(theme.cosmic().background.on.into()),
+                    })))
+                    .width(Length::Fixed(self.icon_size as f32))
+                    .height(Length::Fixed(self.icon_size as f32)),
             )
-            .selected(self.selected.load(Ordering::Relaxed))
-            .class(Button::AppletIcon)
-            .on_press(Message::TogglePopup),
+            .align_x(cosmic::iced::Alignment::Center)
+            .align_y(cosmic::iced::Alignment::Center)
+            .width(Length::Fixed(self.icon_size as f32 + self.button_padding.horizontal()))
+            .height(Length::Fixed(self.icon_size as f32 + self.button_padding.horizontal())),
         )
+        .selected(self.selected.load(Ordering::Relaxed))
+        .class(Button::AppletIcon)
+        .on_press(Message::TogglePopup);
+        // a right click collapses or expands the collapsible applets
+        Element::from(mouse_area(button).on_right_press(Message::ToggleCollapsed))
     }
 }
@@ -8,6 +8,7 @@ use cosmic::{
     widget::{container, horizontal_space},
     Theme,
 };
+use cosmic_panel_config::OverflowPopupStyle;
 
 use crate::{
     iced::{Element, IcedElement, Program},
@@ -26,9 +27,16 @@ pub fn overflow_popup_element(
     theme: Theme,
     panel_id: usize,
     count: usize,
+    style: Option<OverflowPopupStyle>,
 ) -> OverflowPopupElement {
     IcedElement::new(
-        OverflowPopup { id, logical_width, logical_height, count },
+        OverflowPopup {
+            id,
+            logical_width,
+            logical_height,
+            count,
+            style: style.unwrap_or_default(),
+        },
         ((logical_width).round() as i32, (logical_height).round() as i32),
         loop_handle,
         theme,
@@ -42,6 +50,7 @@ pub struct OverflowPopup {
     pub logical_width: f32,
     pub logical_height: f32,
     pub count: usize,
+    pub style: OverflowPopupStyle,
 }
 
 impl Program for OverflowPopup {
@@ -51,17 +60,29 @@ impl Program for OverflowPopup {
         let width = self.logical_width;
         let height = self.logical_height;
         let border_width = BORDER_WIDTH as f32;
+        let style = self.style.clone();
         Element::from(
             cosmic::widget::container(horizontal_space().width(Length::Fixed(width)))
                 .width(Length::Fixed(width))
                 .height(Length::Fixed(height))
                 .class(theme::Container::custom(move |theme| {
                     let cosmic = theme.cosmic();
-                    let radius_m = cosmic.corner_radii.radius_m;
+                    // the radius can't be larger than half of the popup
+                    let max_radius = width.min(height) / 2.;
+                    let radius_m =
+                        style.border_radius.map_or(cosmic.corner_radii.radius_m, |r| [r as f32; 4]);
+                    let radius_m = radius_m.map(|r| r.min(max_radius));
+                    let mut background =
+                        style.background.map_or(Color::from(cosmic.background.base), |[r, g, b]| {
+                            Color::from_rgb(r, g, b)
+                        });
+                    if let Some(opacity) = style.opacity {
+                        background.a = opacity.clamp(0., 1.);
+                    }
 
                     container::Style {
                         text_color: Some(cosmic.background.on.into()),
-                        background: Some(Color::from(cosmic.background.base).into()),
+                        background: Some(background.into()),
                         border: cosmic::iced::Border {
                             radius: radius_m.into(),
                             width: border_width,
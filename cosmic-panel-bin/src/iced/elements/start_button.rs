@@ -0,0 +1,124 @@
+use std::{borrow::Cow, hash::Hash, rc::Rc};
+
+use calloop::LoopHandle;
+// element for rendering the configured icon at the start of the panel
+use crate::xdg_shell_wrapper::{self, shared_state::GlobalState};
+use cosmic::{
+    iced::{Length, Padding},
+    iced_core::id,
+    theme::{self, Button},
+    widget::{button, layer_container},
+    Element,
+};
+use smithay::utils::Size;
+
+use crate::iced::{IcedElement, Program};
+
+pub type StartButtonElement = IcedElement<StartButton>;
+
+pub fn start_button_element(
+    id: id::Id,
+    icon_size: u16,
+    button_padding: Padding,
+    icon: Cow<'static, str>,
+    handle: LoopHandle<'static, GlobalState>,
+    theme: cosmic::Theme,
+    panel_id: usize,
+) -> StartButtonElement {
+    let icon_size = icon_size as f32;
+    let size = (
+        (icon_size + button_padding.horizontal()).round() as i32,
+        (icon_size + button_padding.vertical()).round() as i32,
+    );
+    IcedElement::new(
+        StartButton::new(id, icon_size.round() as u16, button_padding, icon, panel_id),
+        Size::from(size),
+        handle,
+        theme,
+        panel_id,
+        true,
+    )
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Message {
+    Activate,
+}
+
+#[derive(Debug, Clone)]
+pub struct StartButton {
+    pub id: id::Id,
+    icon_size: u16,
+    button_padding: Padding,
+    pub icon: Cow<'static, str>,
+    panel_id: usize,
+}
+
+impl StartButton {
+    pub fn new(
+        id: id::Id,
+        icon_size: u16,
+        button_padding: Padding,
+        icon: Cow<'static, str>,
+        panel_id: usize,
+    ) -> Self {
+        Self { id, icon_size, button_padding, icon, panel_id }
+    }
+}
+
+impl PartialEq for StartButton {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for StartButton {}
+
+impl Hash for StartButton {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl Program for StartButton {
+    type Message = Message;
+
+    fn update(
+        &mut self,
+        message: Self::Message,
+        loop_handle: &calloop::LoopHandle<'static, xdg_shell_wrapper::shared_state::GlobalState>,
+    ) -> cosmic::Task<Self::Message> {
+        match message {
+            Message::Activate => {
+                let panel_id = self.panel_id;
+                _ = loop_handle.insert_idle(move |state| {
+                    state.space.activate_start_button(panel_id);
+                });
+            },
+        }
+        cosmic::Task::none()
+    }
+
+    fn view(&self) -> crate::iced::Element<'_, Self::Message> {
+        Element::from(
+            button::custom(
+                layer_container(
+                    cosmic::widget::icon(cosmic::widget::icon::from_name(self.icon.clone()).into())
+                        .class(theme::Svg::Custom(Rc::new(|theme| {
+                            cosmic::iced_widget::svg::Style {
+                                color: Some(theme.cosmic().background.on.into()),
+                            }
+                        })))
+                        .width(Length::Fixed(self.icon_size as f32))
+                        .height(Length::Fixed(self.icon_size as f32)),
+                )
+                .align_x(cosmic::iced::Alignment::Center)
+                .align_y(cosmic::iced::Alignment::Center)
+                .width(Length::Fixed(self.icon_size as f32 + self.button_padding.horizontal()))
+                .height(Length::Fixed(self.icon_size as f32 + self.button_padding.vertical())),
+            )
+            .class(Button::AppletIcon)
+            .on_press(Message::Activate),
+        )
+    }
+}
@@ -1,4 +1,6 @@
-use super::{overflow_button::OverflowButtonElement, CosmicMappedInternal};
+use super::{
+    overflow_button::OverflowButtonElement, start_button::StartButtonElement, CosmicMappedInternal,
+};
 use crate::xdg_shell_wrapper::shared_state::GlobalState;
 
 use anyhow::bail;
@@ -13,6 +15,7 @@ use smithay::{
 pub enum SpaceTarget {
     Surface(WlSurface),
     OverflowButton(OverflowButtonElement),
+    StartButton(StartButtonElement),
 }
 
 impl TryFrom<CosmicMappedInternal> for SpaceTarget {
@@ -24,6 +27,7 @@ impl TryFrom<CosmicMappedInternal> for SpaceTarget {
                 Ok(SpaceTarget::Surface(w.toplevel().unwrap().wl_surface().clone()))
             },
             CosmicMappedInternal::OverflowButton(b) => Ok(SpaceTarget::OverflowButton(b)),
+            CosmicMappedInternal::StartButton(b) => Ok(SpaceTarget::StartButton(b)),
             CosmicMappedInternal::_GenericCatcher(_) => bail!("Cannot convert generic catcher"),
             CosmicMappedInternal::Background(_) => bail!("Cannot convert background"),
         }
@@ -42,11 +46,18 @@ impl From<OverflowButtonElement> for SpaceTarget {
     }
 }
 
+impl From<StartButtonElement> for SpaceTarget {
+    fn from(button: StartButtonElement) -> Self {
+        SpaceTarget::StartButton(button)
+    }
+}
+
 impl IsAlive for SpaceTarget {
     fn alive(&self) -> bool {
         match self {
             SpaceTarget::Surface(s) => s.alive(),
             SpaceTarget::OverflowButton(b) => b.alive(),
+            SpaceTarget::StartButton(b) => b.alive(),
         }
     }
 }
@@ -61,6 +72,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::enter(s, seat, data, event),
             SpaceTarget::OverflowButton(b) => PointerTarget::enter(b, seat, data, event),
+            SpaceTarget::StartButton(b) => PointerTarget::enter(b, seat, data, event),
         }
     }
 
@@ -73,6 +85,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::motion(s, seat, data, event),
             SpaceTarget::OverflowButton(b) => b.motion(seat, data, event),
+            SpaceTarget::StartButton(b) => b.motion(seat, data, event),
         }
     }
 
@@ -85,6 +98,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::relative_motion(s, seat, data, event),
             SpaceTarget::OverflowButton(b) => b.relative_motion(seat, data, event),
+            SpaceTarget::StartButton(b) => b.relative_motion(seat, data, event),
         }
     }
 
@@ -97,6 +111,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::button(s, seat, data, event),
             SpaceTarget::OverflowButton(b) => b.button(seat, data, event),
+            SpaceTarget::StartButton(b) => b.button(seat, data, event),
         }
     }
 
@@ -109,6 +124,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::axis(s, seat, data, frame),
             SpaceTarget::OverflowButton(b) => b.axis(seat, data, frame),
+            SpaceTarget::StartButton(b) => b.axis(seat, data, frame),
         }
     }
 
@@ -116,6 +132,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::frame(s, seat, data),
             SpaceTarget::OverflowButton(b) => b.frame(seat, data),
+            SpaceTarget::StartButton(b) => b.frame(seat, data),
         }
     }
 
@@ -128,6 +145,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::gesture_swipe_begin(s, seat, data, event),
             SpaceTarget::OverflowButton(b) => b.gesture_swipe_begin(seat, data, event),
+            SpaceTarget::StartButton(b) => b.gesture_swipe_begin(seat, data, event),
         }
     }
 
@@ -140,6 +158,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::gesture_swipe_update(s, seat, data, event),
             SpaceTarget::OverflowButton(b) => b.gesture_swipe_update(seat, data, event),
+            SpaceTarget::StartButton(b) => b.gesture_swipe_update(seat, data, event),
         }
     }
 
@@ -152,6 +171,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::gesture_swipe_end(s, seat, data, event),
             SpaceTarget::OverflowButton(b) => b.gesture_swipe_end(seat, data, event),
+            SpaceTarget::StartButton(b) => b.gesture_swipe_end(seat, data, event),
         }
     }
 
@@ -164,6 +184,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::gesture_pinch_begin(s, seat, data, event),
             SpaceTarget::OverflowButton(b) => b.gesture_pinch_begin(seat, data, event),
+            SpaceTarget::StartButton(b) => b.gesture_pinch_begin(seat, data, event),
         }
     }
 
@@ -176,6 +197,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::gesture_pinch_update(s, seat, data, event),
             SpaceTarget::OverflowButton(b) => b.gesture_pinch_update(seat, data, event),
+            SpaceTarget::StartButton(b) => b.gesture_pinch_update(seat, data, event),
         }
     }
 
@@ -188,6 +210,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::gesture_pinch_end(s, seat, data, event),
             SpaceTarget::OverflowButton(b) => b.gesture_pinch_end(seat, data, event),
+            SpaceTarget::StartButton(b) => b.gesture_pinch_end(seat, data, event),
         }
     }
 
@@ -200,6 +223,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::gesture_hold_begin(s, seat, data, event),
             SpaceTarget::OverflowButton(b) => b.gesture_hold_begin(seat, data, event),
+            SpaceTarget::StartButton(b) => b.gesture_hold_begin(seat, data, event),
         }
     }
 
@@ -212,6 +236,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::gesture_hold_end(s, seat, data, event),
             SpaceTarget::OverflowButton(b) => b.gesture_hold_end(seat, data, event),
+            SpaceTarget::StartButton(b) => b.gesture_hold_end(seat, data, event),
         }
     }
 
@@ -225,6 +250,7 @@ impl PointerTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => PointerTarget::leave(s, seat, data, serial, time),
             SpaceTarget::OverflowButton(b) => PointerTarget::leave(b, seat, data, serial, time),
+            SpaceTarget::StartButton(b) => PointerTarget::leave(b, seat, data, serial, time),
         }
     }
 }
@@ -240,6 +266,7 @@ impl KeyboardTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => KeyboardTarget::enter(s, seat, data, keys, serial),
             SpaceTarget::OverflowButton(b) => KeyboardTarget::enter(b, seat, data, keys, serial),
+            SpaceTarget::StartButton(b) => KeyboardTarget::enter(b, seat, data, keys, serial),
         }
     }
 
@@ -252,6 +279,7 @@ impl KeyboardTarget<GlobalState> for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => KeyboardTarget::leave(s, seat, data, serial),
             SpaceTarget::OverflowButton(b) => KeyboardTarget::leave(b, seat, data, serial),
+            SpaceTarget::StartButton(b) => KeyboardTarget::leave(b, seat, data, serial),
         }
     }
 
@@ -269,6 +297,9 @@ impl KeyboardTarget<GlobalState> for SpaceTarget {
             SpaceTarget::OverflowButton(b) => {
                 KeyboardTarget::key(b, seat, data, key, state, serial, time)
             },
+            SpaceTarget::StartButton(b) => {
+                KeyboardTarget::key(b, seat, data, key, state, serial, time)
+            },
         }
     }
 
@@ -284,6 +315,9 @@ impl KeyboardTarget<GlobalState> for SpaceTarget {
             SpaceTarget::OverflowButton(b) => {
                 KeyboardTarget::modifiers(b, seat, data, modifiers, serial)
             },
+            SpaceTarget::StartButton(b) => {
+                KeyboardTarget::modifiers(b, seat, data, modifiers, serial)
+            },
         }
     }
 }
@@ -297,6 +331,7 @@ impl WaylandFocus for SpaceTarget {
         match self {
             SpaceTarget::Surface(s) => Some(std::borrow::Cow::Borrowed(s)),
             SpaceTarget::OverflowButton(b) => b.wl_surface(),
+            SpaceTarget::StartButton(b) => b.wl_surface(),
         }
     }
 }
@@ -0,0 +1,35 @@
+// Session bus interface which lets a keyboard shortcut move the keyboard focus
+// to a panel, to navigate among its applets
+
+use smithay::reexports::calloop::channel::Sender;
+use tracing::error;
+use zbus::{connection::Builder, interface};
+
+use crate::PanelCalloopMsg;
+
+pub const KEYBOARD_FOCUS_PATH: &str = "/com/system76/CosmicPanel/KeyboardFocus";
+
+struct KeyboardFocus {
+    panel_tx: Sender<PanelCalloopMsg>,
+}
+
+#[interface(name = "com.system76.CosmicPanel.KeyboardFocus")]
+impl KeyboardFocus {
+    /// give a panel the keyboard focus to navigate among its applets, by panel
+    /// name
+    ///
+    /// An empty name focuses the first panel.
+    fn focus_panel(&self, panel: String) {
+        if let Err(err) = self.panel_tx.send(PanelCalloopMsg::FocusPanel(panel)) {
+            error!("Failed to send panel focus: {}", err);
+        }
+    }
+}
+
+/// serve the keyboard focus interface on the connection of the panel
+pub fn serve_keyboard_focus(
+    builder: Builder<'_>,
+    panel_tx: Sender<PanelCalloopMsg>,
+) -> zbus::Result<Builder<'_>> {
+    builder.serve_at(KEYBOARD_FOCUS_PATH, KeyboardFocus { panel_tx })
+}
@@ -1,22 +1,36 @@
+mod actions;
+mod applet_discovery;
+mod badges;
 mod config_watching;
+mod dbus;
+#[cfg(feature = "headless")]
+mod headless;
 mod iced;
+mod keyboard_focus;
 mod minimize;
 mod notifications;
+mod progress;
 mod space;
 mod space_container;
+mod status;
 mod xdg_shell_wrapper;
 
-use crate::xdg_shell_wrapper::{
-    client_state::ClientState, run, server_state::ServerState, shared_state::GlobalState,
+use crate::{
+    actions::emit_action,
+    xdg_shell_wrapper::{
+        client_state::ClientState, run, server_state::ServerState, shared_state::GlobalState,
+    },
 };
 use anyhow::Result;
 use calloop::channel::Sender;
 use cctk::{
     cosmic_protocols::toplevel_info::v1::client::zcosmic_toplevel_handle_v1,
-    wayland_client::protocol::wl_output::WlOutput,
+    wayland_client::{protocol::wl_output::WlOutput, Proxy},
 };
 use config_watching::{watch_config, watch_cosmic_theme};
+use cosmic::cosmic_config::CosmicConfigEntry;
 use cosmic_panel_config::CosmicPanelConfig;
+use dbus::panel_conn;
 use launch_pad::{ProcessKey, ProcessManager};
 use minimize::MinimizeApplet;
 use notifications::notifications_conn;
@@ -25,9 +39,13 @@ use std::{
     collections::HashMap,
     mem,
     os::fd::{AsRawFd, OwnedFd},
+    sync::atomic::Ordering,
     time::Duration,
 };
-use tokio::{runtime, sync::mpsc};
+use tokio::{
+    runtime,
+    sync::{mpsc, oneshot},
+};
 use tracing::{error, info, warn};
 use tracing_subscriber::{fmt, prelude::*, EnvFilter};
 
@@ -37,6 +55,87 @@ pub enum PanelCalloopMsg {
     RestartSpace(CosmicPanelConfig, WlOutput),
     MinimizeRect { output: String, applet_info: MinimizeApplet },
     UpdateToplevel(zcosmic_toplevel_handle_v1::ZcosmicToplevelHandleV1),
+    /// applets were added to or removed from the applets directory of a panel
+    AppletsChanged(String),
+    /// an applet reported the count shown in its badge
+    AppletBadge {
+        applet: String,
+        count: u32,
+    },
+    /// an applet crashed too often and is no longer restarted
+    AppletCrashed(String),
+    /// a system-wide progress was set, or cleared
+    Progress(Option<f32>),
+    /// a panel, by name, should take the keyboard focus to navigate among its
+    /// applets
+    FocusPanel(String),
+    /// the applets which are no longer restarted were requested, as the name
+    /// and output of their panel, and the name of the applet
+    CrashedApplets(oneshot::Sender<Vec<(String, String, String)>>),
+    /// the visibility of each panel was requested, as the name and output of
+    /// the panel, and its visibility
+    PanelVisibility(oneshot::Sender<Vec<(String, String, String)>>),
+    /// the geometry of each panel was requested, as the name, output and
+    /// anchor of the panel, its size, its gap and its exclusive zone
+    PanelGeometry(oneshot::Sender<Vec<(String, String, String, (i32, i32), i32, i32)>>),
+    /// the minimize targets were requested, as the output, the rectangle of
+    /// the applet, its priority and the protocol id of its surface
+    MinimizeTargets(oneshot::Sender<Vec<(String, (i32, i32, i32, i32), i32, u32)>>),
+    /// a panel, by name, should be forced to stay visible, or be let to hide
+    /// again
+    PinPanel {
+        name: String,
+        pinned: bool,
+    },
+    /// the opacity of a panel, by name, should be overridden at runtime, or
+    /// return to the configured opacity
+    PanelOpacity {
+        name: String,
+        opacity: Option<f32>,
+    },
+    /// the environment an applet of a panel, by name, was last spawned with was
+    /// requested
+    AppletEnv {
+        name: String,
+        applet: String,
+        tx: oneshot::Sender<Option<Vec<(String, String)>>>,
+    },
+    /// the additional environment of an applet of a panel, by name, was set,
+    /// to be used the next time it is spawned
+    SetAppletEnv {
+        name: String,
+        applet: String,
+        env: Vec<(String, String)>,
+    },
+    /// a panel, by name, should be reset to the default configuration
+    ResetPanel(String),
+    /// an applet of a panel, by name, should be hidden or shown again without
+    /// respawning it
+    AppletVisible {
+        name: String,
+        applet: String,
+        visible: bool,
+    },
+    /// an applet of a panel, by name, should be moved to the index of a section
+    MoveApplet {
+        name: String,
+        applet: String,
+        section: space::Alignment,
+        index: usize,
+    },
+    /// every panel should be raised above windows for the duration, e.g. for
+    /// a gesture which reveals the panels
+    BoostPanels(Duration),
+    /// the overflow popup of a section of a panel, by name, should be opened,
+    /// or closed if there is no section
+    OverflowPopup {
+        name: String,
+        section: Option<space::OverflowSection>,
+    },
+    /// the panels should be rendered offscreen and saved to the directory,
+    /// replying with the paths of the saved files
+    #[cfg(feature = "headless")]
+    SaveRenders(std::path::PathBuf, oneshot::Sender<Vec<std::path::PathBuf>>),
 }
 
 fn main() -> Result<()> {
@@ -94,6 +193,9 @@ fn main() -> Result<()> {
         },
         Err(e) => warn!("Failed to watch config: {:?}", e),
     };
+    for entry in space.config.config_list.clone() {
+        space.watch_applets_dir(&entry);
+    }
     match watch_cosmic_theme(event_loop.handle()) {
         Ok(w) => mem::forget(w),
         Err(e) => error!("Error while watching cosmic theme: {:?}", e),
@@ -139,17 +241,172 @@ fn main() -> Result<()> {
                     PanelCalloopMsg::MinimizeRect { output, applet_info } => {
                         minimize::set_rectangles(state, output, applet_info)
                     },
+                    PanelCalloopMsg::AppletBadge { applet, count } => {
+                        state.space.set_applet_badge(&applet, count);
+                    },
+                    PanelCalloopMsg::Progress(progress) => {
+                        state.space.set_progress(progress);
+                    },
+                    PanelCalloopMsg::FocusPanel(name) => {
+                        state.space.focus_panel(&name);
+                    },
+                    PanelCalloopMsg::AppletCrashed(applet) => {
+                        info!("Showing a placeholder for crashed applet {}", applet);
+                        state.space.sync_crashed_applets();
+                    },
+                    PanelCalloopMsg::CrashedApplets(tx) => {
+                        let crashed = state
+                            .space
+                            .crashed_applets()
+                            .into_iter()
+                            .map(|(panel, output, applet)| {
+                                (panel, output.unwrap_or_default(), applet)
+                            })
+                            .collect();
+                        _ = tx.send(crashed);
+                    },
+                    PanelCalloopMsg::PanelVisibility(tx) => {
+                        let visibility = state
+                            .space
+                            .panel_visibility()
+                            .into_iter()
+                            .map(|(panel, output, visibility)| {
+                                (panel, output.unwrap_or_default(), format!("{:?}", visibility))
+                            })
+                            .collect();
+                        _ = tx.send(visibility);
+                    },
+                    PanelCalloopMsg::PanelGeometry(tx) => {
+                        let geometry = state
+                            .space
+                            .panel_geometry()
+                            .into_iter()
+                            .map(|(panel, output, anchor, size, gap, exclusive_zone)| {
+                                (
+                                    panel,
+                                    output.unwrap_or_default(),
+                                    anchor.to_string(),
+                                    (size.w, size.h),
+                                    gap,
+                                    exclusive_zone,
+                                )
+                            })
+                            .collect();
+                        _ = tx.send(geometry);
+                    },
+                    PanelCalloopMsg::MinimizeTargets(tx) => {
+                        let targets = state
+                            .space
+                            .minimized_applets()
+                            .map(|(output, info)| {
+                                let rect = info.rect;
+                                (
+                                    output.to_string(),
+                                    (rect.loc.x, rect.loc.y, rect.size.w, rect.size.h),
+                                    info.priority,
+                                    info.surface.id().protocol_id(),
+                                )
+                            })
+                            .collect();
+                        _ = tx.send(targets);
+                    },
+                    PanelCalloopMsg::PinPanel { name, pinned } => {
+                        state.space.set_panel_pinned(&name, pinned);
+                    },
+                    PanelCalloopMsg::PanelOpacity { name, opacity } => {
+                        state.space.set_panel_opacity(&name, opacity);
+                    },
+                    PanelCalloopMsg::AppletEnv { name, applet, tx } => {
+                        _ = tx.send(state.space.applet_env(&name, &applet));
+                    },
+                    PanelCalloopMsg::SetAppletEnv { name, applet, env } => {
+                        state.space.set_applet_env(&name, &applet, env);
+                    },
+                    PanelCalloopMsg::ResetPanel(name) => {
+                        state.space.reset_space(&name);
+                    },
+                    PanelCalloopMsg::AppletVisible { name, applet, visible } => {
+                        state.space.set_applet_visible(&name, &applet, visible);
+                    },
+                    PanelCalloopMsg::MoveApplet { name, applet, section, index } => {
+                        state.space.move_applet_section(&name, &applet, section, index);
+                    },
+                    PanelCalloopMsg::BoostPanels(duration) => {
+                        state.space.boost_panel_layers(duration);
+                    },
+                    PanelCalloopMsg::OverflowPopup { name, section: Some(section) } => {
+                        let Some(seat) = state.server_state.seats.first() else {
+                            return;
+                        };
+                        let c_seat = (seat.client.last_pointer_press.0, seat.client._seat.clone());
+                        state.space.open_overflow_popup(
+                            &name,
+                            section,
+                            &state.client_state.compositor_state,
+                            state.client_state.fractional_scaling_manager.as_ref(),
+                            state.client_state.viewporter_state.as_ref(),
+                            &state.client_state.queue_handle,
+                            &mut state.client_state.xdg_shell_state,
+                            c_seat,
+                        );
+                    },
+                    PanelCalloopMsg::OverflowPopup { name, section: None } => {
+                        state.space.close_overflow_popup(&name);
+                    },
+                    #[cfg(feature = "headless")]
+                    PanelCalloopMsg::SaveRenders(dir, tx) => {
+                        _ = tx.send(state.space.save_offscreen_renders(&dir));
+                    },
+                    PanelCalloopMsg::AppletsChanged(name) => {
+                        // reload the entry so that removed applets are dropped
+                        let entry = match CosmicPanelConfig::cosmic_config(&name) {
+                            Ok(helper) => match CosmicPanelConfig::get_entry(&helper) {
+                                Ok(entry) | Err((_, entry)) => Some(entry),
+                            },
+                            Err(err) => {
+                                error!("Failed to load cosmic config: {:?}", err);
+                                state
+                                    .space
+                                    .config
+                                    .config_list
+                                    .iter()
+                                    .find(|c| c.name == name)
+                                    .cloned()
+                            },
+                        };
+                        if let Some(entry) = entry {
+                            state.space.update_space(
+                                entry,
+                                &state.client_state.compositor_state,
+                                state.client_state.fractional_scaling_manager.as_ref(),
+                                state.client_state.viewporter_state.as_ref(),
+                                &mut state.client_state.layer_state,
+                                &state.client_state.queue_handle,
+                                None,
+                                state.client_state.overlap_notify.clone(),
+                            );
+                        }
+                    },
                 },
                 calloop::channel::Event::Closed => {},
             };
         })
         .expect("failed to insert dbus event source");
 
+    let panel_conn_tx = calloop_tx.clone();
     std::thread::spawn(move || -> anyhow::Result<()> {
         let rt = runtime::Builder::new_current_thread().enable_all().build()?;
         let mut process_ids: HashMap<String, Vec<ProcessKey>> = HashMap::new();
 
         rt.block_on(async move {
+            let panel_conn = match panel_conn(panel_conn_tx).await {
+                Ok(conn) => Some(conn),
+                Err(err) => {
+                    error!("Failed to serve the panel on the session bus {:?}", err);
+                    None
+                },
+            };
+
             let process_manager = ProcessManager::new().await;
             let _ = process_manager
                 .set_restart_mode(launch_pad::RestartMode::ExponentialBackoff(
@@ -240,6 +497,32 @@ fn main() -> Result<()> {
                             let _ = process_manager.stop_process(id).await;
                         }
                     },
+                    space::AppletMsg::RunCommand(cmd) => {
+                        let mut command = tokio::process::Command::new("sh");
+                        command.arg("-c").arg(&cmd);
+                        // only the panel and its applets fall back to software
+                        if space::SOFTWARE_RENDERING.load(Ordering::SeqCst) {
+                            command.env_remove("LIBGL_ALWAYS_SOFTWARE");
+                        }
+                        match command.spawn() {
+                            Ok(mut child) => {
+                                // reap the child when it exits
+                                tokio::spawn(async move {
+                                    let _ = child.wait().await;
+                                });
+                            },
+                            Err(err) => error!("Failed to run click command {cmd}: {err}"),
+                        }
+                    },
+                    space::AppletMsg::EmitSignal { panel, name } => {
+                        let Some(conn) = panel_conn.as_ref() else {
+                            warn!("Can't emit signal {name} without a session bus connection");
+                            continue;
+                        };
+                        if let Err(err) = emit_action(conn, &panel, &name).await {
+                            error!("Failed to emit signal {name}: {err}");
+                        }
+                    },
                     space::AppletMsg::NeedNewNotificationFd(sender) => {
                         let Some(proxy) = notifications_proxy.as_mut() else {
                             warn!("Can't start notifications applet without a connection");
@@ -1,15 +1,21 @@
 use crate::xdg_shell_wrapper::shared_state::GlobalState;
 use cctk::{
     cosmic_protocols::toplevel_info::v1::client::zcosmic_toplevel_handle_v1,
-    wayland_client::{protocol::wl_surface::WlSurface, Proxy},
+    wayland_client::{
+        protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+        Proxy,
+    },
 };
 use smithay::utils::{Logical, Rectangle};
+use std::{collections::HashMap, time::Instant};
 
 #[derive(Debug, Clone)]
 pub struct MinimizeApplet {
     pub priority: i32,
     pub rect: Rectangle<i32, Logical>,
     pub surface: WlSurface,
+    /// when the applet reported its rectangle
+    pub updated: Instant,
 }
 
 pub fn update_toplevel(
@@ -19,19 +25,14 @@ pub fn update_toplevel(
     let Some(toplevel_mngr) = state.client_state.toplevel_manager_state.as_ref() else {
         return;
     };
-    let minimized_applets = &state.space.minimized_applets;
     let Some((_, toplevel_info)) = state.space.toplevels.iter().find(|t| &t.0 == &toplevel) else {
         return;
     };
-
-    if let Some((_, info)) = minimized_applets.iter().find(|(output_name, _)| {
-        toplevel_info.output.iter().any(|o| {
-            let Some(i) = state.client_state.output_state.info(o) else {
-                return false;
-            };
-            i.name.as_ref() == Some(output_name)
-        })
-    }) {
+    let outputs = output_names(state, &toplevel_info.output);
+    if let Some(info) = target_for_outputs(&state.space.minimized_applets, &outputs) {
+        if state.space.minimize_excluded(&info.surface, &toplevel_info.app_id) {
+            return;
+        }
         toplevel_mngr.manager.set_rectangle(
             &toplevel,
             &info.surface,
@@ -43,8 +44,79 @@ pub fn update_toplevel(
     }
 }
 
+/// names of the given outputs
+fn output_names<'a>(
+    state: &GlobalState,
+    outputs: impl IntoIterator<Item = &'a WlOutput>,
+) -> Vec<String> {
+    outputs.into_iter().filter_map(|o| state.client_state.output_state.info(o)?.name).collect()
+}
+
+/// the minimize target sent to a new toplevel, or one of a reconnected
+/// client, on the given outputs
+fn target_for_outputs<'a, T>(targets: &'a HashMap<String, T>, outputs: &[String]) -> Option<&'a T> {
+    outputs.iter().find_map(|o| targets.get(o))
+}
+
+/// the toplevels the minimize target of `output` is re-sent to when it
+/// changed, those on the output which are not excluded from it
+///
+/// Each toplevel comes with the names of its outputs and whether it is
+/// excluded.
+fn rebroadcast_to<T>(
+    toplevels: impl IntoIterator<Item = (T, Vec<String>, bool)>,
+    output: &str,
+) -> Vec<T> {
+    toplevels
+        .into_iter()
+        .filter(|(_, outputs, excluded)| !excluded && outputs.iter().any(|o| o == output))
+        .map(|(toplevel, ..)| toplevel)
+        .collect()
+}
+
+/// Change of the minimize target of an output when an applet reports its
+/// rectangle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TargetUpdate {
+    /// the current target is kept as it is
+    Keep,
+    /// the current target moved or was resized, e.g. after a relayout
+    Moved,
+    /// the reporting applet replaces the current target
+    Replace,
+}
+
+fn target_update(
+    same_surface: bool,
+    cur_alive: bool,
+    (cur_priority, cur_updated): (i32, Instant),
+    cur_rect: Rectangle<i32, Logical>,
+    (priority, updated): (i32, Instant),
+    rect: Rectangle<i32, Logical>,
+) -> TargetUpdate {
+    if same_surface {
+        if cur_rect != rect {
+            TargetUpdate::Moved
+        } else {
+            TargetUpdate::Keep
+        }
+    } else if !cur_alive
+        || cur_priority < priority
+        || (cur_priority == priority && cur_updated < updated)
+    {
+        TargetUpdate::Replace
+    } else {
+        TargetUpdate::Keep
+    }
+}
+
+/// Update the minimize target for an output.
+///
+/// Only one applet per output is used as the minimize target. The highest
+/// priority applet with a live surface wins, and if priorities are equal the
+/// most recently updated applet wins. A target whose surface is no longer
+/// alive is always replaced.
 pub fn set_rectangles(state: &mut GlobalState, output: String, info: MinimizeApplet) {
-    // if surface matches cur & is different, or is higher priority, replace
     let mut changed = false;
     let minimized_applets = &mut state.space.minimized_applets;
 
@@ -54,30 +126,46 @@ pub fn set_rectangles(state: &mut GlobalState, output: String, info: MinimizeApp
     });
 
     if !changed {
-        if old_info.surface == info.surface && old_info.rect != info.rect {
-            old_info.rect = info.rect;
-            changed = true;
-        } else if old_info.priority < info.priority || !old_info.surface.is_alive() {
-            *old_info = info.clone();
-            changed = true;
+        let same_surface = old_info.surface == info.surface;
+        if same_surface {
+            old_info.priority = info.priority;
+            old_info.updated = info.updated;
+        }
+        match target_update(
+            same_surface,
+            old_info.surface.is_alive(),
+            (old_info.priority, old_info.updated),
+            old_info.rect,
+            (info.priority, info.updated),
+            info.rect,
+        ) {
+            TargetUpdate::Keep => {},
+            TargetUpdate::Moved => {
+                old_info.rect = info.rect;
+                changed = true;
+            },
+            TargetUpdate::Replace => {
+                *old_info = info.clone();
+                changed = true;
+            },
         }
     }
 
     // if changed, send rect for all toplevels for the given out
     if changed {
+        let info = old_info.clone();
         let Some(toplevel_mngr) = state.client_state.toplevel_manager_state.as_ref() else {
             return;
         };
 
-        for (toplevel, toplevel_info) in &mut state.space.toplevels {
-            if !toplevel_info.output.iter().any(|o| {
-                let Some(i) = state.client_state.output_state.info(o) else {
-                    return false;
-                };
-                i.name.as_ref() == Some(&output)
-            }) {
-                continue;
-            }
+        let toplevels = state.space.toplevels.iter().map(|(toplevel, toplevel_info)| {
+            (
+                toplevel,
+                output_names(state, &toplevel_info.output),
+                state.space.minimize_excluded(&info.surface, &toplevel_info.app_id),
+            )
+        });
+        for toplevel in rebroadcast_to(toplevels, &output) {
             toplevel_mngr.manager.set_rectangle(
                 toplevel,
                 &info.surface,
@@ -89,3 +177,99 @@ pub fn set_rectangles(state: &mut GlobalState, output: String, info: MinimizeApp
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn rect(x: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, 0), (32, 32))
+    }
+
+    /// an instant the given number of seconds after the first update
+    fn at(secs: u64) -> Instant {
+        thread_local!(static START: Instant = Instant::now());
+        START.with(|start| *start + Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn moving_the_target_updates_it() {
+        assert_eq!(
+            target_update(true, true, (0, at(0)), rect(0), (0, at(1)), rect(40)),
+            TargetUpdate::Moved
+        );
+    }
+
+    #[test]
+    fn unchanged_target_is_kept() {
+        assert_eq!(
+            target_update(true, true, (0, at(0)), rect(0), (0, at(1)), rect(0)),
+            TargetUpdate::Keep
+        );
+    }
+
+    #[test]
+    fn higher_priority_applet_replaces_target() {
+        assert_eq!(
+            target_update(false, true, (0, at(1)), rect(0), (1, at(0)), rect(40)),
+            TargetUpdate::Replace
+        );
+        assert_eq!(
+            target_update(false, true, (1, at(0)), rect(0), (0, at(1)), rect(40)),
+            TargetUpdate::Keep
+        );
+    }
+
+    #[test]
+    fn priority_tie_prefers_most_recent_update() {
+        assert_eq!(
+            target_update(false, true, (1, at(0)), rect(0), (1, at(1)), rect(40)),
+            TargetUpdate::Replace
+        );
+        assert_eq!(
+            target_update(false, true, (1, at(1)), rect(0), (1, at(0)), rect(40)),
+            TargetUpdate::Keep
+        );
+    }
+
+    #[test]
+    fn dead_target_is_replaced() {
+        assert_eq!(
+            target_update(false, false, (1, at(1)), rect(0), (0, at(0)), rect(40)),
+            TargetUpdate::Replace
+        );
+        assert_eq!(
+            target_update(false, false, (1, at(1)), rect(0), (1, at(0)), rect(40)),
+            TargetUpdate::Replace
+        );
+    }
+
+    #[test]
+    fn moved_target_is_rebroadcast_to_toplevels_on_its_output() {
+        assert_eq!(
+            target_update(true, true, (0, at(0)), rect(0), (0, at(1)), rect(40)),
+            TargetUpdate::Moved
+        );
+        let toplevels = [
+            ("on DP-1", vec!["DP-1".to_string()], false),
+            ("on HDMI-A-1", vec!["HDMI-A-1".to_string()], false),
+            ("excluded", vec!["DP-1".to_string()], true),
+            ("on both", vec!["HDMI-A-1".to_string(), "DP-1".to_string()], false),
+        ];
+        assert_eq!(rebroadcast_to(toplevels, "DP-1"), ["on DP-1", "on both"]);
+    }
+
+    #[test]
+    fn new_toplevels_are_sent_the_current_target() {
+        let targets = HashMap::from([("DP-1".to_string(), rect(40))]);
+        // a toplevel which is new, or whose client reconnected
+        assert_eq!(
+            target_for_outputs(&targets, &["HDMI-A-1".to_string(), "DP-1".to_string()]),
+            Some(&rect(40))
+        );
+        assert_eq!(target_for_outputs(&targets, &["HDMI-A-1".to_string()]), None);
+        assert_eq!(target_for_outputs(&targets, &[]), None);
+    }
+}
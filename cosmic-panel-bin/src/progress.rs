@@ -0,0 +1,42 @@
+// Session bus interface which lets other components show a system-wide
+// progress along the edge of the panels
+
+use smithay::reexports::calloop::channel::Sender;
+use tracing::error;
+use zbus::{connection::Builder, interface};
+
+use crate::PanelCalloopMsg;
+
+pub const PROGRESS_PATH: &str = "/com/system76/CosmicPanel/Progress";
+
+struct Progress {
+    panel_tx: Sender<PanelCalloopMsg>,
+}
+
+#[interface(name = "com.system76.CosmicPanel.Progress")]
+impl Progress {
+    /// set the progress shown along the edge of the panels, from 0.0 to 1.0
+    fn set_progress(&self, progress: f64) {
+        if !progress.is_finite() {
+            return;
+        }
+        if let Err(err) = self.panel_tx.send(PanelCalloopMsg::Progress(Some(progress as f32))) {
+            error!("Failed to send progress: {}", err);
+        }
+    }
+
+    /// stop showing the progress
+    fn clear_progress(&self) {
+        if let Err(err) = self.panel_tx.send(PanelCalloopMsg::Progress(None)) {
+            error!("Failed to send progress: {}", err);
+        }
+    }
+}
+
+/// serve the progress interface on the connection of the panel
+pub fn serve_progress(
+    builder: Builder<'_>,
+    panel_tx: Sender<PanelCalloopMsg>,
+) -> zbus::Result<Builder<'_>> {
+    builder.serve_at(PROGRESS_PATH, Progress { panel_tx })
+}
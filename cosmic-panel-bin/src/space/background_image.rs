@@ -0,0 +1,131 @@
+// Panel background drawn from an image with 9-slice scaling
+
+use std::path::{Path, PathBuf};
+
+use smithay::{
+    backend::{
+        allocator::Fourcc,
+        renderer::{
+            element::{
+                memory::{MemoryRenderBuffer, MemoryRenderBufferRenderElement},
+                Kind,
+            },
+            gles::GlesRenderer,
+        },
+    },
+    utils::{Buffer, Physical, Point, Rectangle, Size, Transform},
+};
+
+#[derive(Debug)]
+pub struct NineSliceBackground {
+    pub path: PathBuf,
+    /// top, right, bottom, left insets in pixels of the source image
+    pub insets: [u32; 4],
+    /// the slices of the source image, row by row from the top left
+    slices: Vec<Option<tiny_skia::Pixmap>>,
+    source_size: (u32, u32),
+    buffer: Option<(Size<i32, Buffer>, MemoryRenderBuffer)>,
+}
+
+impl NineSliceBackground {
+    pub fn load(path: &Path, insets: [u32; 4]) -> anyhow::Result<Self> {
+        let source = tiny_skia::Pixmap::load_png(path)?;
+        let (w, h) = (source.width(), source.height());
+        let [top, right, bottom, left] = insets;
+        if left + right > w || top + bottom > h {
+            anyhow::bail!("Background image insets are larger than the image");
+        }
+        let xs = [0, left, w - right, w];
+        let ys = [0, top, h - bottom, h];
+        let mut slices = Vec::with_capacity(9);
+        for row in 0..3 {
+            for col in 0..3 {
+                let rect = tiny_skia::IntRect::from_xywh(
+                    xs[col] as i32,
+                    ys[row] as i32,
+                    xs[col + 1] - xs[col],
+                    ys[row + 1] - ys[row],
+                );
+                slices.push(rect.and_then(|r| source.clone_rect(r)));
+            }
+        }
+        Ok(Self { path: path.to_path_buf(), insets, slices, source_size: (w, h), buffer: None })
+    }
+
+    /// draw the slices into a buffer of the given size
+    /// corners keep their size, edges are stretched along the edge, and the
+    /// center is stretched in both directions
+    fn draw(&self, size: Size<i32, Buffer>) -> Option<MemoryRenderBuffer> {
+        let mut pixmap = tiny_skia::Pixmap::new(size.w as u32, size.h as u32)?;
+        let [top, right, bottom, left] = self.insets.map(|i| i as f32);
+        let (src_w, src_h) = (self.source_size.0 as f32, self.source_size.1 as f32);
+        let (dst_w, dst_h) = (size.w as f32, size.h as f32);
+        // shrink the corners if the target is smaller than the insets
+        let x_scale = (dst_w / (left + right)).min(1.0);
+        let y_scale = (dst_h / (top + bottom)).min(1.0);
+        let dst_xs = [0.0, left * x_scale, dst_w - right * x_scale, dst_w];
+        let dst_ys = [0.0, top * y_scale, dst_h - bottom * y_scale, dst_h];
+        let src_ws = [left, src_w - left - right, right];
+        let src_hs = [top, src_h - top - bottom, bottom];
+
+        for row in 0..3 {
+            for col in 0..3 {
+                let Some(slice) = self.slices[row * 3 + col].as_ref() else {
+                    continue;
+                };
+                let sx = (dst_xs[col + 1] - dst_xs[col]) / src_ws[col];
+                let sy = (dst_ys[row + 1] - dst_ys[row]) / src_hs[row];
+                if !sx.is_finite() || !sy.is_finite() || sx <= 0.0 || sy <= 0.0 {
+                    continue;
+                }
+                pixmap.draw_pixmap(
+                    0,
+                    0,
+                    slice.as_ref(),
+                    &tiny_skia::PixmapPaint {
+                        quality: tiny_skia::FilterQuality::Bilinear,
+                        ..Default::default()
+                    },
+                    tiny_skia::Transform::from_row(sx, 0.0, 0.0, sy, dst_xs[col], dst_ys[row]),
+                    None,
+                );
+            }
+        }
+
+        let mut buffer =
+            MemoryRenderBuffer::new(Fourcc::Abgr8888, size, 1, Transform::Normal, None);
+        _ = buffer.render().draw(|buf| {
+            buf.copy_from_slice(pixmap.data());
+            Result::<_, ()>::Ok(vec![Rectangle::from_loc_and_size((0, 0), size)])
+        });
+        Some(buffer)
+    }
+
+    /// get a render element for the background at the given physical location
+    /// and size
+    pub fn element(
+        &mut self,
+        renderer: &mut GlesRenderer,
+        loc: Point<i32, Physical>,
+        size: Size<i32, Physical>,
+    ) -> Option<MemoryRenderBufferRenderElement<GlesRenderer>> {
+        let size: Size<i32, Buffer> = (size.w, size.h).into();
+        if size.w <= 0 || size.h <= 0 {
+            return None;
+        }
+        if self.buffer.as_ref().map_or(true, |(s, _)| *s != size) {
+            self.buffer = self.draw(size).map(|b| (size, b));
+        }
+        let (_, buffer) = self.buffer.as_ref()?;
+        MemoryRenderBufferRenderElement::from_buffer(
+            renderer,
+            loc.to_f64(),
+            buffer,
+            None,
+            None,
+            None,
+            Kind::Unspecified,
+        )
+        .ok()
+    }
+}
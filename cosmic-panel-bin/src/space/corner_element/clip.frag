@@ -0,0 +1,64 @@
+// Title: Texture clipped to a rounded rectangle
+#version 100
+
+//_DEFINES_
+
+#if defined(EXTERNAL)
+#extension GL_OES_EGL_image_external : require
+#endif
+
+precision mediump float;
+#if defined(EXTERNAL)
+uniform samplerExternalOES tex;
+#else
+uniform sampler2D tex;
+#endif
+
+uniform float alpha;
+varying vec2 v_coords;
+
+#if defined(DEBUG_FLAGS)
+uniform float tint;
+#endif
+
+// radius of the corners of the applet in physical pixels
+uniform float radius;
+// 0.0 is grayscale, 1.0 is the original color
+uniform float saturation;
+// 1.0 along the axes which are mirrored
+uniform vec2 mirror;
+// rectangle of the element in the texture, normalized
+uniform vec4 src;
+// rectangle the element is drawn at, relative to the applet in physical pixels
+uniform vec4 geo;
+// size of the applet in physical pixels
+uniform vec2 applet;
+
+void main() {
+    // the element is drawn at its reflection in the applet, so it samples the
+    // texture at the reflection of the fragment
+    vec2 pos = geo.xy + (v_coords - src.xy) / src.zw * geo.zw;
+    vec2 origin = mix(geo.xy, applet - geo.xy - geo.zw, mirror);
+    vec2 coords = src.xy + (mix(pos, applet - pos, mirror) - origin) / geo.zw * src.zw;
+    vec4 color = texture2D(tex, coords);
+
+#if defined(NO_ALPHA)
+    color = vec4(color.rgb, 1.0);
+#endif
+
+    float luma = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    color = vec4(mix(vec3(luma), color.rgb, saturation), color.a);
+
+    // the whole applet is clipped, rather than each of its elements
+    vec2 half_size = applet / 2.0;
+    vec2 q = abs(pos - half_size) - half_size + radius;
+    float dist = length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+    color = color * alpha * (1.0 - smoothstep(-0.5, 0.5, dist));
+
+#if defined(DEBUG_FLAGS)
+    if (tint == 1.0)
+        color = vec4(0.0, 0.3, 0.0, 0.2) + color * 0.8;
+#endif
+
+    gl_FragColor = color;
+}
@@ -6,17 +6,61 @@ use smithay::{
         gles::{
             element::PixelShaderElement,
             ffi::{BLEND, FUNC_ADD, SRC_ALPHA, ZERO},
-            GlesError, GlesFrame, GlesPixelProgram, GlesRenderer, Uniform, UniformName,
-            UniformType,
+            GlesError, GlesFrame, GlesPixelProgram, GlesRenderer, GlesTexProgram, Uniform,
+            UniformName, UniformType,
         },
     },
     utils::{Buffer, Logical, Physical, Rectangle},
 };
 
+use super::desaturate::AppletShading;
+
 pub static RECTANGLE_SHADER: &str = include_str!("./shader.frag");
+pub static ROUNDED_CLIP_SHADER: &str = include_str!("./clip.frag");
 
 pub struct RoundedRectangleShader(pub GlesPixelProgram);
 
+/// texture program which masks an element with a rounded rectangle
+pub struct RoundedClipShader(pub GlesTexProgram);
+
+impl RoundedClipShader {
+    pub fn get(renderer: &GlesRenderer) -> GlesTexProgram {
+        renderer
+            .egl_context()
+            .user_data()
+            .get::<RoundedClipShader>()
+            .expect("Custom Shaders not initialized")
+            .0
+            .clone()
+    }
+
+    /// draw with the texture program replaced by the clipping program, which
+    /// also desaturates and mirrors the element in its applet
+    ///
+    /// The clip is the rounded bounding box of the applet, so cropped
+    /// elements and subsurfaces are only rounded at the corners of the applet.
+    ///
+    /// `dst` is the mirrored location of the element, from its `geometry`.
+    pub fn draw_with<T>(
+        frame: &mut GlesFrame<'_>,
+        program: &GlesTexProgram,
+        shading: AppletShading,
+        radius: f32,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        draw: impl FnOnce(&mut GlesFrame<'_>) -> Result<T, GlesError>,
+    ) -> Result<T, GlesError> {
+        let size = shading.bbox.size;
+        let radius = radius.min(size.w as f32 / 2.).min(size.h as f32 / 2.);
+        let mut uniforms = shading.uniforms(src, dst);
+        uniforms.push(Uniform::new("radius", radius));
+        frame.override_default_tex_program(program.clone(), uniforms);
+        let res = draw(frame);
+        frame.clear_tex_program_override();
+        res
+    }
+}
+
 #[derive(Default, Debug, Clone, Copy, PartialEq)]
 pub struct RoundedRectangleSettings {
     pub rad_tl: f32,
@@ -31,6 +75,24 @@ pub struct RoundedRectangleSettings {
     pub border_color: [f32; 4],
 }
 
+impl RoundedRectangleSettings {
+    /// scale the physical coordinates of the settings by a factor
+    pub fn scaled(mut self, factor: f32) -> Self {
+        if factor == 1. {
+            return self;
+        }
+        self.rad_tl *= factor;
+        self.rad_tr *= factor;
+        self.rad_bl *= factor;
+        self.rad_br *= factor;
+        self.loc = self.loc.map(|v| v * factor);
+        self.rect_size = self.rect_size.map(|v| v * factor);
+        self.border_width *= factor;
+        self.drop_shadow *= factor;
+        self
+    }
+}
+
 pub struct RoundedRectangleShaderElement(PixelShaderElement);
 
 impl RoundedRectangleShader {
@@ -117,8 +179,18 @@ pub fn init_shaders(gles_renderer: &mut GlesRenderer) -> Result<(), GlesError> {
         ],
     )?;
 
+    let clip_shader = gles_renderer.compile_custom_texture_shader(ROUNDED_CLIP_SHADER, &[
+        UniformName::new("radius", UniformType::_1f),
+        UniformName::new("saturation", UniformType::_1f),
+        UniformName::new("mirror", UniformType::_2f),
+        UniformName::new("src", UniformType::_4f),
+        UniformName::new("geo", UniformType::_4f),
+        UniformName::new("applet", UniformType::_2f),
+    ])?;
+
     let egl_context = gles_renderer.egl_context();
     egl_context.user_data().insert_if_missing(|| RoundedRectangleShader(rectangle_shader));
+    egl_context.user_data().insert_if_missing(|| RoundedClipShader(clip_shader));
 
     Ok(())
 }
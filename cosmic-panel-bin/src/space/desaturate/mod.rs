@@ -0,0 +1,146 @@
+use smithay::{
+    backend::renderer::gles::{
+        GlesError, GlesFrame, GlesRenderer, GlesTexProgram, Uniform, UniformName, UniformType,
+    },
+    utils::{Buffer, Physical, Rectangle, Size},
+};
+
+pub static DESATURATE_SHADER: &str = include_str!("./shader.frag");
+
+pub struct DesaturateShader(pub GlesTexProgram);
+
+/// how the elements of an applet are drawn by the custom texture programs
+///
+/// Elements may be cropped or be subsurfaces, so the programs which mirror an
+/// applet locate each element in the applet instead of assuming it covers the
+/// whole texture.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AppletShading {
+    /// 0.0 is grayscale, 1.0 is the original color
+    pub saturation: f32,
+    /// 1.0 along the axes which are mirrored
+    pub mirror: [f32; 2],
+    /// bounding box of the applet in output space
+    pub bbox: Rectangle<i32, Physical>,
+    /// size of the buffer the element samples
+    pub buffer_size: Size<i32, Buffer>,
+}
+
+impl AppletShading {
+    /// reflect a rectangle in the applet along the mirrored axes
+    pub fn mirrored(&self, mut rect: Rectangle<i32, Physical>) -> Rectangle<i32, Physical> {
+        if self.mirror[0] == 1. {
+            rect.loc.x = 2 * self.bbox.loc.x + self.bbox.size.w - rect.loc.x - rect.size.w;
+        }
+        if self.mirror[1] == 1. {
+            rect.loc.y = 2 * self.bbox.loc.y + self.bbox.size.h - rect.loc.y - rect.size.h;
+        }
+        rect
+    }
+
+    /// uniforms for an element drawn from `src` to `dst`
+    ///
+    /// The rectangle the element is drawn at is relative to the applet, which
+    /// keeps the values small enough for `mediump` precision.
+    pub fn uniforms(
+        &self,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+    ) -> Vec<Uniform<'static>> {
+        let buffer = self.buffer_size.to_f64();
+        let src = [
+            (src.loc.x / buffer.w) as f32,
+            (src.loc.y / buffer.h) as f32,
+            (src.size.w / buffer.w) as f32,
+            (src.size.h / buffer.h) as f32,
+        ];
+        let geo = [
+            (dst.loc.x - self.bbox.loc.x) as f32,
+            (dst.loc.y - self.bbox.loc.y) as f32,
+            dst.size.w as f32,
+            dst.size.h as f32,
+        ];
+        vec![
+            Uniform::new("saturation", self.saturation.clamp(0., 1.)),
+            Uniform::new("mirror", self.mirror),
+            Uniform::new("src", src),
+            Uniform::new("geo", geo),
+            Uniform::new("applet", [self.bbox.size.w as f32, self.bbox.size.h as f32]),
+        ]
+    }
+}
+
+impl DesaturateShader {
+    pub fn get(renderer: &GlesRenderer) -> GlesTexProgram {
+        renderer
+            .egl_context()
+            .user_data()
+            .get::<DesaturateShader>()
+            .expect("Custom Shaders not initialized")
+            .0
+            .clone()
+    }
+
+    /// draw with the texture program replaced by the desaturating program,
+    /// which also mirrors the element in its applet
+    ///
+    /// `dst` is the mirrored location of the element, from its `geometry`.
+    pub fn draw_with<T>(
+        frame: &mut GlesFrame<'_>,
+        program: &GlesTexProgram,
+        shading: AppletShading,
+        src: Rectangle<f64, Buffer>,
+        dst: Rectangle<i32, Physical>,
+        draw: impl FnOnce(&mut GlesFrame<'_>) -> Result<T, GlesError>,
+    ) -> Result<T, GlesError> {
+        frame.override_default_tex_program(program.clone(), shading.uniforms(src, dst));
+        let res = draw(frame);
+        frame.clear_tex_program_override();
+        res
+    }
+}
+
+pub fn init_shaders(gles_renderer: &mut GlesRenderer) -> Result<(), GlesError> {
+    {
+        let egl_context = gles_renderer.egl_context();
+        if egl_context.user_data().get::<DesaturateShader>().is_some() {
+            return Ok(());
+        }
+    }
+
+    let desaturate_shader = gles_renderer.compile_custom_texture_shader(DESATURATE_SHADER, &[
+        UniformName::new("saturation", UniformType::_1f),
+        UniformName::new("mirror", UniformType::_2f),
+        UniformName::new("src", UniformType::_4f),
+        UniformName::new("geo", UniformType::_4f),
+        UniformName::new("applet", UniformType::_2f),
+    ])?;
+
+    let egl_context = gles_renderer.egl_context();
+    egl_context.user_data().insert_if_missing(|| DesaturateShader(desaturate_shader));
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elements_are_reflected_in_their_applet() {
+        let shading = AppletShading {
+            saturation: 1.,
+            mirror: [1., 0.],
+            bbox: Rectangle::from_loc_and_size((100, 0), (40, 32)),
+            buffer_size: (40, 32).into(),
+        };
+        // a subsurface at the left edge of the applet moves to its right edge
+        let subsurface = Rectangle::from_loc_and_size((100, 4), (10, 8));
+        assert_eq!(shading.mirrored(subsurface), Rectangle::from_loc_and_size((130, 4), (10, 8)));
+        // the whole applet stays in place
+        assert_eq!(shading.mirrored(shading.bbox), shading.bbox);
+
+        let shading = AppletShading { mirror: [0., 0.], ..shading };
+        assert_eq!(shading.mirrored(subsurface), subsurface);
+    }
+}
@@ -14,6 +14,7 @@ use crate::{
                 self, overflow_button_element, OverflowButton, OverflowButtonElement,
             },
             overflow_popup::{overflow_popup_element, BORDER_WIDTH},
+            start_button::{start_button_element, StartButtonElement},
             CosmicMappedInternal, PopupMappedInternal,
         },
         IcedElement,
@@ -24,27 +25,45 @@ use crate::{
 };
 
 use super::{
-    panel_space::{ClientShrinkSize, PanelClient},
+    panel_space::{ClientShrinkSize, Clients, PanelClient},
     PanelSpace,
 };
 use crate::xdg_shell_wrapper::space::WrapperSpace;
-use anyhow::bail;
 use cosmic::widget::Id;
-use cosmic_panel_config::PanelAnchor;
+use cosmic_panel_config::{AppletAlignment, BorderRadius, CosmicPanelConfig, PanelAnchor};
 use itertools::{chain, Itertools};
-use sctk::shell::WaylandSurface;
+use sctk::{compositor::Region, shell::WaylandSurface};
 use smithay::{
     desktop::{space::SpaceElement, Space, Window},
-    reexports::wayland_server::Resource,
-    utils::{IsAlive, Physical, Rectangle, Size},
+    reexports::wayland_server::{backend::ClientId, Resource},
+    utils::{IsAlive, Logical, Physical, Point, Rectangle, Size},
     wayland::{
         compositor::with_states, fractional_scale::with_fractional_scale, seat::WaylandFocus,
     },
 };
-use tracing::info;
+use tracing::{error, info};
+
+/// result of laying out the applets of a panel
+#[derive(Debug)]
+pub(crate) enum LayoutOutcome {
+    /// the applets were laid out
+    Ok,
+    /// the panel must be resized to the given size before the applets are
+    /// laid out
+    NeedsResize(Size<i32, Logical>),
+    /// applets were shrunk or moved to an overflow popup to make them fit,
+    /// and must be laid out again
+    NeedsRelayout,
+    /// the applets could not be laid out
+    Failed(anyhow::Error),
+}
+
+/// instance id of a crashed applet, with the number of applet windows before
+/// its slot in its section
+type CrashedSlot = (usize, String);
 
 impl PanelSpace {
-    pub(crate) fn layout_(&mut self) -> anyhow::Result<()> {
+    pub(crate) fn layout_(&mut self) -> LayoutOutcome {
         self.remap_attempts = self.remap_attempts.saturating_sub(1);
 
         let make_indices_contiguous = |windows: &mut Vec<(usize, Window, Option<u32>)>| {
@@ -57,6 +76,7 @@ impl PanelSpace {
         let mut left_overflow_button = None;
         let mut right_overflow_button = None;
         let mut center_overflow_button = None;
+        let mut start_button = None;
 
         let to_map = self
             .space
@@ -89,12 +109,41 @@ impl PanelSpace {
                         right_overflow_button = Some(b);
                         return None;
                     },
+                    CosmicMappedInternal::StartButton(b) => {
+                        start_button = Some(b);
+                        return None;
+                    },
                     _ => return None,
                 };
 
                 w.alive().then_some(w)
             })
             .collect_vec();
+        let start_button = self.sync_start_button(start_button);
+        let left_overflow_button =
+            self.sync_collapse_toggle(OverflowSection::Left, left_overflow_button);
+        let center_overflow_button =
+            self.sync_collapse_toggle(OverflowSection::Center, center_overflow_button);
+        let right_overflow_button =
+            self.sync_collapse_toggle(OverflowSection::Right, right_overflow_button);
+
+        // hidden applets keep running, but are neither laid out nor rendered
+        let hidden_clients = self.hidden_applet_clients();
+        let (to_map, hidden): (Vec<_>, Vec<_>) = to_map.into_iter().partition(|w| {
+            !w.toplevel()
+                .and_then(|t| t.wl_surface().client())
+                .is_some_and(|c| hidden_clients.contains(&c.id()))
+        });
+        for w in hidden {
+            self.space.unmap_elem(&CosmicMappedInternal::Window(w.clone()));
+            self.hidden_windows.push(w);
+        }
+        self.prune_applet_moves(&to_map);
+        // mirrors leave overflow to the mirrored panel, which owns the applets
+        let is_mirror = self.mirror_of.is_some();
+        if !is_mirror && (self.collapse_applets(&to_map) || self.cap_visible_applets(&to_map)) {
+            return LayoutOutcome::NeedsRelayout;
+        }
 
         let is_dock = !self.config.expand_to_edges()
             || self.animate_state.as_ref().is_some_and(|a| !(a.cur.expanded > 0.5));
@@ -115,6 +164,7 @@ impl PanelSpace {
                 })
             })
             .collect_vec();
+        let mut crashed_left = self.crashed_slots(&self.clients_left, &windows_left);
         make_indices_contiguous(&mut windows_left);
 
         let mut windows_center = to_map
@@ -134,6 +184,7 @@ impl PanelSpace {
                 })
             })
             .collect_vec();
+        let mut crashed_center = self.crashed_slots(&self.clients_center, &windows_center);
         make_indices_contiguous(&mut windows_center);
 
         let mut windows_right = to_map
@@ -153,9 +204,16 @@ impl PanelSpace {
                 })
             })
             .collect_vec();
+        let mut crashed_right = self.crashed_slots(&self.clients_right, &windows_right);
         make_indices_contiguous(&mut windows_right);
 
         if is_dock {
+            let (left_len, center_len) = (windows_left.len(), windows_center.len());
+            crashed_center = crashed_left
+                .drain(..)
+                .chain(crashed_center.into_iter().map(|(i, name)| (i + left_len, name)))
+                .chain(crashed_right.drain(..).map(|(i, name)| (i + left_len + center_len, name)))
+                .collect_vec();
             windows_center = windows_left
                 .drain(..)
                 .chain(windows_center)
@@ -170,9 +228,14 @@ impl PanelSpace {
             left_overflow_button,
             right_overflow_button,
             center_overflow_button,
+            start_button,
+            [crashed_left, crashed_center, crashed_right],
         );
-        if let Err(e) = res.as_ref() {
-            info!("Requires relayout: {:?}", e);
+        match &res {
+            LayoutOutcome::Ok => self.reposition_reactive_popups(false),
+            LayoutOutcome::NeedsResize(size) => info!("Requires resize: {:?}", size),
+            LayoutOutcome::NeedsRelayout => info!("Requires relayout"),
+            LayoutOutcome::Failed(err) => error!("Failed to lay out the panel: {:?}", err),
         }
         res
     }
@@ -185,7 +248,9 @@ impl PanelSpace {
         mut left_overflow_button: Option<OverflowButtonElement>,
         mut right_overflow_button: Option<OverflowButtonElement>,
         mut center_overflow_button: Option<OverflowButtonElement>,
-    ) -> anyhow::Result<()> {
+        start_button: Option<StartButtonElement>,
+        mut crashed: [Vec<CrashedSlot>; 3],
+    ) -> LayoutOutcome {
         self.space.refresh();
         let mut bg_color = self.bg_color();
         for c in 0..3 {
@@ -213,23 +278,26 @@ impl PanelSpace {
             }
         }
 
+        // the start button leads the left section, or the center section of a dock
+        let start_in_center = start_button.is_some() && is_dock;
         let has_sides = !windows_left.is_empty()
             || !windows_right.is_empty()
+            || !crashed[0].is_empty()
+            || !crashed[2].is_empty()
             || left_overflow_button.is_some()
-            || right_overflow_button.is_some();
-        let mut num_lists: u32 = 0;
-        if has_sides {
-            num_lists += 2;
-        }
-        let has_center = !windows_center.is_empty() || center_overflow_button.is_some();
-        if has_center {
-            num_lists += 1;
-        }
+            || right_overflow_button.is_some()
+            || start_button.is_some() && !is_dock;
+        let has_center = !windows_center.is_empty()
+            || !crashed[1].is_empty()
+            || center_overflow_button.is_some()
+            || start_in_center;
+        let num_lists = section_count(has_sides, has_center);
 
         fn map_fn(
             (i, w, _): &(usize, Window, Option<u32>),
             anchor: PanelAnchor,
             alignment: Alignment,
+            reserved: Option<u32>,
         ) -> (Alignment, usize, i32, i32, i32) {
             let (mut size, mut suggested_bounds) = w
                 .toplevel()
@@ -257,6 +325,20 @@ impl PanelSpace {
                 suggested_bounds.h = size.h;
             }
 
+            // an applet which draws nothing inline may still keep a slot
+            if let Some(reserved) = reserved.map(|r| r as i32) {
+                match anchor {
+                    PanelAnchor::Left | PanelAnchor::Right => {
+                        size.h = size.h.max(reserved);
+                        suggested_bounds.h = suggested_bounds.h.max(reserved);
+                    },
+                    PanelAnchor::Top | PanelAnchor::Bottom => {
+                        size.w = size.w.max(reserved);
+                        suggested_bounds.w = suggested_bounds.w.max(reserved);
+                    },
+                }
+            }
+
             match anchor {
                 PanelAnchor::Left | PanelAnchor::Right => {
                     (alignment, *i, size.h, size.w, suggested_bounds.h.min(size.h))
@@ -267,10 +349,32 @@ impl PanelSpace {
             }
         }
 
-        let left = windows_left.iter().map(|e| {
-            let l = map_fn(e, anchor, Alignment::Left);
-            l
+        let start_button_len = start_button.as_ref().map(|b| {
+            let size = b.bbox().size.to_f64();
+            if self.config.is_horizontal() {
+                size.w
+            } else {
+                size.h
+            }
         });
+        let start_button_len_scaled = start_button_len.map(|l| l + spacing_scaled);
+
+        // applets thicker than the panel may be cropped or scaled down
+        let map_fitted = |e: &(usize, Window, Option<u32>), alignment: Alignment| {
+            let reserved = e.1.toplevel().and_then(|t| self.reserved_slot(t));
+            let (alignment, i, length, thickness, suggested_length) =
+                map_fn(e, anchor, alignment, reserved);
+            let (thickness, scale) = self.config.fit_applet_thickness(thickness as f64);
+            (
+                alignment,
+                i,
+                (length as f64 * scale).round() as i32,
+                thickness.round() as i32,
+                (suggested_length as f64 * scale).round() as i32,
+            )
+        };
+
+        let left = windows_left.iter().map(|e| map_fitted(e, Alignment::Left));
 
         let left_sum_scaled =
             left.clone().map(|(_, _, _, _, suggested_length)| suggested_length).sum::<i32>() as f64
@@ -284,8 +388,11 @@ impl PanelSpace {
         } else {
             left_sum_scaled
         };
+        let left_sum_scaled = left_sum_scaled
+            + start_button_len_scaled.filter(|_| !start_in_center).unwrap_or_default()
+            + self.crashed_slots_len(&crashed[0], windows_left.len()) * self.scale;
 
-        let center = windows_center.iter().map(|e| map_fn(e, anchor, Alignment::Center));
+        let center = windows_center.iter().map(|e| map_fitted(e, Alignment::Center));
         let center_sum_scaled =
             center.clone().map(|(_, _, _, _, suggested_length)| suggested_length).sum::<i32>()
                 as f64
@@ -299,8 +406,11 @@ impl PanelSpace {
         } else {
             center_sum_scaled
         };
+        let center_sum_scaled = center_sum_scaled
+            + start_button_len_scaled.filter(|_| start_in_center).unwrap_or_default()
+            + self.crashed_slots_len(&crashed[1], windows_center.len()) * self.scale;
 
-        let right = windows_right.iter().map(|e| map_fn(e, anchor, Alignment::Right));
+        let right = windows_right.iter().map(|e| map_fitted(e, Alignment::Right));
         let right_sum_scaled =
             right.clone().map(|(_, _, _length, _, suggested_length)| suggested_length).sum::<i32>()
                 as f64
@@ -314,6 +424,8 @@ impl PanelSpace {
         } else {
             right_sum_scaled
         };
+        let right_sum_scaled = right_sum_scaled
+            + self.crashed_slots_len(&crashed[2], windows_right.len()) * self.scale;
 
         let total_sum_scaled = left_sum_scaled + center_sum_scaled + right_sum_scaled;
         let new_list_length = (total_sum_scaled
@@ -383,17 +495,18 @@ impl PanelSpace {
         self.container_length = container_length;
         let container_lengthwise_pos = (new_list_dim_length - container_length) / 2;
 
-        let mut center_pos = layer_major as f64 / 2. - center_sum / 2.;
+        let cutout = self.cutout();
+        let cutout_len = cutout.map(|(start, end)| end - start).unwrap_or_default();
+        // the center section is split around the cutout
+        let mut center_pos = layer_major as f64 / 2. - center_sum / 2. - cutout_len / 2.;
 
-        let left_pos = container_lengthwise_pos as f64 + padding_u32 as f64;
+        let mut left_pos = container_lengthwise_pos as f64 + padding_u32 as f64;
         let mut right_pos = new_list_dim_length as f64
             - container_lengthwise_pos as f64
             - right_sum
             - padding_u32 as f64;
 
-        let one_third = (layer_major as f64 - (spacing_u32 * num_lists.saturating_sub(1)) as f64)
-            / (3.min(num_lists) as f64);
-        let one_half = layer_major as f64 / (2.min(num_lists) as f64);
+        let (one_third, one_half) = even_section_lengths(layer_major, spacing_u32, num_lists);
         let larger_side = left_sum.max(right_sum);
 
         let mut target_center_len =
@@ -403,6 +516,7 @@ impl PanelSpace {
         } else {
             target_center_len -= spacing_u32 as f64;
         }
+        target_center_len = (target_center_len - cutout_len).max(0.);
         let target_left_len = if !has_center {
             (layer_major as f64
                 - right_sum.min(one_half)
@@ -438,15 +552,18 @@ impl PanelSpace {
             * self.scale) as i32;
 
         let center_overflow = (center_sum - target_center_len) as i32;
-        if center_overflow < suggested_size {
+        if is_mirror {
+            // the mirrored panel's overflow is shown as is
+        } else if center_overflow < suggested_size {
             // check if it can be expanded
             self.relax_overflow_center(center_overflow.unsigned_abs(), &mut center_overflow_button)
         } else if center_overflow > 0 {
             let overflow = self.shrink_center((center_sum - target_center_len) as u32);
-            bail!("overflow: {}", overflow)
+            info!("center overflow: {}", overflow);
+            return LayoutOutcome::NeedsRelayout;
         }
 
-        if !is_dock && self.animate_state.is_none() {
+        if !is_dock && !is_mirror && self.animate_state.is_none() {
             let left_overflow = (left_sum - target_left_len) as i32;
 
             if left_overflow < suggested_size {
@@ -454,7 +571,8 @@ impl PanelSpace {
             } else if left_overflow > 0 {
                 info!("target: {target_left_len}, actual: {left_sum}");
                 let overflow = self.shrink_left(left_overflow as u32);
-                bail!("left overflow: {} {}", left_overflow, overflow)
+                info!("left overflow: {} {}", left_overflow, overflow);
+                return LayoutOutcome::NeedsRelayout;
             }
 
             let right_overflow = (right_sum - target_right_len) as i32;
@@ -465,7 +583,8 @@ impl PanelSpace {
                 );
             } else if right_overflow > 0 {
                 let overflow = self.shrink_right(right_overflow as u32);
-                bail!("right overflow: {} {}", right_overflow, overflow)
+                info!("right overflow: {} {}", right_overflow, overflow);
+                return LayoutOutcome::NeedsRelayout;
             }
         }
 
@@ -477,14 +596,12 @@ impl PanelSpace {
 
         // must use logical coordinates for layout here
 
-        fn center_in_bar(crosswise_dim: u32, dim: u32) -> i32 {
-            (crosswise_dim as i32 - dim as i32) / 2
-        }
+        let alignment = self.config.applet_alignment;
 
         if new_list_thickness_dim != list_cross {
             self.pending_dimensions = Some(new_dim);
             self.is_dirty = true;
-            anyhow::bail!("resizing list");
+            return LayoutOutcome::NeedsResize(new_dim);
         }
         // offset for centering
         let margin_offset = match anchor {
@@ -492,14 +609,28 @@ impl PanelSpace {
             PanelAnchor::Bottom | PanelAnchor::Right => 0,
         } as i32;
 
+        self.section_dividers = self.section_divider_rects(
+            [(left_pos, left_sum), (center_pos, center_sum + cutout_len), (right_pos, right_sum)],
+            margin_offset,
+            new_logical_crosswise_dim,
+        );
+
         if let Some(right_button) = right_overflow_button {
             let size = right_button.bbox().size.to_f64();
             let crosswise_pos = if self.config.is_horizontal() {
                 margin_offset
-                    + center_in_bar(new_logical_crosswise_dim.try_into().unwrap(), size.h as u32)
+                    + align_in_bar(
+                        alignment,
+                        new_logical_crosswise_dim.try_into().unwrap(),
+                        size.h as u32,
+                    )
             } else {
                 margin_offset
-                    + center_in_bar(new_logical_crosswise_dim.try_into().unwrap(), size.w as u32)
+                    + align_in_bar(
+                        alignment,
+                        new_logical_crosswise_dim.try_into().unwrap(),
+                        size.w as u32,
+                    )
             };
 
             let loc = if self.config().is_horizontal() {
@@ -511,14 +642,49 @@ impl PanelSpace {
             self.space.map_element(CosmicMappedInternal::OverflowButton(right_button), loc, true);
         };
 
+        if let Some((start_button, len)) = start_button.zip(start_button_len) {
+            let size = start_button.bbox().size.to_f64();
+            let crosswise_pos = if self.config.is_horizontal() {
+                margin_offset
+                    + align_in_bar(
+                        alignment,
+                        new_logical_crosswise_dim.try_into().unwrap(),
+                        size.h as u32,
+                    )
+            } else {
+                margin_offset
+                    + align_in_bar(
+                        alignment,
+                        new_logical_crosswise_dim.try_into().unwrap(),
+                        size.w as u32,
+                    )
+            };
+            let pos = if start_in_center { &mut center_pos } else { &mut left_pos };
+            let loc = if self.config().is_horizontal() {
+                (pos.round() as i32, crosswise_pos)
+            } else {
+                (crosswise_pos, pos.round() as i32)
+            };
+            *pos += len + spacing_u32 as f64;
+            self.space.map_element(CosmicMappedInternal::StartButton(start_button), loc, false);
+        }
+
         if let Some(center_button) = center_overflow_button {
             let size = center_button.bbox().size.to_f64();
             let crosswise_pos = if self.config.is_horizontal() {
                 margin_offset
-                    + center_in_bar(new_logical_crosswise_dim.try_into().unwrap(), size.h as u32)
+                    + align_in_bar(
+                        alignment,
+                        new_logical_crosswise_dim.try_into().unwrap(),
+                        size.h as u32,
+                    )
             } else {
                 margin_offset
-                    + center_in_bar(new_logical_crosswise_dim.try_into().unwrap(), size.w as u32)
+                    + align_in_bar(
+                        alignment,
+                        new_logical_crosswise_dim.try_into().unwrap(),
+                        size.w as u32,
+                    )
             };
             let loc = if self.config().is_horizontal() {
                 (center_pos.round() as i32, crosswise_pos)
@@ -529,10 +695,17 @@ impl PanelSpace {
             center_pos += size.h + spacing_u32 as f64;
         }
 
+        let crosswise_dim = new_logical_crosswise_dim;
         let mut map_windows = |windows: IterMut<'_, (usize, Window, Option<u32>)>,
-                               mut prev|
+                               mut prev,
+                               crashed: &[CrashedSlot]|
          -> f64 {
-            for (_, w, minimize_priority) in windows {
+            let mut crashed = crashed.iter().peekable();
+            for (pos, (index, w, minimize_priority)) in windows.enumerate() {
+                // placeholders of crashed applets keep their slots
+                while let Some((_, name)) = crashed.next_if(|(before, _)| *before <= pos) {
+                    prev = self.place_crashed_applet(name, prev, crosswise_dim, margin_offset);
+                }
                 // XXX this is a hack to get the logical size of the window
                 // TODO improve how this is done
                 let mut size = w.bbox().size.to_f64();
@@ -544,6 +717,32 @@ impl PanelSpace {
                 if configured_size.h != 0 {
                     size.h = size.h.min(configured_size.h as f64);
                 }
+                if let Some(reserved) = w.toplevel().and_then(|t| self.reserved_slot(t)) {
+                    if self.config.is_horizontal() {
+                        size.w = size.w.max(reserved as f64);
+                    } else {
+                        size.h = size.h.max(reserved as f64);
+                    }
+                }
+                let (thickness, scale) =
+                    self.config.fit_applet_thickness(if self.config.is_horizontal() {
+                        size.h
+                    } else {
+                        size.w
+                    });
+                size = size.upscale(scale);
+                if self.config.is_horizontal() {
+                    size.h = thickness;
+                } else {
+                    size.w = thickness;
+                }
+                // skip over the cutout
+                let len = if self.config.is_horizontal() { size.w } else { size.h };
+                if let Some((start, end)) = cutout {
+                    if prev < end && prev + len > start {
+                        prev = end + spacing_u32 as f64;
+                    }
+                }
                 let cur: f64 = prev;
                 let (x, y);
 
@@ -551,18 +750,21 @@ impl PanelSpace {
                     let cur = (
                         cur,
                         margin_offset
-                            + center_in_bar(
+                            + align_in_bar(
+                                alignment,
                                 new_logical_crosswise_dim.try_into().unwrap(),
                                 size.h as u32,
                             ),
                     );
                     (x, y) = (cur.0 as i32, cur.1);
                     prev += size.w + spacing_u32 as f64;
+                    self.track_applet_move(*index, w, (x, y).into());
                     self.space.map_element(CosmicMappedInternal::Window(w.clone()), (x, y), false);
                 } else {
                     let cur = (
                         margin_offset
-                            + center_in_bar(
+                            + align_in_bar(
+                                alignment,
                                 new_logical_crosswise_dim.try_into().unwrap(),
                                 size.w as u32,
                             ),
@@ -570,13 +772,11 @@ impl PanelSpace {
                     );
                     (x, y) = (cur.0, cur.1 as i32);
                     prev += size.h + spacing_u32 as f64;
+                    self.track_applet_move(*index, w, (x, y).into());
                     self.space.map_element(CosmicMappedInternal::Window(w.clone()), (x, y), false);
                 }
                 if minimize_priority.is_some() {
-                    let new_rect = Rectangle {
-                        loc: (x, y).into(),
-                        size: ((size.w.ceil() as i32).max(1), (size.w.ceil() as i32).max(1)).into(),
-                    };
+                    let new_rect = self.minimize_rect((x, y).into(), size);
                     if new_rect != self.minimize_applet_rect
                         && Instant::now().duration_since(self.last_minimize_update)
                             > Duration::from_secs(1)
@@ -590,28 +790,44 @@ impl PanelSpace {
                                 priority: if is_dock { 1 } else { 0 },
                                 rect: new_rect,
                                 surface: layer.wl_surface().clone(),
+                                updated: self.last_minimize_update,
                             },
                         });
                     }
                 }
             }
+            for (_, name) in crashed {
+                prev = self.place_crashed_applet(name, prev, crosswise_dim, margin_offset);
+            }
             prev
         };
-        let left_pos = map_windows(windows_left.iter_mut(), left_pos);
+        if self.config.center_outward {
+            order_center_outward(&mut windows_center, &mut crashed[1]);
+        }
+        let [crashed_left, crashed_center, crashed_right] = &crashed;
+        let left_pos = map_windows(windows_left.iter_mut(), left_pos, crashed_left);
 
         // will be already offset if dock
-        map_windows(windows_center.iter_mut(), center_pos);
+        map_windows(windows_center.iter_mut(), center_pos, crashed_center);
 
-        map_windows(windows_right.iter_mut(), right_pos);
+        map_windows(windows_right.iter_mut(), right_pos, crashed_right);
         // if there is a left overflow_button, map it
         if let Some(left_button) = left_overflow_button {
             let size = left_button.bbox().size.to_f64();
             let crosswise_pos = if self.config.is_horizontal() {
                 margin_offset
-                    + center_in_bar(new_logical_crosswise_dim.try_into().unwrap(), size.h as u32)
+                    + align_in_bar(
+                        alignment,
+                        new_logical_crosswise_dim.try_into().unwrap(),
+                        size.h as u32,
+                    )
             } else {
                 margin_offset
-                    + center_in_bar(new_logical_crosswise_dim.try_into().unwrap(), size.w as u32)
+                    + align_in_bar(
+                        alignment,
+                        new_logical_crosswise_dim.try_into().unwrap(),
+                        size.w as u32,
+                    )
             };
             let loc = if self.config().is_horizontal() {
                 (left_pos.round() as i32, crosswise_pos)
@@ -620,6 +836,7 @@ impl PanelSpace {
             };
             self.space.map_element(CosmicMappedInternal::OverflowButton(left_button), loc, false);
         }
+        self.fit_fractional_rounding(new_dim);
         self.space.refresh();
 
         let mut panel_size = self.actual_size.to_f64().to_physical(self.scale);
@@ -639,77 +856,90 @@ impl PanelSpace {
         } else {
             (new_dim.w, new_dim.h)
         };
-        if !self.background_element.as_ref().is_some_and(|e| {
+        let gap_scaled = self.gap() as f64 * self.scale;
+        // a full radius follows the new size of the panel, not the previous one
+        let border_radius = match (self.animate_state.as_ref(), self.config.border_radius) {
+            (None, BorderRadius::Full) => panel_size.w.min(panel_size.h) / 2.,
+            _ => self.border_radius() as f64 * self.scale,
+        };
+
+        let border_radius = border_radius.min(panel_size.w / 2.).min(panel_size.h / 2.);
+        let (rad_tl, rad_tr, rad_bl, rad_br) = match (self.config.anchor, self.gap()) {
+            (PanelAnchor::Right, 0) => (border_radius, 0., border_radius, 0.),
+            (PanelAnchor::Left, 0) => (0., border_radius, 0., border_radius),
+            (PanelAnchor::Bottom, 0) => (border_radius, border_radius, 0., 0.),
+            (PanelAnchor::Top, 0) => (0., 0., border_radius, border_radius),
+            _ => (border_radius, border_radius, border_radius, border_radius),
+        };
+
+        let anim_gap_scaled = self.anchor_gap as f32 * self.scale as f32;
+        let loc = match self.config.anchor {
+            PanelAnchor::Left => {
+                [gap_scaled as f32 + anim_gap_scaled, container_lengthwise_pos_scaled]
+            },
+            PanelAnchor::Right => [-anim_gap_scaled, container_lengthwise_pos_scaled],
+            PanelAnchor::Top => [container_lengthwise_pos_scaled, -anim_gap_scaled],
+            PanelAnchor::Bottom => {
+                [container_lengthwise_pos_scaled, gap_scaled as f32 + anim_gap_scaled]
+            },
+        };
+        self.panel_rect_settings = RoundedRectangleSettings {
+            rad_tl: rad_tl as f32,
+            rad_tr: rad_tr as f32,
+            rad_bl: rad_bl as f32,
+            rad_br: rad_br as f32,
+            loc,
+            rect_size: [panel_size.w as f32, panel_size.h as f32],
+            border_width: 0.0,
+            drop_shadow: 0.0,
+            bg_color: [0.0, 0.0, 0.0, 1.0],
+            border_color: [0.0, 0.0, 0.0, 0.0],
+        };
+
+        let loc = match self.config.anchor {
+            PanelAnchor::Left => [gap as f32, container_lengthwise_pos as f32],
+            PanelAnchor::Right => [0., container_lengthwise_pos as f32],
+            PanelAnchor::Bottom => [container_lengthwise_pos as f32, 0.],
+            PanelAnchor::Top => [container_lengthwise_pos as f32, gap as f32],
+        };
+
+        let border_radius = self.border_radius().min(w as u32).min(h as u32) as f32 / 2.;
+        let radius = match (self.config.anchor, self.gap()) {
+            (PanelAnchor::Right, 0) => [border_radius, 0., 0., border_radius],
+            (PanelAnchor::Left, 0) => [0., border_radius, border_radius, 0.],
+            (PanelAnchor::Bottom, 0) => [border_radius, border_radius, 0., 0.],
+            (PanelAnchor::Top, 0) => [0., 0., border_radius, border_radius],
+            _ => [border_radius; 4],
+        };
+        // the background is only rebuilt if one of its inputs changed, as
+        // rendering its rounded corners is expensive
+        let bg_color = self.bg_color();
+        let cached = self.background_element.as_ref().is_some_and(|e| {
             e.with_program(|p| {
-                p.logical_height == h && p.logical_width == w && self.bg_color() == p.color
+                p.logical_height == h
+                    && p.logical_width == w
+                    && p.radius == radius
+                    && p.logical_pos == (loc[0].round() as i32, loc[1].round() as i32)
+                    && p.color == bg_color
+                    && p.border == self.config.border
             })
-        }) || self.animate_state.as_ref().is_some()
-            || matches!(
-                self.visibility,
-                Visibility::TransitionToHidden { .. } | Visibility::TransitionToVisible { .. }
-            )
+        });
+        if !cached
+            || !self.config.cache_background
+                && (self.animate_state.as_ref().is_some()
+                    || matches!(
+                        self.visibility,
+                        Visibility::TransitionToHidden { .. }
+                            | Visibility::TransitionToVisible { .. }
+                            | Visibility::TransitionToPeek { .. }
+                            | Visibility::TransitionFromPeek { .. }
+                    ))
         {
             if let Some(bg) = self.background_element.take() {
                 self.space.unmap_elem(&CosmicMappedInternal::Background(bg));
             }
-            let gap_scaled = self.gap() as f64 * self.scale;
-            let border_radius = self.border_radius() as f64 * self.scale;
-
-            let border_radius = border_radius.min(panel_size.w / 2.).min(panel_size.h / 2.);
-            let (rad_tl, rad_tr, rad_bl, rad_br) = match (self.config.anchor, self.gap()) {
-                (PanelAnchor::Right, 0) => (border_radius, 0., border_radius, 0.),
-                (PanelAnchor::Left, 0) => (0., border_radius, 0., border_radius),
-                (PanelAnchor::Bottom, 0) => (border_radius, border_radius, 0., 0.),
-                (PanelAnchor::Top, 0) => (0., 0., border_radius, border_radius),
-                _ => (border_radius, border_radius, border_radius, border_radius),
-            };
-
-            let anim_gap_scaled = self.anchor_gap as f32 * self.scale as f32;
-            let loc = match self.config.anchor {
-                PanelAnchor::Left => {
-                    [gap_scaled as f32 + anim_gap_scaled, container_lengthwise_pos_scaled]
-                },
-                PanelAnchor::Right => [-anim_gap_scaled, container_lengthwise_pos_scaled],
-                PanelAnchor::Top => [container_lengthwise_pos_scaled, -anim_gap_scaled],
-                PanelAnchor::Bottom => {
-                    [container_lengthwise_pos_scaled, gap_scaled as f32 + anim_gap_scaled]
-                },
-            };
-            self.panel_rect_settings = RoundedRectangleSettings {
-                rad_tl: rad_tl as f32,
-                rad_tr: rad_tr as f32,
-                rad_bl: rad_bl as f32,
-                rad_br: rad_br as f32,
-                loc,
-                rect_size: [panel_size.w as f32, panel_size.h as f32],
-                border_width: 0.0,
-                drop_shadow: 0.0,
-                bg_color: [0.0, 0.0, 0.0, 1.0],
-                border_color: [0.0, 0.0, 0.0, 0.0],
-            };
-
             let Some(output) = self.output.as_ref().map(|o| o.1.clone()) else {
-                bail!("output missing");
-            };
-            let loc = match self.config.anchor {
-                PanelAnchor::Left => [gap as f32, container_lengthwise_pos as f32],
-                PanelAnchor::Right => [0., container_lengthwise_pos as f32],
-                PanelAnchor::Bottom => [container_lengthwise_pos as f32, 0.],
-                PanelAnchor::Top => [container_lengthwise_pos as f32, gap as f32],
-            };
-
-            let border_radius = self.border_radius().min(w as u32).min(h as u32) as f32 / 2.;
-            let radius = match (self.config.anchor, self.gap()) {
-                (PanelAnchor::Right, 0) => [border_radius as f32, 0., 0., border_radius as f32],
-                (PanelAnchor::Left, 0) => [0., border_radius as f32, border_radius as f32, 0.],
-                (PanelAnchor::Bottom, 0) => [border_radius as f32, border_radius as f32, 0., 0.],
-                (PanelAnchor::Top, 0) => [0., 0., border_radius as f32, border_radius as f32],
-                _ => [
-                    border_radius as f32,
-                    border_radius as f32,
-                    border_radius as f32,
-                    border_radius as f32,
-                ],
+                return LayoutOutcome::Failed(anyhow::anyhow!("output missing"));
             };
             let bg = background_element(
                 Id::new("panel_bg"),
@@ -720,7 +950,8 @@ impl PanelSpace {
                 self.colors.theme.clone(),
                 self.space.id(),
                 loc,
-                self.bg_color(),
+                bg_color,
+                self.config.border,
             );
             bg.output_enter(&output, Rectangle::default());
             self.background_element = Some(bg.clone());
@@ -729,13 +960,19 @@ impl PanelSpace {
         input_region.subtract(0, 0, i32::MAX, i32::MAX);
         let anim_gap = self.anchor_gap;
 
-        if is_dock {
+        if self.config.click_through || self.config.hidden_while_empty() {
+            // leave the input region empty
+        } else if is_dock {
             let (layer_length, actual_length) = if self.config.is_horizontal() {
                 (new_dim.w, self.actual_size.w)
             } else {
                 (new_dim.h, self.actual_size.h)
             };
             let side = (layer_length as u32 - actual_length as u32) / 2;
+            // keep the dock revealed until the pointer is past the hysteresis
+            let hysteresis = self.config.get_hide_hysteresis().unwrap_or_default().min(side);
+            let side = side - hysteresis;
+            let container_length = container_length + 2 * hysteresis as i32;
 
             let (loc, size) = match self.config.anchor {
                 PanelAnchor::Left => (
@@ -763,6 +1000,7 @@ impl PanelSpace {
             };
 
             input_region.add(loc.0, loc.1, size.0, size.1);
+            self.round_input_corners(input_region, loc, size, new_logical_crosswise_dim);
         } else {
             let (loc, size) = match self.config.anchor {
                 PanelAnchor::Left => ((-1, 0), (new_dim.w + 1 + anim_gap, new_dim.h)),
@@ -772,14 +1010,292 @@ impl PanelSpace {
             };
 
             input_region.add(loc.0, loc.1, size.0, size.1);
+            self.round_input_corners(input_region, loc, size, new_logical_crosswise_dim);
         };
+        if let Some((start, end)) = cutout {
+            let (start, len) = (start.floor() as i32, (end - start).ceil() as i32);
+            if self.config.is_horizontal() {
+                input_region.subtract(start, i32::MIN / 2, len, i32::MAX);
+            } else {
+                input_region.subtract(i32::MIN / 2, start, i32::MAX, len);
+            }
+        }
+        // clicks pass through mirrors to whatever is below them
+        if self.mirror_of.is_some() {
+            input_region.subtract(i32::MIN / 2, i32::MIN / 2, i32::MAX, i32::MAX);
+        }
         layer.wl_surface().set_input_region(Some(input_region.wl_region()));
 
+        // let the compositor skip drawing what is below an opaque panel
+        if let Some(opaque_region) = self.opaque_region.as_ref() {
+            opaque_region.subtract(0, 0, i32::MAX, i32::MAX);
+            if self.is_fully_opaque() {
+                opaque_region.add(0, 0, new_dim.w, new_dim.h);
+                layer.wl_surface().set_opaque_region(Some(opaque_region.wl_region()));
+            } else {
+                layer.wl_surface().set_opaque_region(None);
+            }
+        }
+
         self.reorder_overflow_space(OverflowSection::Left);
         self.reorder_overflow_space(OverflowSection::Center);
         self.reorder_overflow_space(OverflowSection::Right);
 
-        Ok(())
+        LayoutOutcome::Ok
+    }
+
+    /// instance ids of the crashed applets in a section, with the number of
+    /// applet windows which come before each of them
+    fn crashed_slots(
+        &self,
+        clients: &Clients,
+        windows: &[(usize, Window, Option<u32>)],
+    ) -> Vec<CrashedSlot> {
+        if self.crashed_applets.is_empty() {
+            return Vec::new();
+        }
+        clients
+            .lock()
+            .unwrap()
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| self.crashed_applets.contains_key(&c.instance_id()))
+            .map(|(i, c)| (windows.iter().filter(|(j, ..)| *j < i).count(), c.instance_id()))
+            .collect()
+    }
+
+    /// logical length taken by the placeholders of crashed applets in a
+    /// section with the given number of applet windows
+    fn crashed_slots_len(&self, slots: &[CrashedSlot], windows: usize) -> f64 {
+        let len: i32 = slots
+            .iter()
+            .filter_map(|(_, name)| self.crashed_applets.get(name))
+            .map(|(p, _)| {
+                let size = p.with_program(|p| p.size);
+                if self.config.is_horizontal() {
+                    size.w
+                } else {
+                    size.h
+                }
+            })
+            .sum();
+        let spacings = (slots.len() + windows).saturating_sub(1) - windows.saturating_sub(1);
+        len as f64 + (spacings as u32 * self.config.spacing()) as f64
+    }
+
+    /// place the placeholder of a crashed applet at the given lengthwise
+    /// position, returning the position after it
+    fn place_crashed_applet(
+        &mut self,
+        id: &str,
+        pos: f64,
+        crosswise_dim: i32,
+        crosswise_pos: i32,
+    ) -> f64 {
+        let Some((placeholder, loc)) = self.crashed_applets.get_mut(id) else {
+            return pos;
+        };
+        let size = placeholder.with_program(|p| p.size);
+        let spacing = self.config.spacing() as f64;
+        let alignment = self.config.applet_alignment;
+        if self.config.is_horizontal() {
+            let offset = align_in_bar(alignment, crosswise_dim as u32, size.h as u32);
+            *loc = (pos.round() as i32, crosswise_pos + offset).into();
+            pos + size.w as f64 + spacing
+        } else {
+            let offset = align_in_bar(alignment, crosswise_dim as u32, size.w as u32);
+            *loc = (crosswise_pos + offset, pos.round() as i32).into();
+            pos + size.h as f64 + spacing
+        }
+    }
+
+    /// square minimized windows animate into, for a minimize applet at the
+    /// given location
+    ///
+    /// The square is centered on the applet. Its side is the configured
+    /// minimize target size, or the crosswise size of the applet.
+    fn minimize_rect(
+        &self,
+        loc: Point<i32, Logical>,
+        size: Size<f64, Logical>,
+    ) -> Rectangle<i32, Logical> {
+        let crosswise = if self.config.is_horizontal() { size.h } else { size.w };
+        let side = self.config.minimize_target_size.map_or(crosswise, |s| s as f64).ceil().max(1.);
+        let center = loc.to_f64() + Point::from((size.w / 2., size.h / 2.));
+        Rectangle::from_loc_and_size(
+            (center - Point::from((side / 2., side / 2.))).to_i32_round(),
+            (side as i32, side as i32),
+        )
+    }
+
+    /// move the applets and buttons which end past the panel after rounding
+    /// their placement to physical pixels back within it
+    ///
+    /// At a fractional scale, the location and size of an element are rounded
+    /// separately, so the last element may end a physical pixel past the end
+    /// of the panel and be clipped. The trailing run of elements is moved
+    /// together, so none of them is moved over the one before it.
+    fn fit_fractional_rounding(&mut self, dim: Size<i32, Logical>) {
+        let scale = self.scale;
+        if !self.config.fractional_compensation || scale.fract() == 0. {
+            return;
+        }
+        let is_horizontal = self.config.is_horizontal();
+        let major_physical = |s: Size<i32, Physical>| if is_horizontal { s.w } else { s.h };
+        let major = |s: Size<i32, Logical>| if is_horizontal { s.w } else { s.h };
+        let start = |p: Point<i32, Logical>| if is_horizontal { p.x } else { p.y };
+        let limit = major_physical(dim.to_f64().to_physical(scale).to_i32_round());
+        let mut elements = self
+            .space
+            .elements()
+            .filter(|e| {
+                matches!(
+                    e,
+                    CosmicMappedInternal::Window(_)
+                        | CosmicMappedInternal::OverflowButton(_)
+                        | CosmicMappedInternal::StartButton(_)
+                )
+            })
+            .filter_map(|e| Some((e.clone(), self.space.element_location(e)?, e.bbox().size)))
+            .collect_vec();
+        elements.sort_by_key(|(_, loc, _)| start(*loc));
+
+        let overshoot = elements
+            .iter()
+            .map(|(_, loc, size)| {
+                let physical_loc = loc.to_f64().to_physical(scale).to_i32_round();
+                let physical_start = if is_horizontal { physical_loc.x } else { physical_loc.y };
+                physical_start + major_physical(size.to_physical_precise_round(scale)) - limit
+            })
+            .max()
+            .unwrap_or_default();
+        if overshoot <= 0 {
+            return;
+        }
+        let nudge = (overshoot as f64 / scale).ceil() as i32;
+
+        // the run ends at the first element which is far enough from the next
+        // one to stay in place
+        let mut run_start = None;
+        for (element, loc, size) in elements.into_iter().rev() {
+            if run_start.is_some_and(|run_start| start(loc) + major(size) <= run_start - nudge) {
+                break;
+            }
+            run_start = Some(start(loc));
+            let loc = if is_horizontal {
+                loc - Point::from((nudge, 0))
+            } else {
+                loc - Point::from((0, nudge))
+            };
+            self.space.map_element(element, loc, false);
+        }
+    }
+
+    /// geometries of the dividers between the wings and the center
+    ///
+    /// Sections are given as their lengthwise start and length, in order. A
+    /// divider is placed in the middle of the space between two adjacent
+    /// sections, if both are non-empty and the space fits the divider with the
+    /// spacing on either side.
+    fn section_divider_rects(
+        &self,
+        sections: [(f64, f64); 3],
+        crosswise_pos: i32,
+        crosswise_dim: i32,
+    ) -> Vec<Rectangle<i32, Logical>> {
+        let Some(divider) = self.config.section_divider else {
+            return Vec::new();
+        };
+        let width = divider.width.max(1.) as f64;
+        let spacing = self.config.spacing() as f64;
+        let padding = self.config.padding() as i32;
+        let thickness = (crosswise_dim - padding * 2).max(1);
+        sections
+            .windows(2)
+            .filter_map(|pair| {
+                let ((start, len), (next_start, next_len)) = (pair[0], pair[1]);
+                let end = start + len;
+                if len <= 0. || next_len <= 0. || next_start - end < width + spacing * 2. {
+                    return None;
+                }
+                let pos = ((end + next_start - width) / 2.).round() as i32;
+                let length = width.round() as i32;
+                Some(if self.config.is_horizontal() {
+                    Rectangle::from_loc_and_size(
+                        (pos, crosswise_pos + padding),
+                        (length, thickness),
+                    )
+                } else {
+                    Rectangle::from_loc_and_size(
+                        (crosswise_pos + padding, pos),
+                        (thickness, length),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// subtract the rounded corners of the panel from a rectangle of the input
+    /// region
+    ///
+    /// Only the corners facing away from the anchored edge are rounded, so the
+    /// edge of the output stays clickable. Each corner is approximated by
+    /// `input_corner_steps` rectangles.
+    fn round_input_corners(
+        &self,
+        input_region: &Region,
+        loc: (i32, i32),
+        size: (i32, i32),
+        crosswise_dim: i32,
+    ) {
+        let radius = (self.border_radius().min(crosswise_dim.max(0) as u32) / 2)
+            .min(size.0.max(0) as u32 / 2)
+            .min(size.1.max(0) as u32 / 2) as i32;
+        if radius == 0 {
+            return;
+        }
+        let steps = self.config.get_input_corner_steps() as i32;
+        let (x0, y0, x1, y1) = (loc.0, loc.1, loc.0 + size.0, loc.1 + size.1);
+        // corner position and the direction pointing into the rectangle
+        let corners = match self.config.anchor {
+            PanelAnchor::Left => [((x1, y0), (-1, 1)), ((x1, y1), (-1, -1))],
+            PanelAnchor::Right => [((x0, y0), (1, 1)), ((x0, y1), (1, -1))],
+            PanelAnchor::Top => [((x0, y1), (1, -1)), ((x1, y1), (-1, -1))],
+            PanelAnchor::Bottom => [((x0, y0), (1, 1)), ((x1, y0), (-1, 1))],
+        };
+        let r = radius as f32;
+        for ((cx, cy), (dx, dy)) in corners {
+            for i in 0..steps {
+                let band_start = radius * i / steps;
+                let band_end = radius * (i + 1) / steps;
+                if band_end <= band_start {
+                    continue;
+                }
+                let mid = (band_start + band_end) as f32 / 2.;
+                let inset = (r - (r * r - (r - mid).powi(2)).max(0.).sqrt()).round() as i32;
+                if inset <= 0 {
+                    continue;
+                }
+                // bands step away from the far edge, inset along the length
+                let (w, h) = if self.config.is_horizontal() {
+                    (inset, band_end - band_start)
+                } else {
+                    (band_end - band_start, inset)
+                };
+                let (x, y) = if self.config.is_horizontal() {
+                    (
+                        if dx > 0 { cx } else { cx - inset },
+                        if dy > 0 { cy + band_start } else { cy - band_end },
+                    )
+                } else {
+                    (
+                        if dx > 0 { cx + band_start } else { cx - band_end },
+                        if dy > 0 { cy } else { cy - inset },
+                    )
+                };
+                input_region.subtract(x, y, w, h);
+            }
+        }
     }
 
     // reorder overflow space windows, and remove dead windows
@@ -817,6 +1333,7 @@ impl PanelSpace {
         let applet_size_unit = self.config.size.get_applet_icon_size_with_padding(true);
         let padding = self.config.padding as i32;
         let spacing = self.config.spacing as i32;
+        let columns = self.config.overflow_columns.max(1) as usize;
         let Some(output) = self.output.as_ref().map(|o| o.1.clone()) else {
             return;
         };
@@ -850,13 +1367,13 @@ impl PanelSpace {
                     if !w.alive() {
                         space.unmap_elem(&PopupMappedInternal::Window(w.clone()));
                     } else {
-                        let x_i = overflow_cnt % 8;
+                        let x_i = overflow_cnt % columns;
                         let mut x = BORDER_WIDTH as i32
                             + padding
                             + x_i as i32 * (applet_size_unit as i32 + spacing);
                         let mut y = BORDER_WIDTH as i32
                             + padding
-                            + (overflow_cnt / 8) as i32 * (applet_size_unit as i32 + spacing);
+                            + (overflow_cnt / columns) as i32 * (applet_size_unit as i32 + spacing);
                         if !self.config.is_horizontal() {
                             std::mem::swap(&mut x, &mut y);
                         }
@@ -868,16 +1385,8 @@ impl PanelSpace {
                     let prev_cnt = p.with_program(|p| p.count);
                     if prev_cnt != cur_cnt {
                         let actual = cur_cnt.saturating_sub(1);
-                        let mut popup_major = 2. * BORDER_WIDTH as f32
-                            + actual.min(8) as f32 * applet_size_unit as f32
-                            + 2. * padding as f32
-                            + (actual.min(8).saturating_sub(1) as f32) * spacing as f32;
-                        let mut popup_cross = 2. * BORDER_WIDTH as f32
-                            + (actual as f32 / 8.).ceil().min(1.0) * applet_size_unit as f32
-                            + 2. * padding as f32;
-                        if !self.config.is_horizontal() {
-                            std::mem::swap(&mut popup_major, &mut popup_cross);
-                        }
+                        let (popup_major, popup_cross) =
+                            Self::overflow_popup_size(&self.config, actual, applet_size_unit);
 
                         let new_popup = PopupMappedInternal::Popup(overflow_popup_element(
                             match section {
@@ -891,6 +1400,7 @@ impl PanelSpace {
                             self.colors.theme.clone(),
                             self.space.id(),
                             actual,
+                            self.config.overflow_popup_style.clone(),
                         ));
                         space.unmap_elem(&PopupMappedInternal::Popup(p.clone()));
                         new_popup.output_enter(&output, Rectangle::default());
@@ -932,10 +1442,11 @@ impl PanelSpace {
             };
             if let Some(shrink_min_size) = c.shrink_min_size {
                 overflow_partition.shrinkable.push((w.0, w.1 as i32, shrink_min_size));
-            } else if c.shrink_priority.is_some() {
+            } else if c.shrink_priority.is_some() && !c.pin {
                 overflow_partition.movable.push(w);
             } else {
-                // make shrinkable if no shrink priority with lowest priority so it is moved last
+                // make shrinkable if no shrink priority or pinned, with lowest priority so it
+                // is only shrunk last and never moved
                 overflow_partition.shrinkable.push((w.0, -1, ClientShrinkSize::AppletUnit(1)));
             }
         }
@@ -1095,6 +1606,7 @@ impl PanelSpace {
         let applet_size_unit = self.config.size.get_applet_icon_size(true)
             + 2 * self.config.size.get_applet_padding(true) as u32;
         let spacing = self.config.spacing;
+        let columns = self.config.overflow_columns.max(1) as usize;
 
         if overflow_cnt == 0 {
             overflow += applet_size_unit + spacing;
@@ -1119,19 +1631,19 @@ impl PanelSpace {
             overflow = overflow.saturating_sub(diff);
             let padding = self.config.padding as i32;
             // TODO spacing & padding
-            let x_i = overflow_cnt % 8;
+            let x_i = overflow_cnt % columns;
             let mut x = padding
                 + x_i as i32 * (applet_size_unit as i32 + spacing as i32)
                 + BORDER_WIDTH as i32;
             let mut y = BORDER_WIDTH as i32
-                + (overflow_cnt / 8) as i32 * (applet_size_unit + spacing) as i32;
+                + (overflow_cnt / columns) as i32 * (applet_size_unit + spacing) as i32;
             if !self.config.is_horizontal() {
                 std::mem::swap(&mut x, &mut y);
             }
 
             space.unmap_elem(&CosmicMappedInternal::Window(w.0.clone()));
             overflow_space.map_element(PopupMappedInternal::Window(w.0.clone()), (x, y), true);
-            // Rows of 8 with configured applet size
+            // Rows of `overflow_columns` with configured applet size
             if let Some(t) = w.0.toplevel() {
                 with_states(t.wl_surface(), |states| {
                     with_fractional_scale(states, |fractional_scale| {
@@ -1153,18 +1665,8 @@ impl PanelSpace {
             .filter(|e| if let PopupMappedInternal::Window(w) = e { w.alive() } else { false })
             .count();
 
-        let space = self.config.spacing as f32;
-        let padding = self.config.padding as f32;
-        let mut popup_major = 2. * BORDER_WIDTH as f32
-            + overflow_cnt.min(8) as f32 * applet_size_unit as f32
-            + 2. * padding
-            + (overflow_cnt.min(8).saturating_sub(1) as f32) * space;
-        let mut popup_cross = 2. * BORDER_WIDTH as f32
-            + (overflow_cnt as f32 / 8.).ceil().min(1.0) * applet_size_unit as f32
-            + 2. * padding;
-        if !self.config.is_horizontal() {
-            std::mem::swap(&mut popup_major, &mut popup_cross);
-        }
+        let (popup_major, popup_cross) =
+            Self::overflow_popup_size(&self.config, overflow_cnt, applet_size_unit);
         let popup = overflow_space
             .elements()
             .find(|e| {
@@ -1195,6 +1697,7 @@ impl PanelSpace {
                 self.colors.theme.clone(),
                 self.space.id(),
                 count,
+                self.config.overflow_popup_style.clone(),
             ))
         };
 
@@ -1216,56 +1719,136 @@ impl PanelSpace {
             self.space.refresh();
         }
 
-        if self.space.elements().all(|e| !matches!(e, CosmicMappedInternal::OverflowButton(_))) {
-            let overflow_button_loc = (0, 0);
-            let id = match section {
-                OverflowSection::Left => self.left_overflow_button_id.clone(),
-                OverflowSection::Center => self.center_overflow_button_id.clone(),
-                OverflowSection::Right => self.right_overflow_button_id.clone(),
-            };
-
-            let icon_size = self.config.size.get_applet_icon_size(true);
-            let padding = self.config.size.get_applet_padding(true);
-            let icon = if self.config.is_horizontal() {
-                "view-more-horizontal-symbolic"
-            } else {
-                "view-more-symbolic"
-            };
-            let e = overflow_button_element(
-                id,
-                (0, 0).into(),
-                u16::try_from(icon_size).unwrap_or(32),
-                (padding as f32).into(),
-                Arc::new(AtomicBool::new(false)),
-                icon.into(),
-                self.loop_handle.clone(),
-                self.colors.theme.clone(),
-                self.space.id(),
-            );
-            let output = self.output.as_ref().map(|o| &o.1).unwrap();
-            e.output_enter(output, Default::default());
-            self.space.map_element(
-                CosmicMappedInternal::OverflowButton(e),
-                overflow_button_loc,
-                false,
-            );
-            self.space.refresh();
-            self.is_dirty = true;
+        if !self.has_overflow_button(section) {
+            if let Some(e) = self.new_overflow_button(section) {
+                self.space.map_element(CosmicMappedInternal::OverflowButton(e), (0, 0), false);
+                self.space.refresh();
+                self.is_dirty = true;
+            }
         }
         overflow
     }
 
+    /// the id of the overflow button of a section
+    fn overflow_button_id(&self, section: OverflowSection) -> &Id {
+        match section {
+            OverflowSection::Left => &self.left_overflow_button_id,
+            OverflowSection::Center => &self.center_overflow_button_id,
+            OverflowSection::Right => &self.right_overflow_button_id,
+        }
+    }
+
+    /// whether the overflow button of a section is mapped
+    fn has_overflow_button(&self, section: OverflowSection) -> bool {
+        let id = self.overflow_button_id(section);
+        self.space.elements().any(|e| match e {
+            CosmicMappedInternal::OverflowButton(b) => overflow_button::with_id(b, |b| b == id),
+            _ => false,
+        })
+    }
+
+    /// a new overflow button for a section, which is not mapped yet
+    fn new_overflow_button(&self, section: OverflowSection) -> Option<OverflowButtonElement> {
+        let output = self.output.as_ref().map(|o| &o.1)?;
+        let icon_size = self.config.size.get_applet_icon_size(true);
+        let padding = self.config.size.get_applet_padding(true);
+        let icon = if self.config.is_horizontal() {
+            "view-more-horizontal-symbolic"
+        } else {
+            "view-more-symbolic"
+        };
+        let e = overflow_button_element(
+            self.overflow_button_id(section).clone(),
+            (0, 0).into(),
+            u16::try_from(icon_size).unwrap_or(32),
+            (padding as f32).into(),
+            Arc::new(AtomicBool::new(false)),
+            icon.into(),
+            self.loop_handle.clone(),
+            self.colors.theme.clone(),
+            self.space.id(),
+        );
+        e.output_enter(output, Default::default());
+        Some(e)
+    }
+
+    /// the overflow button of a section, mapping one while its collapsible
+    /// applets are expanded so that it can collapse them
+    fn sync_collapse_toggle(
+        &mut self,
+        section: OverflowSection,
+        current: Option<OverflowButtonElement>,
+    ) -> Option<OverflowButtonElement> {
+        if current.is_some() || !self.is_collapse_toggle(section) {
+            return current;
+        }
+        let button = self.new_overflow_button(section)?;
+        self.space.map_element(CosmicMappedInternal::OverflowButton(button.clone()), (0, 0), false);
+        self.is_dirty = true;
+        Some(button)
+    }
+
+    /// whether the overflow button of a section collapses its applets when
+    /// pressed, instead of showing the overflow popup
+    ///
+    /// This is the case while the panel is expanded and the section has at
+    /// least two collapsible applets.
+    pub(crate) fn is_collapse_toggle(&self, section: OverflowSection) -> bool {
+        if self.collapsed
+            || self.mirror_of.is_some()
+            || !self.overflow_sections().contains(&section)
+        {
+            return false;
+        }
+        let collapsible = self.collapsible_applet_clients();
+        if collapsible.is_empty() {
+            return false;
+        }
+        let pinned = self.pinned_applet_clients();
+        self.section_clients(section)
+            .iter()
+            .filter(|c| collapsible.contains(c) && !pinned.contains(c))
+            .count()
+            >= 2
+    }
+
+    /// size of the overflow popup for a grid of `count` applets
+    fn overflow_popup_size(
+        config: &CosmicPanelConfig,
+        count: usize,
+        applet_size_unit: u32,
+    ) -> (f32, f32) {
+        let columns = config.overflow_columns.max(1) as usize;
+        let spacing = config.spacing as f32;
+        let padding = config.padding as f32;
+        let rows = count.div_ceil(columns).max(1);
+        let mut popup_major = 2. * BORDER_WIDTH as f32
+            + count.min(columns) as f32 * applet_size_unit as f32
+            + 2. * padding
+            + (count.min(columns).saturating_sub(1) as f32) * spacing;
+        let mut popup_cross = 2. * BORDER_WIDTH as f32
+            + rows as f32 * applet_size_unit as f32
+            + 2. * padding
+            + (rows - 1) as f32 * spacing;
+        if !config.is_horizontal() {
+            std::mem::swap(&mut popup_major, &mut popup_cross);
+        }
+        (popup_major, popup_cross)
+    }
+
     fn move_from_overflow(
         mut extra_space: u32,
         is_horizontal: bool,
         space: &mut Space<CosmicMappedInternal>,
         overflow_space: &mut Space<PopupMappedInternal>,
         suggested_size: u32,
+        collapsed: &[ClientId],
+        mut room: usize,
     ) -> u32 {
         // TODO move applets until extra_space is as close as possible to 0
         let overflow_elements = overflow_space.elements().cloned().collect_vec();
         for w in overflow_elements {
-            if extra_space < suggested_size {
+            if extra_space < suggested_size || room == 0 {
                 break;
             }
             let size: Size<i32, _> = w.bbox().size;
@@ -1276,7 +1859,14 @@ impl PanelSpace {
                     PopupMappedInternal::Window(w) => w,
                     _ => continue,
                 };
+                if w.toplevel()
+                    .and_then(|t| t.wl_surface().client())
+                    .is_some_and(|c| collapsed.contains(&c.id()))
+                {
+                    continue;
+                }
                 extra_space = extra_space.saturating_sub(applet_len);
+                room -= 1;
                 overflow_space.unmap_elem(&PopupMappedInternal::Window(w.clone()));
                 overflow_space.refresh();
                 space.map_element(CosmicMappedInternal::Window(w.clone()), (0, 0), false);
@@ -1294,11 +1884,204 @@ impl PanelSpace {
         extra_space
     }
 
+    /// move the windows of collapsed applets to the overflow space of their
+    /// section, if there are at least two of them
+    ///
+    /// returns true if any window was moved
+    fn collapse_applets(&mut self, windows: &[Window]) -> bool {
+        let collapsed = self.collapsed_applet_clients();
+        if collapsed.is_empty() {
+            return false;
+        }
+        let pinned = self.pinned_applet_clients();
+        let mut moved = false;
+        for section in self.overflow_sections() {
+            let section_clients = self
+                .section_clients(section)
+                .into_iter()
+                .filter(|c| collapsed.contains(c) && !pinned.contains(c))
+                .collect_vec();
+            let movable = windows
+                .iter()
+                .filter(|w| {
+                    w.toplevel()
+                        .and_then(|t| t.wl_surface().client())
+                        .is_some_and(|c| section_clients.contains(&c.id()))
+                })
+                .map(|w| (w.clone(), 0))
+                .collect_vec();
+            // a single applet is not worth hiding behind a button
+            if movable.len() < 2 {
+                continue;
+            }
+            moved = true;
+            let partition = OverflowClientPartition { movable, ..Default::default() };
+            self.move_to_overflow(u32::MAX, self.config.is_horizontal(), partition, section);
+        }
+        moved
+    }
+
+    /// the sections with their own overflow popup
+    ///
+    /// Panels which do not expand to the edges put all of their applets in
+    /// the center section.
+    fn overflow_sections(&self) -> Vec<OverflowSection> {
+        if self.config.expand_to_edges() {
+            vec![OverflowSection::Left, OverflowSection::Center, OverflowSection::Right]
+        } else {
+            vec![OverflowSection::Center]
+        }
+    }
+
+    /// ids of the clients of the applets in a section, in order
+    fn section_clients(&self, section: OverflowSection) -> Vec<ClientId> {
+        let clients = match (self.config.expand_to_edges(), section) {
+            (false, _) => vec![&self.clients_left, &self.clients_center, &self.clients_right],
+            (true, OverflowSection::Left) => vec![&self.clients_left],
+            (true, OverflowSection::Center) => vec![&self.clients_center],
+            (true, OverflowSection::Right) => vec![&self.clients_right],
+        };
+        clients
+            .into_iter()
+            .flat_map(|c| c.lock().unwrap().iter().map(|c| c.client.id()).collect_vec())
+            .collect()
+    }
+
+    /// the configured maximum number of applets shown in a section
+    fn max_visible(&self, section: OverflowSection) -> Option<usize> {
+        let i = match section {
+            _ if !self.config.expand_to_edges() => 1,
+            OverflowSection::Left => 0,
+            OverflowSection::Center => 1,
+            OverflowSection::Right => 2,
+        };
+        self.config.max_visible[i].map(|max| max as usize)
+    }
+
+    /// number of applets which may be moved back from the overflow popup of a
+    /// section without exceeding its maximum number of visible applets
+    fn visible_room(&self, section: OverflowSection) -> usize {
+        let Some(max) = self.max_visible(section) else {
+            return usize::MAX;
+        };
+        let section_clients = self.section_clients(section);
+        let is_section_window = |w: &Window| {
+            w.toplevel()
+                .and_then(|t| t.wl_surface().client())
+                .is_some_and(|c| section_clients.contains(&c.id()))
+        };
+        let visible = self
+            .space
+            .elements()
+            .filter(|e| matches!(e, CosmicMappedInternal::Window(w) if is_section_window(w)))
+            .count();
+        let overflow_space = match section {
+            OverflowSection::Left => &self.overflow_left,
+            OverflowSection::Center => &self.overflow_center,
+            OverflowSection::Right => &self.overflow_right,
+        };
+        let overflowed = overflow_space
+            .elements()
+            .filter(|e| matches!(e, PopupMappedInternal::Window(w) if is_section_window(w)))
+            .count();
+        if visible + overflowed <= max {
+            usize::MAX
+        } else {
+            // the overflow button takes the place of an applet
+            max.saturating_sub(1).saturating_sub(visible)
+        }
+    }
+
+    /// move the windows of applets beyond the maximum number of visible
+    /// applets of their section to its overflow space
+    ///
+    /// The overflow button takes the place of one applet, so one more applet
+    /// than the excess is moved. Pinned applets stay visible, so the last
+    /// unpinned applets are moved in their place.
+    ///
+    /// returns true if any window was moved
+    fn cap_visible_applets(&mut self, windows: &[Window]) -> bool {
+        let pinned = self.pinned_applet_clients();
+        let is_pinned = |w: &Window| {
+            w.toplevel()
+                .and_then(|t| t.wl_surface().client())
+                .is_some_and(|c| pinned.contains(&c.id()))
+        };
+        let mut moved = false;
+        for section in self.overflow_sections() {
+            let Some(max) = self.max_visible(section) else {
+                continue;
+            };
+            let section_windows = self
+                .section_clients(section)
+                .into_iter()
+                .flat_map(|c| {
+                    windows.iter().filter(move |w| {
+                        w.toplevel()
+                            .and_then(|t| t.wl_surface().client())
+                            .is_some_and(|client| client.id() == c)
+                    })
+                })
+                .collect_vec();
+            if section_windows.len() <= max {
+                continue;
+            }
+            let excess = section_windows.len() - max.saturating_sub(1);
+            let unpinned = section_windows.into_iter().filter(|w| !is_pinned(*w)).collect_vec();
+            let movable = unpinned[unpinned.len().saturating_sub(excess)..]
+                .iter()
+                .map(|w| ((*w).clone(), 0))
+                .collect_vec();
+            if movable.is_empty() {
+                continue;
+            }
+            moved = true;
+            let partition = OverflowClientPartition { movable, ..Default::default() };
+            self.move_to_overflow(u32::MAX, self.config.is_horizontal(), partition, section);
+        }
+        moved
+    }
+
+    /// the start button for the configured start icon, replacing the current
+    /// one if the icon changed
+    fn sync_start_button(
+        &mut self,
+        current: Option<StartButtonElement>,
+    ) -> Option<StartButtonElement> {
+        let icon = self.config.start_icon.as_ref().map(|i| i.icon.clone());
+        if let Some(button) = current {
+            if icon.as_ref().is_some_and(|icon| button.with_program(|p| &p.icon == icon)) {
+                return Some(button);
+            }
+            self.space.unmap_elem(&CosmicMappedInternal::StartButton(button));
+        }
+        let icon = icon?;
+        let output = self.output.as_ref().map(|o| &o.1)?;
+        let icon_size = self.config.size.get_applet_icon_size(true);
+        let padding = self.config.size.get_applet_padding(true);
+        let button = start_button_element(
+            self.start_button_id.clone(),
+            u16::try_from(icon_size).unwrap_or(32),
+            (padding as f32).into(),
+            icon.into(),
+            self.loop_handle.clone(),
+            self.colors.theme.clone(),
+            self.space.id(),
+        );
+        button.output_enter(output, Default::default());
+        self.space.map_element(CosmicMappedInternal::StartButton(button.clone()), (0, 0), false);
+        self.is_dirty = true;
+        Some(button)
+    }
+
     fn relax_overflow_left(
         &mut self,
         extra_space: u32,
         left_overflow_button: &mut Option<IcedElement<OverflowButton>>,
     ) {
+        let collapsed = self.collapsed_applet_clients();
+        let room = self.visible_room(OverflowSection::Left);
+        let is_collapse_toggle = self.is_collapse_toggle(OverflowSection::Left);
         let left = self.clients_left.lock().unwrap();
         let mut clients = self.shrinkable_clients(left.iter());
         drop(left);
@@ -1311,8 +2094,12 @@ impl PanelSpace {
                 &mut self.space,
                 &mut self.overflow_left,
                 suggested_size,
+                &collapsed,
+                room,
             );
-            if self.overflow_left.elements().all(|e| matches!(e, PopupMappedInternal::Popup(_))) {
+            if !is_collapse_toggle
+                && self.overflow_left.elements().all(|e| matches!(e, PopupMappedInternal::Popup(_)))
+            {
                 if let Some(overflow_button) = left_overflow_button.take() {
                     self.space.unmap_elem(&CosmicMappedInternal::OverflowButton(overflow_button));
                     self.space.refresh();
@@ -1328,6 +2115,9 @@ impl PanelSpace {
         extra_space: u32,
         center_overflow_button: &mut Option<IcedElement<OverflowButton>>,
     ) {
+        let collapsed = self.collapsed_applet_clients();
+        let room = self.visible_room(OverflowSection::Center);
+        let is_collapse_toggle = self.is_collapse_toggle(OverflowSection::Center);
         let center: MutexGuard<Vec<PanelClient>> = self.clients_center.lock().unwrap();
         let mut clients = self.shrinkable_clients(center.iter());
         drop(center);
@@ -1340,8 +2130,15 @@ impl PanelSpace {
                 &mut self.space,
                 &mut self.overflow_center,
                 suggested_size,
+                &collapsed,
+                room,
             );
-            if self.overflow_center.elements().all(|e| matches!(e, PopupMappedInternal::Popup(_))) {
+            if !is_collapse_toggle
+                && self
+                    .overflow_center
+                    .elements()
+                    .all(|e| matches!(e, PopupMappedInternal::Popup(_)))
+            {
                 if let Some(overflow_button) = center_overflow_button.take() {
                     self.space.unmap_elem(&CosmicMappedInternal::OverflowButton(overflow_button));
                     self.space.refresh();
@@ -1379,29 +2176,39 @@ impl PanelSpace {
         }
         let suggested_size = self.config.size.get_applet_icon_size(true)
             + self.config.size.get_applet_padding(true) as u32 * 2;
+        let collapsed = self.collapsed_applet_clients();
         self.relax_overflow_left(u32::MAX, &mut left_overflow_button);
         self.relax_overflow_center(u32::MAX, &mut center_overflow_button);
         self.relax_overflow_right(u32::MAX, &mut right_overflow_button);
+        let room = self.visible_room(OverflowSection::Left);
         PanelSpace::move_from_overflow(
             u32::MAX,
             self.config.is_horizontal(),
             &mut self.space,
             &mut self.overflow_left,
             suggested_size,
+            &collapsed,
+            room,
         );
+        let room = self.visible_room(OverflowSection::Center);
         PanelSpace::move_from_overflow(
             u32::MAX,
             self.config.is_horizontal(),
             &mut self.space,
             &mut self.overflow_center,
             suggested_size,
+            &collapsed,
+            room,
         );
+        let room = self.visible_room(OverflowSection::Right);
         PanelSpace::move_from_overflow(
             u32::MAX,
             self.config.is_horizontal(),
             &mut self.space,
             &mut self.overflow_right,
             suggested_size,
+            &collapsed,
+            room,
         );
     }
     fn relax_overflow_right(
@@ -1409,6 +2216,9 @@ impl PanelSpace {
         extra_space: u32,
         right_overflow_button: &mut Option<IcedElement<OverflowButton>>,
     ) {
+        let collapsed = self.collapsed_applet_clients();
+        let room = self.visible_room(OverflowSection::Right);
+        let is_collapse_toggle = self.is_collapse_toggle(OverflowSection::Right);
         let right = self.clients_right.lock().unwrap();
         let mut clients = self.shrinkable_clients(right.iter());
 
@@ -1421,8 +2231,15 @@ impl PanelSpace {
                 &mut self.space,
                 &mut self.overflow_right,
                 suggested_size,
+                &collapsed,
+                room,
             );
-            if self.overflow_right.elements().all(|e| matches!(e, PopupMappedInternal::Popup(_))) {
+            if !is_collapse_toggle
+                && self
+                    .overflow_right
+                    .elements()
+                    .all(|e| matches!(e, PopupMappedInternal::Popup(_)))
+            {
                 if let Some(overflow_button) = right_overflow_button.take() {
                     self.space.unmap_elem(&CosmicMappedInternal::OverflowButton(overflow_button));
                     self.space.refresh();
@@ -1488,6 +2305,9 @@ impl PanelSpace {
 
     /// Send frame callback to hidden applets
     pub fn update_hidden_applet_frame(&mut self) {
+        if self.occluded() {
+            return;
+        }
         let Some(output) = self.output.as_ref().map(|o| o.1.clone()) else {
             return;
         };
@@ -1536,7 +2356,7 @@ impl PanelSpace {
 // they can have nested popups in a common use case now too.
 // overflow buttons go in the original space.
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OverflowSection {
     Left,
     Center,
@@ -1617,3 +2437,119 @@ impl OverflowClientPartition {
         }
     }
 }
+
+/// number of sections of the panel with content, counting both sides once
+/// either of them has content
+fn section_count(has_sides: bool, has_center: bool) -> u32 {
+    let mut num_lists = 0;
+    if has_sides {
+        num_lists += 2;
+    }
+    if has_center {
+        num_lists += 1;
+    }
+    num_lists
+}
+
+/// lengths of the panel split evenly into thirds and halves between its
+/// sections
+///
+/// A panel without applets has no lists, but is still laid out as an empty
+/// bar.
+fn even_section_lengths(layer_major: i32, spacing: u32, num_lists: u32) -> (f64, f64) {
+    let one_third = (layer_major as f64 - (spacing * num_lists.saturating_sub(1)) as f64)
+        / (3.min(num_lists.max(1)) as f64);
+    let one_half = layer_major as f64 / (2.min(num_lists.max(1)) as f64);
+    (one_third, one_half)
+}
+
+/// offset of an applet across the thickness of the panel
+fn align_in_bar(alignment: AppletAlignment, crosswise_dim: u32, dim: u32) -> i32 {
+    match alignment {
+        AppletAlignment::Start => 0,
+        AppletAlignment::Center => (crosswise_dim as i32 - dim as i32) / 2,
+        AppletAlignment::End => crosswise_dim as i32 - dim as i32,
+    }
+}
+
+/// order the windows of a section from the center outward, with the first in
+/// the middle and the others alternating to its left and right
+///
+/// The section is centered as a whole, so with an even count the first two
+/// windows straddle the middle. Crashed slots keep preceding the same window.
+fn order_center_outward<T>(windows: &mut Vec<T>, crashed: &mut [CrashedSlot]) {
+    let len = windows.len();
+    // odd positions fill the start from the middle, even ones the end
+    let order = (0..len).filter(|i| i % 2 == 1).rev().chain((0..len).filter(|i| i % 2 == 0));
+    let mut rank = vec![0; len];
+    for (visual, i) in order.enumerate() {
+        rank[i] = visual;
+    }
+    let mut ranked = windows.drain(..).enumerate().collect::<Vec<_>>();
+    ranked.sort_by_key(|(i, _)| rank[*i]);
+    windows.extend(ranked.into_iter().map(|(_, w)| w));
+    for (before, _) in crashed.iter_mut() {
+        *before = rank.get(*before).copied().unwrap_or(len);
+    }
+    crashed.sort_by_key(|(before, _)| *before);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn panel_without_applets_has_finite_sections() {
+        let (one_third, one_half) = even_section_lengths(1920, 4, 0);
+        assert_eq!(one_third, 1920.);
+        assert_eq!(one_half, 1920.);
+    }
+
+    #[test]
+    fn center_outward_order_starts_in_the_middle() {
+        let mut windows = vec!['a', 'b', 'c', 'd', 'e'];
+        order_center_outward(&mut windows, &mut []);
+        assert_eq!(windows, ['d', 'b', 'a', 'c', 'e']);
+
+        // the first two straddle the middle
+        let mut windows = vec!['a', 'b', 'c', 'd'];
+        order_center_outward(&mut windows, &mut []);
+        assert_eq!(windows, ['d', 'b', 'a', 'c']);
+
+        let mut windows = vec!['a'];
+        order_center_outward(&mut windows, &mut []);
+        assert_eq!(windows, ['a']);
+    }
+
+    #[test]
+    fn center_outward_crashed_slots_precede_the_same_window() {
+        let mut windows = vec!['a', 'b', 'c', 'd'];
+        let mut crashed = [(0, "z".to_string()), (1, "x".to_string()), (4, "y".to_string())];
+        order_center_outward(&mut windows, &mut crashed);
+        assert_eq!(windows, ['d', 'b', 'a', 'c']);
+        // still before 'b', before 'a' and after the last window
+        assert_eq!(crashed, [(1, "x".to_string()), (2, "z".to_string()), (4, "y".to_string())]);
+    }
+
+    #[test]
+    fn panel_without_plugins_is_laid_out_as_an_empty_bar() {
+        let config =
+            CosmicPanelConfig { plugins_wings: None, plugins_center: None, ..Default::default() };
+        let [left, center, right] =
+            [config.plugins_left(), config.plugins_center(), config.plugins_right()]
+                .map(Option::unwrap_or_default);
+        let num_lists = section_count(!left.is_empty() || !right.is_empty(), !center.is_empty());
+        assert_eq!(num_lists, 0);
+        let (one_third, one_half) = even_section_lengths(1920, 4, num_lists);
+        assert!(one_third.is_finite() && one_third > 0.);
+        assert!(one_half.is_finite() && one_half > 0.);
+    }
+
+    #[test]
+    fn sections_split_the_panel_evenly() {
+        assert_eq!(even_section_lengths(1920, 0, 3), (640., 960.));
+        assert_eq!(even_section_lengths(1920, 6, 3), (636., 960.));
+        assert_eq!(even_section_lengths(1920, 6, 2), (957., 960.));
+        assert_eq!(even_section_lengths(1920, 6, 1), (1920., 1920.));
+    }
+}
@@ -2,17 +2,21 @@
 //! separate process and compositing them in a layer shell surface as configured
 //! PanelSpace *partially* implements the WrapperSpace abstraction
 
+mod background_image;
 mod corner_element;
+mod desaturate;
 mod layout;
 mod overflow;
 mod panel_space;
+mod persisted_state;
 mod popup;
 mod render;
 mod wrapper_space;
 
-pub(crate) use panel_space::{AppletMsg, PanelColors, PanelSpace};
+pub(crate) use layout::OverflowSection;
+pub(crate) use panel_space::{move_plugin, AppletMsg, PanelColors, PanelSpace, SOFTWARE_RENDERING};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum Alignment {
     Left,
     Center,
@@ -41,9 +41,16 @@ impl PanelSpace {
     ) -> anyhow::Result<()> {
         if force_hide || self.overflow_popup.is_some() {
             tracing::info!("removing overflow popup");
-            self.overflow_popup = None;
+            self.close_overflow_popup();
             return Ok(());
         }
+        // the button of a section with expanded collapsible applets collapses them
+        if let (Some((_, section)), _) = self.overflow_elements_for_id(&element_id) {
+            if self.is_collapse_toggle(section) {
+                self.set_collapsed(true);
+                return Ok(());
+            }
+        }
         // get popup location and anchor based on element_id and panel
         // anchor create popup using sctk
         let c_wl_surface = compositor_state.create_surface(qh);
@@ -120,6 +127,7 @@ impl PanelSpace {
                 ),
                 c_popup,
                 egl_surface: None,
+                egl_attempts: 0,
                 dirty: false,
                 rectangle: Rectangle::from_loc_and_size((0, 0), popup_bbox.size),
                 state: Some(WrapperPopupState::WaitConfigure),
@@ -134,10 +142,71 @@ impl PanelSpace {
             },
             section,
         ));
+        self.set_overflow_button_selected(Some(section));
 
         Ok(())
     }
 
+    /// open the overflow popup of a section, closing the popup of any other
+    /// section
+    pub fn open_overflow_popup(
+        &mut self,
+        section: OverflowSection,
+        compositor_state: &sctk::compositor::CompositorState,
+        fractional_scale_manager: Option<&FractionalScalingManager>,
+        viewport: Option<&ViewporterState>,
+        qh: &QueueHandle<GlobalState>,
+        xdg_shell_state: &mut sctk::shell::xdg::XdgShell,
+        seat: (u32, WlSeat),
+    ) -> anyhow::Result<()> {
+        if let Some((_, open_section)) = self.overflow_popup.as_ref() {
+            if *open_section == section {
+                return Ok(());
+            }
+            self.close_overflow_popup();
+        }
+        let element_id = self.overflow_button_id(section).clone();
+        self.toggle_overflow_popup(
+            element_id,
+            compositor_state,
+            fractional_scale_manager,
+            viewport,
+            qh,
+            xdg_shell_state,
+            seat,
+            false,
+        )
+    }
+
+    /// close the overflow popup if it is open
+    pub fn close_overflow_popup(&mut self) {
+        if self.overflow_popup.take().is_some() {
+            self.is_dirty = true;
+        }
+        self.set_overflow_button_selected(None);
+    }
+
+    fn overflow_button_id(&self, section: OverflowSection) -> &id::Id {
+        match section {
+            OverflowSection::Left => &self.left_overflow_button_id,
+            OverflowSection::Center => &self.center_overflow_button_id,
+            OverflowSection::Right => &self.right_overflow_button_id,
+        }
+    }
+
+    /// keep the selected state of the overflow buttons in sync with the open
+    /// popup
+    pub(crate) fn set_overflow_button_selected(&self, section: Option<OverflowSection>) {
+        let selected_id = section.map(|s| self.overflow_button_id(s));
+        for e in self.space.elements() {
+            if let CosmicMappedInternal::OverflowButton(b) = e {
+                if b.with_program(|p| p.set_selected(Some(&p.id) == selected_id)) {
+                    b.force_redraw();
+                }
+            }
+        }
+    }
+
     fn overflow_elements_for_id(
         &self,
         element_id: &id::Id,
@@ -1,24 +1,32 @@
 use std::{
     cell::{Cell, RefCell},
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fmt::Debug,
     os::{fd::OwnedFd, unix::net::UnixStream},
     rc::Rc,
     str::FromStr,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     time::{Duration, Instant},
 };
 
 use crate::{
-    iced::elements::{background::BackgroundElement, PopupMappedInternal},
+    iced::elements::{
+        background::BackgroundElement,
+        badge::{badge_element, BadgeElement},
+        crashed_applet::{crashed_applet_element, CrashedAppletElement},
+        PopupMappedInternal,
+    },
     xdg_shell_wrapper::{
         client::handlers::overlap::OverlapNotifyV1,
         client_state::{ClientFocus, FocusStatus},
         server_state::{ServerFocus, ServerPtrFocus},
         shared_state::GlobalState,
         space::{
-            ClientEglDisplay, ClientEglSurface, PanelPopup, SpaceEvent, Visibility, WrapperPopup,
-            WrapperSpace,
+            ClientEglDisplay, ClientEglSurface, PanelPopup, SpaceEvent, Visibility, VisibilityKind,
+            WrapperPopup, WrapperSpace,
         },
         util::smootherstep,
         wp_security_context::SecurityContextManager,
@@ -37,12 +45,18 @@ use sctk::{
     reexports::{
         calloop,
         client::{
-            protocol::{wl_display::WlDisplay, wl_output as c_wl_output},
+            protocol::{
+                wl_display::WlDisplay, wl_output as c_wl_output, wl_surface as c_wl_surface,
+            },
             Proxy, QueueHandle,
         },
     },
+    seat::{
+        keyboard::Keysym,
+        pointer::{CursorIcon, PointerEvent, PointerEventKind, BTN_LEFT},
+    },
     shell::{
-        wlr_layer::{LayerSurface, LayerSurfaceConfigure},
+        wlr_layer::{KeyboardInteractivity, Layer, LayerSurface, LayerSurfaceConfigure},
         xdg::XdgPositioner,
         WaylandSurface,
     },
@@ -58,22 +72,25 @@ use smithay::{
         },
         renderer::{damage::OutputDamageTracker, gles::GlesRenderer, Bind, Unbind},
     },
-    desktop::{PopupManager, Space},
+    desktop::{space::SpaceElement, PopupManager, Space, Window},
     output::Output,
     reexports::{
         wayland_protocols::xdg::shell::client::xdg_positioner::{Anchor, Gravity},
-        wayland_server::{backend::ClientId, Client, DisplayHandle},
+        wayland_server::{
+            backend::{ClientId, ObjectId},
+            Client, DisplayHandle, Resource,
+        },
     },
-    utils::{Logical, Rectangle, Size},
+    utils::{IsAlive, Logical, Point, Rectangle, Size},
     wayland::{
         compositor::with_states,
         fractional_scale::with_fractional_scale,
         seat::WaylandFocus,
-        shell::xdg::{PopupSurface, PositionerState},
+        shell::xdg::{PopupSurface, PositionerState, ToplevelSurface},
     },
 };
 use tokio::sync::{mpsc, oneshot};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use wayland_egl::WlEglSurface;
 use wayland_protocols::{
     wp::{
@@ -84,21 +101,146 @@ use wayland_protocols::{
     xdg::shell::client::xdg_positioner::ConstraintAdjustment,
 };
 
-use cosmic_panel_config::{CosmicPanelBackground, CosmicPanelConfig, PanelAnchor};
+use cosmic_panel_config::{
+    BorderRadius, ClickAction, ClickZone, CosmicPanelBackground, CosmicPanelConfig, Easing,
+    ExpandAnimation, OversizedPopup, PanelAnchor, PopupOutputBounds,
+};
 
 use crate::{iced::elements::CosmicMappedInternal, PanelCalloopMsg};
 
 use super::{
+    background_image::NineSliceBackground,
     corner_element::{init_shaders, RoundedRectangleSettings},
-    layout::OverflowSection,
+    desaturate,
+    layout::{LayoutOutcome, OverflowSection},
+    persisted_state::PersistedState,
+    render::RenderStats,
 };
 
+/// maps the progress of an animation [0, 1] -> [0, 1] using an easing curve
+fn ease(easing: Easing, t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    match easing {
+        Easing::Linear => t,
+        Easing::EaseIn => t.powi(3),
+        Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+        Easing::Smootherstep => smootherstep(t),
+    }
+}
+
+/// round a logical length to the nearest length which covers a whole number of
+/// physical pixels at the given scale
+///
+/// Fractional scales are multiples of 1/120, so such a length always exists,
+/// but it is only used if the step between them is small.
+fn snap_to_pixel_grid(len: i32, scale: f64) -> i32 {
+    const MAX_STEP: i32 = 8;
+    let numerator = (scale * 120.).round() as i32;
+    if numerator <= 0 {
+        return len;
+    }
+    let (mut a, mut b) = (numerator, 120);
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    let step = 120 / a;
+    if step > MAX_STEP {
+        return len;
+    }
+    (len as f64 / step as f64).round() as i32 * step
+}
+
+/// maximum distance in logical pixels between the clicks of a double click
+const DOUBLE_CLICK_DISTANCE: i32 = 4;
+
+/// time without a frame callback after which the panel is considered occluded
+///
+/// Layer shell has no occlusion state, but compositors withhold frame callbacks
+/// from surfaces which are not visible.
+const OCCLUSION_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// fraction of the scale used for rendering while transitioning, if enabled
+const TRANSITION_RENDER_SCALE: f64 = 0.5;
+
+/// duration of the animation of the fill of the progress indicator
+const PROGRESS_ANIMATION: Duration = Duration::from_millis(250);
+
+/// duration of the fade of an applet to its hovered or unhovered opacity, and
+/// of the popup backdrop
+const FADE_DURATION: Duration = Duration::from_millis(150);
+
+/// forces the version of the GL context, as `major.minor`
+const GL_VERSION_ENV: &str = "COSMIC_PANEL_GL_VERSION";
+/// falls back to rendering with software when set to `1`, if no GL context
+/// can be created
+const SOFTWARE_FALLBACK_ENV: &str = "COSMIC_PANEL_SOFTWARE_FALLBACK";
+
+/// whether the panel fell back to rendering with software, so that the
+/// applets render with software too, and other commands do not
+pub(crate) static SOFTWARE_RENDERING: AtomicBool = AtomicBool::new(false);
+
+/// fall back to rendering with software, if the environment allows it,
+/// returning whether a display should be initialized again to try it
+///
+/// Mesa picks the driver from `LIBGL_ALWAYS_SOFTWARE` of the process when a
+/// display is initialized, so it is set for the panel, and removed from the
+/// commands it runs which are not applets.
+fn enable_software_fallback() -> bool {
+    if SOFTWARE_RENDERING.load(Ordering::SeqCst)
+        || !std::env::var(SOFTWARE_FALLBACK_ENV).is_ok_and(|v| v == "1")
+        || std::env::var_os("LIBGL_ALWAYS_SOFTWARE").is_some()
+    {
+        return false;
+    }
+    warn!("Falling back to rendering with software");
+    std::env::set_var("LIBGL_ALWAYS_SOFTWARE", "1");
+    SOFTWARE_RENDERING.store(true, Ordering::SeqCst);
+    true
+}
+
+/// versions of the GL context to try in order
+///
+/// A version forced by the environment is the only one tried.
+fn gl_versions() -> Vec<(u8, u8)> {
+    if let Ok(version) = std::env::var(GL_VERSION_ENV) {
+        let parsed = version
+            .split_once('.')
+            .and_then(|(major, minor)| Some((major.parse().ok()?, minor.parse().ok()?)));
+        match parsed {
+            Some(version) => return vec![version],
+            None => error!("Invalid {}: {}", GL_VERSION_ENV, version),
+        }
+    }
+    vec![(3, 0), (2, 0)]
+}
+
+/// create a GL context with the first version which succeeds
+fn new_egl_context(display: &EGLDisplay) -> anyhow::Result<EGLContext> {
+    let mut last_err = None;
+    for version in gl_versions() {
+        match EGLContext::new_with_config(
+            display,
+            GlAttributes { version, profile: None, debug: cfg!(debug_assertions), vsync: false },
+            PixelFormatRequirements::_8_bit(),
+        ) {
+            Ok(egl_context) => return Ok(egl_context),
+            Err(err) => {
+                warn!("Failed to create a GL {}.{} context: {:?}", version.0, version.1, err);
+                last_err = Some(err);
+            },
+        }
+    }
+    Err(last_err.map_or_else(|| anyhow::anyhow!("No GL version to try"), Into::into))
+}
+
 pub enum AppletMsg {
     NewProcess(String, Process),
     NewNotificationsProcess(String, Process, Vec<(String, String)>, Vec<OwnedFd>),
     NeedNewNotificationFd(oneshot::Sender<OwnedFd>),
     ClientSocketPair(ClientId),
     Cleanup(String),
+    RunCommand(String),
+    EmitSignal { panel: String, name: String },
 }
 
 impl Debug for AppletMsg {
@@ -116,6 +258,10 @@ impl Debug for AppletMsg {
             },
             Self::ClientSocketPair(arg0) => f.debug_tuple("ClientSocketPair").field(arg0).finish(),
             Self::Cleanup(arg0) => f.debug_tuple("Cleanup").field(arg0).finish(),
+            Self::RunCommand(arg0) => f.debug_tuple("RunCommand").field(arg0).finish(),
+            Self::EmitSignal { panel, name } => {
+                f.debug_struct("EmitSignal").field("panel", panel).field("name", name).finish()
+            },
         }
     }
 }
@@ -125,9 +271,15 @@ pub type Clients = Arc<Mutex<Vec<PanelClient>>>;
 #[derive(Debug)]
 pub struct PanelClient {
     pub name: String,
+    /// Index of this instance among the applets of the panel with the same
+    /// name.
+    pub instance: u32,
     pub client: Client,
     pub stream: Option<UnixStream>,
     pub security_ctx: Option<WpSecurityContextV1>,
+    /// If set, the connection of this applet to the host compositor is
+    /// restricted to unprivileged protocols.
+    pub sandboxed: bool,
     pub exec: Option<String>,
     pub minimize_priority: Option<u32>,
     pub requests_wayland_display: Option<bool>,
@@ -136,6 +288,24 @@ pub struct PanelClient {
     pub shrink_min_size: Option<ClientShrinkSize>,
     /// If there is an existing popup, this applet with be pressed when hovered.
     pub auto_popup_hover_press: Option<AppletAutoClickAnchor>,
+    /// If set, popups of this applet are anchored to the pointer.
+    pub popup_at_pointer: Option<bool>,
+    /// If false, this applet draws its own background and is not clipped to
+    /// rounded corners.
+    pub rounded_clip: Option<bool>,
+    /// Length reserved along the panel for this applet, even if its window
+    /// is empty because it draws everything in popups.
+    pub reserved_slot: Option<u32>,
+    /// How clicks on this applet are handled.
+    pub click_policy: Option<AppletClickPolicy>,
+    /// Environment the applet was last spawned with.
+    pub env: Vec<(String, String)>,
+    /// If true, this applet is never moved to an overflow popup.
+    pub pin: bool,
+    /// When the applet recently crashed, for the restart backoff.
+    pub crashes: Vec<Instant>,
+    /// If true, this applet crashed too often and is no longer restarted.
+    pub crashed: bool,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -184,13 +354,38 @@ impl FromStr for AppletAutoClickAnchor {
     }
 }
 
+/// how the panel handles a click on an applet
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AppletClickPolicy {
+    /// the click is forwarded to the applet
+    #[default]
+    Forward,
+    /// while a popup of the applet is open, the click closes it instead of
+    /// being forwarded
+    Toggle,
+}
+
+impl FromStr for AppletClickPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "forward" => Ok(Self::Forward),
+            "toggle" => Ok(Self::Toggle),
+            _ => Err(()),
+        }
+    }
+}
+
 impl PanelClient {
     pub fn new(name: String, client: Client, stream: Option<UnixStream>) -> Self {
         Self {
             name,
+            instance: 0,
             client,
             stream,
             security_ctx: None,
+            sandboxed: false,
             exec: None,
             minimize_priority: None,
             requests_wayland_display: None,
@@ -198,8 +393,31 @@ impl PanelClient {
             auto_popup_hover_press: None,
             shrink_priority: None,
             shrink_min_size: None,
+            popup_at_pointer: None,
+            rounded_clip: None,
+            reserved_slot: None,
+            click_policy: None,
+            env: Vec::new(),
+            pin: false,
+            crashes: Vec::new(),
+            crashed: false,
         }
     }
+
+    /// id of this instance of the applet, which keys its state in the panel
+    pub fn instance_id(&self) -> String {
+        instance_id(&self.name, self.instance)
+    }
+}
+
+/// id of an instance of an applet, which is its name for the first instance,
+/// and its name and index otherwise, e.g. `com.system76.CosmicAppList#1`
+pub(crate) fn instance_id(name: &str, instance: u32) -> String {
+    if instance == 0 {
+        name.to_string()
+    } else {
+        format!("{}#{}", name, instance)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -218,17 +436,21 @@ pub struct AnimateState {
     started_at: Instant,
     progress: f32,
     duration: Duration,
+    easing: Easing,
 }
 
 #[derive(Debug, Clone)]
 pub struct PanelColors {
     pub theme: cosmic::Theme,
     pub color_override: Option<[f32; 4]>,
+    /// RGB background color used in place of that of the theme, if the theme
+    /// failed to load
+    pub fallback_bg: Option<[f32; 3]>,
 }
 
 impl PanelColors {
     pub fn new(theme: cosmic::Theme) -> Self {
-        Self { theme, color_override: None }
+        Self { theme, color_override: None, fallback_bg: None }
     }
 
     pub fn with_color_override(mut self, color_override: Option<[f32; 4]>) -> Self {
@@ -236,8 +458,16 @@ impl PanelColors {
         self
     }
 
+    pub fn with_fallback_bg(mut self, fallback_bg: Option<[f32; 3]>) -> Self {
+        self.fallback_bg = fallback_bg;
+        self
+    }
+
     pub fn bg_color(&self, alpha: f32) -> [f32; 4] {
         self.color_override.unwrap_or_else(|| {
+            if let Some([r, g, b]) = self.fallback_bg {
+                return [r, g, b, alpha];
+            }
             let c = self.theme.cosmic().bg_color();
             [c.red, c.green, c.blue, alpha]
         })
@@ -288,8 +518,20 @@ pub struct PanelSpace {
     pub colors: PanelColors,
     pub applet_tx: mpsc::Sender<AppletMsg>,
     pub input_region: Option<Region>,
+    pub opaque_region: Option<Region>,
     pub has_frame: bool,
+    /// time of the pending frame request of the layer surface
+    pub frame_requested: Option<Instant>,
     pub scale: f64,
+    /// scale of the last rendered frame, which is lower than `scale` while
+    /// rendering at a reduced resolution
+    pub rendered_scale: f64,
+    /// saturation of the applets in the last rendered frame
+    pub rendered_saturation: f32,
+    /// statistics of the last rendered frame
+    pub(crate) render_stats: RenderStats,
+    /// time the render statistics were last logged, with the frame count then
+    pub(crate) render_stats_logged: (Instant, u64),
     pub output_has_toplevel: bool,
     pub security_context_manager: Option<SecurityContextManager>,
     pub animate_state: Option<AnimateState>,
@@ -300,6 +542,11 @@ pub struct PanelSpace {
     pub scale_change_retries: u32,
     /// Extra gap for stacked panels. Logical coordinate space.
     pub additional_gap: i32,
+    /// Lengthwise margins leaving the corners to panels on the adjacent edges.
+    pub(crate) corner_margins: (i32, i32),
+    /// Fraction of the output length allotted to the panel when it shares its
+    /// edge with split panels.
+    pub(crate) length_budget: Option<f32>,
     /// Target gap for the panel on its anchored edge. Logical coordinate space.
     pub anchor_gap: i32,
     pub loop_handle: calloop::LoopHandle<'static, GlobalState>,
@@ -309,6 +556,7 @@ pub struct PanelSpace {
     pub left_overflow_popup_id: id::Id,
     pub center_overflow_popup_id: id::Id,
     pub right_overflow_popup_id: id::Id,
+    pub start_button_id: id::Id,
     pub overflow_popup: Option<(PanelPopup, OverflowSection)>,
     pub remap_attempts: u32,
     pub background_element: Option<BackgroundElement>,
@@ -316,16 +564,212 @@ pub struct PanelSpace {
     pub(crate) toplevel_overlaps: HashSet<wayland_backend::client::ObjectId>,
     pub(crate) notification_subscription: Option<ZcosmicOverlapNotificationV1>,
     pub(crate) overlap_notify: Option<OverlapNotifyV1>,
+    /// Last pointer position on the layer surface. Logical coordinate space.
+    pub last_pointer_pos: Option<Point<i32, Logical>>,
+    /// Number of consecutive failed attempts to initialize EGL for the panel.
+    pub egl_failures: u32,
+    /// Keeps the panel visible, even if it is configured to autohide.
+    pub pinned: bool,
+    /// Opacity applied at runtime, in place of the configured opacity.
+    pub opacity_override: Option<f32>,
+    /// Runtime state last saved or restored, to skip writing it unchanged.
+    pub(crate) persisted_state: Option<PersistedState>,
+    /// Image drawn in place of the background fill, if configured.
+    pub background_image: Option<NineSliceBackground>,
+    /// Exclusive zone last set on the layer surface.
+    pub(crate) exclusive_zone: Cell<i32>,
+    /// Names of applets which keep running, but are not shown.
+    pub hidden_applets: HashSet<String>,
+    /// Whether the collapsible applets are moved to the overflow popups.
+    pub collapsed: bool,
+    /// Whether something is dragged over the panel, highlighting drop targets.
+    pub drag_target: bool,
+    /// Ids of the drop target highlights, reused between frames.
+    pub(crate) drag_highlight_ids: Vec<smithay::backend::renderer::element::Id>,
+    /// Logical geometries of the dividers between the wings and the center.
+    pub(crate) section_dividers: Vec<Rectangle<i32, Logical>>,
+    /// Ids of the section dividers, reused between frames.
+    pub(crate) section_divider_ids: Vec<smithay::backend::renderer::element::Id>,
+    /// Windows of hidden applets, unmapped until the applet is shown again.
+    pub(crate) hidden_windows: Vec<Window>,
+    /// Logical geometries of all outputs, used to let popups span outputs.
+    pub(crate) output_geometries: Vec<Rectangle<i32, Logical>>,
+    /// Whether the last press was intercepted, so its release is as well.
+    pub(crate) intercepted_press: bool,
+    /// Time and location of the last click on empty space of the panel.
+    pub(crate) last_empty_click: Option<(Instant, Point<i32, Logical>)>,
+    /// Distance in logical pixels of a swipe revealing the hidden panel.
+    pub(crate) reveal_swipe: Option<f64>,
+    /// Last index of each applet window in its section, by surface.
+    pub(crate) applet_indices: HashMap<ObjectId, usize>,
+    /// Applet windows gliding to their new location after a reorder.
+    pub(crate) applet_moves: HashMap<ObjectId, AppletMove>,
+    /// When the panel started waiting for its applets to map before its first
+    /// frame, if it is still waiting.
+    pub(crate) awaiting_applets: Option<Instant>,
+    /// Timer waking the panel to draw once it waited for its applets for too
+    /// long.
+    pub(crate) awaiting_applets_timer: Option<calloop::RegistrationToken>,
+    /// Timer retrying to create the surfaces of popups which can't be
+    /// repositioned.
+    pub(crate) popup_retry_timer: Option<calloop::RegistrationToken>,
+    /// Last parent location of each reactive popup, by surface.
+    pub(crate) reactive_popup_anchors: HashMap<ObjectId, Point<i32, Logical>>,
+    /// Whether keyboard interactivity was requested for the layer surface
+    /// while a popup of an applet holds a grab.
+    pub(crate) popup_keyboard_grab: bool,
+    /// Badges drawn over applets with the count they reported, by applet
+    /// instance id.
+    pub(crate) applet_badges: HashMap<String, BadgeElement>,
+    /// Badge counts set before the applet was spawned, by instance id, which
+    /// are applied once it is.
+    pub(crate) pending_badges: HashMap<String, u32>,
+    /// Placeholders in the slots of applets which are no longer restarted or
+    /// not started yet, with their location, by applet instance id.
+    pub(crate) crashed_applets: HashMap<String, (CrashedAppletElement, Point<i32, Logical>)>,
+    /// Timers starting applets with a start delay, by applet instance id.
+    pub(crate) pending_starts: Vec<(String, calloop::RegistrationToken)>,
+    /// Config of the panel this panel mirrors on another output, if it is a
+    /// mirror.
+    pub(crate) mirror_of: Option<CosmicPanelConfig>,
+    /// Gap set at runtime in place of the configured anchor gap, if any.
+    pub(crate) gap_override: Option<u16>,
+    /// Transient animation of the anchor gap, if one is running.
+    pub(crate) gap_animation: Option<GapAnimation>,
+    /// When the panel returns to its configured layer, if it is raised to the
+    /// overlay layer.
+    pub(crate) layer_boost: Option<Instant>,
+    /// System-wide progress shown along the anchored edge, if any.
+    pub(crate) progress: Option<ProgressIndicator>,
+    /// Id of the progress indicator, reused between frames.
+    pub(crate) progress_id: smithay::backend::renderer::element::Id,
+    /// Applet windows which are hovered or fading after the pointer left them,
+    /// by surface.
+    pub(crate) hover_fades: HashMap<ObjectId, Fade>,
+    /// Backdrop over the panel while a popup is open, if shown or fading out.
+    pub(crate) backdrop: Option<Fade>,
+    /// Fade of the background towards the opacity configured for an output
+    /// without windows, if shown or fading out.
+    pub(crate) empty_output: Option<Fade>,
+    /// Id of the popup backdrop, reused between frames.
+    pub(crate) backdrop_id: smithay::backend::renderer::element::Id,
+    /// Commit of the popup backdrop, incremented while it fades.
+    pub(crate) backdrop_commit: smithay::backend::renderer::utils::CommitCounter,
+    /// Index of the applet focused by keyboard navigation, in the order of the
+    /// panel, while navigating.
+    pub(crate) keyboard_nav: Option<usize>,
+    /// Id of the highlight of the applet focused by keyboard navigation.
+    pub(crate) keyboard_nav_id: smithay::backend::renderer::element::Id,
+    /// Commit of the keyboard navigation highlight, incremented when the focus
+    /// moves.
+    pub(crate) keyboard_nav_commit: smithay::backend::renderer::utils::CommitCounter,
+}
+
+/// transient animation of the gap between the panel and the edge of its
+/// output
+#[derive(Debug, Clone, Copy)]
+pub struct GapAnimation {
+    /// gap when the animation starts
+    from: f32,
+    /// gap when the animation ends
+    to: u16,
+    started: Instant,
+    animation: ExpandAnimation,
+}
+
+impl GapAnimation {
+    fn progress(&self) -> f32 {
+        let duration = Duration::from_millis(self.animation.duration.into());
+        (self.started.elapsed().as_secs_f32() / duration.as_secs_f32().max(f32::EPSILON)).min(1.)
+    }
+
+    fn gap(&self) -> f32 {
+        self.from + (self.to as f32 - self.from) * ease(self.animation.easing, self.progress())
+    }
+}
+
+/// move an applet to the given index of a section of the plugin lists of a
+/// config
+pub(crate) fn move_plugin(config: &mut CosmicPanelConfig, name: &str, to: Alignment, index: usize) {
+    let (mut start, mut end) = config.plugins_wings.take().unwrap_or_default();
+    let mut center = config.plugins_center.take().unwrap_or_default();
+    for list in [&mut start, &mut center, &mut end] {
+        list.retain(|n| n != name);
+    }
+    let list = match to {
+        Alignment::Left => &mut start,
+        Alignment::Center => &mut center,
+        Alignment::Right => &mut end,
+    };
+    list.insert(index.min(list.len()), name.to_string());
+    config.plugins_wings = Some((start, end));
+    config.plugins_center = Some(center);
+}
+
+/// progress shown as a bar filling along the anchored edge of the panel
+#[derive(Debug, Clone, Copy)]
+pub struct ProgressIndicator {
+    /// fill when the animation to the progress starts
+    from: f32,
+    /// progress from 0.0 to 1.0
+    to: f32,
+    started: Instant,
+}
+
+impl ProgressIndicator {
+    fn progress(&self) -> f32 {
+        (self.started.elapsed().as_secs_f32() / PROGRESS_ANIMATION.as_secs_f32()).min(1.)
+    }
+
+    /// fraction of the length of the panel which is filled
+    pub(crate) fn fill(&self) -> f32 {
+        self.from + (self.to - self.from) * smootherstep(self.progress())
+    }
+}
+
+/// fade towards being shown or hidden, such as of the hovered opacity of an
+/// applet window
+#[derive(Debug, Clone, Copy)]
+pub struct Fade {
+    /// level when the fade starts
+    from: f32,
+    shown: bool,
+    started: Instant,
+}
+
+impl Fade {
+    /// start fading from the current level of the previous fade, if any
+    fn start(prev: Option<Fade>, shown: bool) -> Self {
+        Self { from: prev.map_or(0., |f| f.level()), shown, started: Instant::now() }
+    }
+
+    fn progress(&self) -> f32 {
+        (self.started.elapsed().as_secs_f32() / FADE_DURATION.as_secs_f32()).min(1.)
+    }
+
+    /// level from 0.0 when hidden to 1.0 when shown
+    pub(crate) fn level(&self) -> f32 {
+        let to = if self.shown { 1. } else { 0. };
+        self.from + (to - self.from) * smootherstep(self.progress())
+    }
+}
+
+/// movement of a reordered applet window towards its mapped location
+#[derive(Debug, Clone, Copy)]
+pub struct AppletMove {
+    /// location the window is drawn at when the movement starts
+    from: Point<f64, Logical>,
+    started: Instant,
 }
 
 impl PanelSpace {
     /// create a new space for the cosmic panel
     pub fn new(
-        config: CosmicPanelConfig,
+        mut config: CosmicPanelConfig,
         c_focused_surface: Rc<RefCell<ClientFocus>>,
         c_hovered_surface: Rc<RefCell<ClientFocus>>,
         applet_tx: mpsc::Sender<AppletMsg>,
-        theme: cosmic::Theme,
+        colors: PanelColors,
         s_display: DisplayHandle,
         security_context_manager: Option<SecurityContextManager>,
         conn: &Connection,
@@ -333,8 +777,9 @@ impl PanelSpace {
         visibility: Visibility,
         loop_handle: calloop::LoopHandle<'static, GlobalState>,
     ) -> Self {
+        config.apply_orientation_overrides();
         let name = format!("{}-{}", config.name, config.output);
-        Self {
+        let mut panel = Self {
             config,
             space: Space::default(),
             overflow_left: Space::default(),
@@ -362,14 +807,20 @@ impl PanelSpace {
             c_hovered_surface,
             s_focused_surface: Default::default(),
             s_hovered_surface: Default::default(),
-            colors: PanelColors::new(theme),
+            colors,
             applet_tx,
             actual_size: (0, 0).into(),
             input_region: None,
+            opaque_region: None,
             damage_tracked_renderer: None,
             is_dirty: false,
             has_frame: true,
+            frame_requested: None,
             scale: 1.0,
+            rendered_scale: 1.0,
+            rendered_saturation: 1.0,
+            render_stats: RenderStats::default(),
+            render_stats_logged: (Instant::now(), 0),
             output_has_toplevel: false,
             security_context_manager,
             animate_state: None,
@@ -380,6 +831,8 @@ impl PanelSpace {
             panel_rect_settings: RoundedRectangleSettings::default(),
             scale_change_retries: 0,
             additional_gap: 0,
+            corner_margins: (0, 0),
+            length_budget: None,
             loop_handle,
             left_overflow_button_id: id::Id::new(format!("{}-left-overflow-button", name)),
             center_overflow_button_id: id::Id::new(format!("{}-center-overflow-button", name)),
@@ -387,6 +840,7 @@ impl PanelSpace {
             left_overflow_popup_id: id::Id::new(format!("{}-left-overflow-popup", name)),
             center_overflow_popup_id: id::Id::new(format!("{}-center-overflow-popup", name)),
             right_overflow_popup_id: id::Id::new(format!("{}-right-overflow-popup", name)),
+            start_button_id: id::Id::new(format!("{}-start-button", name)),
             overflow_popup: None,
             remap_attempts: 0,
             background_element: None,
@@ -395,85 +849,1086 @@ impl PanelSpace {
             toplevel_overlaps: HashSet::new(),
             notification_subscription: None,
             overlap_notify: None,
+            last_pointer_pos: None,
+            egl_failures: 0,
+            pinned: false,
+            opacity_override: None,
+            persisted_state: None,
+            background_image: None,
+            exclusive_zone: Cell::new(0),
+            hidden_applets: HashSet::new(),
+            collapsed: false,
+            drag_target: false,
+            drag_highlight_ids: Vec::new(),
+            section_dividers: Vec::new(),
+            section_divider_ids: Vec::new(),
+            hidden_windows: Vec::new(),
+            output_geometries: Vec::new(),
+            intercepted_press: false,
+            last_empty_click: None,
+            reveal_swipe: None,
+            applet_indices: HashMap::new(),
+            applet_moves: HashMap::new(),
+            awaiting_applets: None,
+            awaiting_applets_timer: None,
+            popup_retry_timer: None,
+            reactive_popup_anchors: HashMap::new(),
+            popup_keyboard_grab: false,
+            applet_badges: HashMap::new(),
+            pending_badges: HashMap::new(),
+            crashed_applets: HashMap::new(),
+            pending_starts: Vec::new(),
+            mirror_of: None,
+            gap_override: None,
+            gap_animation: None,
+            layer_boost: None,
+            progress: None,
+            progress_id: smithay::backend::renderer::element::Id::new(),
+            hover_fades: HashMap::new(),
+            backdrop: None,
+            empty_output: None,
+            backdrop_id: smithay::backend::renderer::element::Id::new(),
+            backdrop_commit: Default::default(),
+            keyboard_nav: None,
+            keyboard_nav_id: smithay::backend::renderer::element::Id::new(),
+            keyboard_nav_commit: Default::default(),
+        };
+        if let Some(timeout) = panel.config.wait_for_applets {
+            panel.awaiting_applets = Some(Instant::now());
+            // nothing else may wake the panel when the timeout elapses
+            panel.awaiting_applets_timer = panel
+                .loop_handle
+                .insert_source(
+                    calloop::timer::Timer::from_duration(Duration::from_millis(timeout.into())),
+                    |_, _, state| {
+                        state.space.applets_wait_elapsed();
+                        calloop::timer::TimeoutAction::Drop
+                    },
+                )
+                .map_err(|err| error!("Failed to schedule the applet timeout: {}", err))
+                .ok();
         }
+        panel.load_background_image();
+        panel
     }
 
-    pub fn crosswise(&self) -> i32 {
-        if self.config.is_horizontal() {
-            self.dimensions.h
-        } else {
-            self.dimensions.w
+    /// load the configured background image, reusing the loaded one if the path
+    /// and insets are unchanged
+    fn load_background_image(&mut self) {
+        let Some(path) = self.config.background_image.as_ref() else {
+            self.background_image = None;
+            return;
+        };
+        if self
+            .background_image
+            .as_ref()
+            .is_some_and(|i| &i.path == path && i.insets == self.config.background_image_insets)
+        {
+            return;
         }
+        self.background_image =
+            match NineSliceBackground::load(path, self.config.background_image_insets) {
+                Ok(image) => Some(image),
+                Err(err) => {
+                    error!("Failed to load background image {:?}: {:?}", path, err);
+                    None
+                },
+            };
     }
 
-    pub fn bg_color(&self) -> [f32; 4] {
-        if let Some(animatable_state) = self.animate_state.as_ref() {
-            animatable_state.cur.bg_color
-        } else {
-            self.colors.bg_color(self.config.opacity)
+    /// key of the persisted state, unique for each output of a panel on all
+    /// outputs
+    fn state_key(&self) -> Option<String> {
+        let output = self.output.as_ref()?.1.name();
+        Some(format!("{}-{}", self.config.name, output))
+    }
+
+    /// restore the runtime state saved by a previous instance of the panel on
+    /// its output
+    pub(crate) fn restore_state(&mut self) {
+        if !self.config.persist_state {
+            return;
+        }
+        let Some(state) = self.state_key().and_then(|key| PersistedState::load(&key)) else {
+            return;
+        };
+        self.pinned = state.pinned;
+        self.opacity_override = state.opacity;
+        self.collapsed = state.collapsed;
+        self.persisted_state = Some(state.clone());
+        // a panel without autohide is always visible
+        if self.config.autohide().is_none() {
+            return;
+        }
+        // a pinned panel starts out visible instead of revealing itself
+        if state.pinned {
+            self.visibility = Visibility::Visible;
+            return;
+        }
+        // let the transition run instead of snapping to the saved visibility
+        if state.visible {
+            if matches!(self.visibility, Visibility::Hidden) {
+                self.visibility = Visibility::TransitionToVisible {
+                    last_instant: Instant::now(),
+                    progress: Duration::new(0, 0),
+                    prev_margin: 0,
+                };
+            }
+        } else if matches!(self.visibility, Visibility::Visible) {
+            self.visibility = Visibility::TransitionToHidden {
+                last_instant: Instant::now(),
+                progress: Duration::new(0, 0),
+                prev_margin: 0,
+            };
         }
     }
 
-    pub fn border_radius(&self) -> u32 {
-        if let Some(animatable_state) = self.animate_state.as_ref() {
-            animatable_state.cur.border_radius
+    /// save the runtime state so it can be restored after a restart, if it
+    /// changed since it was last saved
+    pub fn persist_state(&mut self) {
+        if !self.config.persist_state {
+            return;
+        }
+        let Some(key) = self.state_key() else {
+            return;
+        };
+        let state = PersistedState {
+            visible: !matches!(
+                self.visibility,
+                Visibility::Hidden
+                    | Visibility::TransitionToHidden { .. }
+                    | Visibility::Peek { .. }
+                    | Visibility::TransitionToPeek { .. }
+                    | Visibility::TransitionFromPeek { to_visible: false, .. }
+            ),
+            pinned: self.pinned,
+            opacity: self.opacity_override,
+            collapsed: self.collapsed,
+        };
+        if self.persisted_state.as_ref() == Some(&state) {
+            return;
+        }
+        match state.save(&key) {
+            Ok(()) => self.persisted_state = Some(state),
+            Err(err) => error!("Failed to persist panel state: {:?}", err),
+        }
+    }
+
+    /// the current visibility of the panel
+    ///
+    /// Use [`Self::set_pinned`] to force the panel to stay visible.
+    pub fn visibility_kind(&self) -> VisibilityKind {
+        if self.config.autohide().is_none() {
+            return VisibilityKind::Visible;
+        }
+        self.visibility.kind()
+    }
+
+    pub fn set_pinned(&mut self, pinned: bool) {
+        if self.pinned == pinned {
+            return;
+        }
+        self.pinned = pinned;
+        self.is_dirty = true;
+        self.persist_state();
+    }
+
+    /// hide or show an applet without respawning it
+    pub fn set_applet_visible(&mut self, name: &str, visible: bool) {
+        let changed = if visible {
+            self.hidden_applets.remove(name)
         } else {
-            self.config.border_radius
+            self.hidden_applets.insert(name.to_string())
+        };
+        if !changed {
+            return;
+        }
+        if visible {
+            // hidden windows are unmapped during layout
+            let hidden_clients = self.hidden_applet_clients();
+            let (shown, hidden): (Vec<_>, Vec<_>) =
+                self.hidden_windows.drain(..).filter(|w| w.alive()).partition(|w| {
+                    !w.toplevel()
+                        .and_then(|t| t.wl_surface().client())
+                        .is_some_and(|c| hidden_clients.contains(&c.id()))
+                });
+            self.hidden_windows = hidden;
+            for w in shown {
+                self.space.map_element(CosmicMappedInternal::Window(w), (0, 0), false);
+            }
         }
+        self.is_dirty = true;
     }
 
-    pub fn gap(&self) -> u16 {
-        if let Some(animatable_state) = self.animate_state.as_ref() {
-            animatable_state.cur.gap
+    /// move an applet to the given index of a section
+    pub fn move_applet_section(&mut self, name: &str, to: Alignment, index: usize) {
+        let client = [&self.clients_left, &self.clients_center, &self.clients_right]
+            .into_iter()
+            .find_map(|clients| {
+                let mut clients = clients.lock().unwrap();
+                let i = clients.iter().position(|c| c.name == name)?;
+                Some(clients.remove(i))
+            });
+        let Some(client) = client else {
+            warn!("Failed to move applet {}: not found", name);
+            return;
+        };
+        let mut clients = match to {
+            Alignment::Left => &self.clients_left,
+            Alignment::Center => &self.clients_center,
+            Alignment::Right => &self.clients_right,
+        }
+        .lock()
+        .unwrap();
+        clients.insert(index.min(clients.len()), client);
+        drop(clients);
+
+        move_plugin(&mut self.config, name, to, index);
+        self.is_dirty = true;
+    }
+
+    /// ids of the clients of hidden applets
+    pub(crate) fn hidden_applet_clients(&self) -> Vec<ClientId> {
+        if self.hidden_applets.is_empty() {
+            return Vec::new();
+        }
+        [&self.clients_left, &self.clients_center, &self.clients_right]
+            .into_iter()
+            .flat_map(|clients| {
+                clients
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|c| self.hidden_applets.contains(&c.name))
+                    .map(|c| c.client.id())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// whether the panel may draw its first frame
+    ///
+    /// If configured, the panel waits until every spawned applet has mapped a
+    /// window, or until the timeout elapsed, so applets don't pop in one by
+    /// one.
+    pub(crate) fn applets_ready(&mut self) -> bool {
+        let Some(started) = self.awaiting_applets else {
+            return true;
+        };
+        let timeout =
+            Duration::from_millis(self.config.wait_for_applets.unwrap_or_default().into());
+        let mapped: HashSet<_> = self
+            .space
+            .elements()
+            .filter_map(|e| match e {
+                CosmicMappedInternal::Window(w) => Some(w.clone()),
+                _ => None,
+            })
+            .chain(
+                [&self.overflow_left, &self.overflow_center, &self.overflow_right]
+                    .into_iter()
+                    .flat_map(|s| s.elements())
+                    .filter_map(|e| match e {
+                        PopupMappedInternal::Window(w) => Some(w.clone()),
+                        _ => None,
+                    }),
+            )
+            .chain(self.hidden_windows.iter().cloned())
+            .filter_map(|w| w.toplevel().and_then(|t| t.wl_surface().client()).map(|c| c.id()))
+            .collect();
+        let expected = [&self.clients_left, &self.clients_center, &self.clients_right]
+            .into_iter()
+            .flat_map(|clients| {
+                clients.lock().unwrap().iter().map(|c| c.client.id()).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        // the applets may not be spawned yet
+        let spawned = !expected.is_empty() || !self.config.has_applets();
+        if spawned && expected.iter().all(|c| mapped.contains(c)) {
+            self.awaiting_applets = None;
+        } else if started.elapsed() >= timeout {
+            info!(
+                "Drawing panel {} before all applets mapped, {} of {} are missing",
+                self.config.name,
+                expected.iter().filter(|c| !mapped.contains(c)).count(),
+                expected.len()
+            );
+            self.awaiting_applets = None;
+        }
+        if self.awaiting_applets.is_none() {
+            if let Some(token) = self.awaiting_applets_timer.take() {
+                self.loop_handle.remove(token);
+            }
+        }
+        self.awaiting_applets.is_none()
+    }
+
+    /// environment the named applet was last spawned with
+    pub fn applet_env(&self, name: &str) -> Option<Vec<(String, String)>> {
+        [&self.clients_left, &self.clients_center, &self.clients_right].into_iter().find_map(
+            |clients| {
+                clients.lock().unwrap().iter().find(|c| c.name == name).map(|c| c.env.clone())
+            },
+        )
+    }
+
+    /// set the additional environment variables of the named applet, which
+    /// are used the next time it is spawned
+    pub fn set_applet_env(&mut self, name: &str, env: Vec<(String, String)>) {
+        if env.is_empty() {
+            self.config.applet_env.remove(name);
         } else {
-            self.config.get_effective_anchor_gap() as u16
+            self.config.applet_env.insert(name.to_string(), env);
         }
     }
 
-    pub fn id(&self) -> String {
-        let id = format!(
-            "panel-{}-{}-{}",
-            self.config.name,
-            self.config.output,
-            self.output.as_ref().map(|o| o.2.name.clone().unwrap_or_default()).unwrap_or_default()
+    /// ids of the clients of collapsible applets, if they are collapsed
+    pub(crate) fn collapsed_applet_clients(&self) -> Vec<ClientId> {
+        if !self.collapsed {
+            return Vec::new();
+        }
+        self.collapsible_applet_clients()
+    }
+
+    /// ids of the clients of collapsible applets
+    pub(crate) fn collapsible_applet_clients(&self) -> Vec<ClientId> {
+        if self.config.collapsible_applets.is_empty() {
+            return Vec::new();
+        }
+        [&self.clients_left, &self.clients_center, &self.clients_right]
+            .into_iter()
+            .flat_map(|clients| {
+                clients
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|c| self.config.collapsible_applets.contains(&c.name))
+                    .map(|c| c.client.id())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// ids of the clients of pinned applets, which never overflow
+    pub(crate) fn pinned_applet_clients(&self) -> Vec<ClientId> {
+        [&self.clients_left, &self.clients_center, &self.clients_right]
+            .into_iter()
+            .flat_map(|clients| {
+                clients
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|c| c.pin)
+                    .map(|c| c.client.id())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// collapse the collapsible applets into the overflow popups, or expand
+    /// them back into the panel
+    pub fn set_collapsed(&mut self, collapsed: bool) {
+        if self.collapsed == collapsed {
+            return;
+        }
+        self.collapsed = collapsed;
+        if !collapsed {
+            self.relax_all();
+        }
+        self.is_dirty = true;
+        self.persist_state();
+    }
+
+    /// ids of the clients of applets which are highlighted as drop targets
+    pub(crate) fn drop_target_clients(&self) -> Vec<ClientId> {
+        if self.config.drop_target_applets.is_empty() {
+            return Vec::new();
+        }
+        [&self.clients_left, &self.clients_center, &self.clients_right]
+            .into_iter()
+            .flat_map(|clients| {
+                clients
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|c| self.config.drop_target_applets.contains(&c.name))
+                    .map(|c| c.client.id())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// enter or leave the drag target state, in which the drop target applets
+    /// are highlighted
+    pub fn set_drag_target(&mut self, drag_target: bool) {
+        if self.drag_target == drag_target {
+            return;
+        }
+        self.drag_target = drag_target;
+        self.is_dirty = true;
+    }
+
+    /// set the count shown in the badge of an applet of the panel, by instance
+    /// id, clearing the badge if it is zero
+    ///
+    /// The count of an applet which isn't spawned yet is kept until it is.
+    pub fn set_applet_badge(&mut self, applet: &str, count: u32) {
+        self.pending_badges.remove(applet);
+        if self.applet_badges.get(applet).map(|b| b.with_program(|p| p.count)).unwrap_or(0) == count
+        {
+            return;
+        }
+        self.is_dirty = true;
+        if count == 0 {
+            self.applet_badges.remove(applet);
+            return;
+        }
+        let has_applet = [&self.clients_left, &self.clients_center, &self.clients_right]
+            .into_iter()
+            .any(|clients| clients.lock().unwrap().iter().any(|c| c.instance_id() == applet));
+        let Some(output) = self.output.as_ref().map(|o| &o.1).filter(|_| has_applet) else {
+            self.pending_badges.insert(applet.to_string(), count);
+            return;
+        };
+        let badge = badge_element(
+            count,
+            self.config.size.get_applet_icon_size(true),
+            self.loop_handle.clone(),
+            self.colors.theme.clone(),
+            self.space.id(),
         );
-        id
+        badge.output_enter(output, Default::default());
+        self.applet_badges.insert(applet.to_string(), badge);
     }
 
-    pub fn handle_focus(&mut self) {
-        let (layer_surface, layer_shell_wl_surface) =
-            if let Some(layer_surface) = self.layer.as_ref() {
-                (layer_surface, layer_surface.wl_surface())
+    /// instance ids of the applets which crashed too often to be restarted
+    pub fn crashed_applet_ids(&self) -> Vec<String> {
+        [&self.clients_left, &self.clients_center, &self.clients_right]
+            .into_iter()
+            .flat_map(|clients| {
+                clients
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|c| c.crashed)
+                    .map(PanelClient::instance_id)
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// show a placeholder in the slot of each applet which is no longer
+    /// restarted or whose start is delayed
+    pub fn sync_crashed_applets(&mut self) {
+        let crashed = self.crashed_applet_ids();
+        let pending = self
+            .pending_starts
+            .iter()
+            .map(|(name, _)| name.clone())
+            .filter(|name| !crashed.contains(name))
+            .collect::<Vec<_>>();
+        let count = self.crashed_applets.len();
+        self.crashed_applets.retain(|name, (p, _)| {
+            let is_crashed = p.with_program(|p| p.crashed);
+            if is_crashed {
+                crashed.contains(name)
             } else {
-                return;
-            };
+                pending.contains(name)
+            }
+        });
+        let changed = count != self.crashed_applets.len();
+        let Some(output) = self.output.as_ref().map(|o| o.1.clone()) else {
+            return;
+        };
+        let mut added = false;
+        let placeholders =
+            crashed.into_iter().map(|n| (n, true)).chain(pending.into_iter().map(|n| (n, false)));
+        for (name, is_crashed) in placeholders {
+            if self.crashed_applets.contains_key(&name) {
+                continue;
+            }
+            let placeholder = crashed_applet_element(
+                is_crashed,
+                self.config.size.get_applet_icon_size(true),
+                self.config.size.get_applet_padding(true) as u32,
+                self.loop_handle.clone(),
+                self.colors.theme.clone(),
+                self.space.id(),
+            );
+            placeholder.output_enter(&output, Default::default());
+            self.crashed_applets.insert(name, (placeholder, Point::default()));
+            added = true;
+        }
+        if changed || added {
+            self.is_dirty = true;
+        }
+    }
 
-        let cur_hover = {
-            let c_focused_surface = self.c_focused_surface.borrow();
-            let c_hovered_surface = self.c_hovered_surface.borrow();
-            // no transition if not configured for autohide
-            let no_hover_focus =
-                c_focused_surface.iter().all(|f| matches!(f.2, FocusStatus::LastFocused(_)))
-                    && c_hovered_surface.iter().all(|f| matches!(f.2, FocusStatus::LastFocused(_)));
-            if self.config.autohide().is_none() {
-                if no_hover_focus && self.animate_state.is_none() {
-                    self.additional_gap = 0;
-                    self.visibility = Visibility::Hidden;
-                } else {
-                    self.visibility = Visibility::Visible;
-                }
-                return;
-            };
+    /// forget the start timer of an applet, by instance id, once it is started
+    pub(crate) fn applet_started(&mut self, id: &str) {
+        if let Some(i) = self.pending_starts.iter().position(|(n, _)| n == id) {
+            self.pending_starts.remove(i);
+        }
+        self.sync_crashed_applets();
+    }
 
-            let f = c_hovered_surface.iter().fold(
-                if self.animate_state.is_some() || !self.output_has_toplevel {
-                    FocusStatus::Focused
-                } else {
-                    FocusStatus::LastFocused(self.start_instant)
-                },
-                |acc, (surface, _, f)| {
-                    if surface.is_alive()
-                        && (self.layer.as_ref().is_some_and(|s| *s.wl_surface() == *surface)
+    /// hide or show a panel with autohide
+    pub fn toggle_visibility(&mut self) {
+        if self.config.autohide().is_none() {
+            return;
+        }
+        self.visibility = match self.visibility {
+            Visibility::Hidden | Visibility::TransitionToHidden { .. } => {
+                Visibility::TransitionToVisible {
+                    last_instant: Instant::now(),
+                    progress: Duration::new(0, 0),
+                    prev_margin: 0,
+                }
+            },
+            Visibility::Peek { .. }
+            | Visibility::TransitionToPeek { .. }
+            | Visibility::TransitionFromPeek { to_visible: false, .. } => {
+                Visibility::TransitionFromPeek {
+                    last_instant: Instant::now(),
+                    progress: Duration::new(0, 0),
+                    prev_margin: 0,
+                    to_visible: true,
+                }
+            },
+            Visibility::Visible
+            | Visibility::TransitionToVisible { .. }
+            | Visibility::TransitionFromPeek { to_visible: true, .. } => {
+                Visibility::TransitionToHidden {
+                    last_instant: Instant::now(),
+                    progress: Duration::new(0, 0),
+                    prev_margin: 0,
+                }
+            },
+        };
+        self.is_dirty = true;
+    }
+
+    /// run the action of the click zone at the given location, if any
+    /// returns true if a zone was clicked
+    pub fn handle_click_zones(&mut self, pos: Point<i32, Logical>) -> bool {
+        let Some(action) = self.click_zone_at(pos).map(|z| z.action.clone()) else {
+            return false;
+        };
+        self.run_click_action(action);
+        true
+    }
+
+    fn click_zone_at(&self, pos: Point<i32, Logical>) -> Option<&ClickZone> {
+        self.config.click_zones.iter().find(|z| {
+            let (x, y, w, h) = z.rect;
+            Rectangle::<i32, Logical>::from_loc_and_size((x, y), (w, h)).contains(pos)
+        })
+    }
+
+    /// the cursor shown over a click zone at the position, a pointer unless
+    /// the zone configures another cursor
+    pub fn click_zone_cursor(&self, pos: Point<i32, Logical>) -> Option<CursorIcon> {
+        let zone = self.click_zone_at(pos)?;
+        match zone.cursor.as_deref() {
+            Some(name) => {
+                name.parse().map_err(|_| warn!("Invalid click zone cursor: {}", name)).ok()
+            },
+            None => Some(CursorIcon::Pointer),
+        }
+    }
+
+    /// run the configured action if a click on empty space of the panel
+    /// completes a double click
+    pub fn handle_empty_click(&mut self, pos: Point<i32, Logical>) {
+        let now = Instant::now();
+        let interval = Duration::from_millis(self.config.double_click_interval.into());
+        let is_double_click = self.last_empty_click.take().is_some_and(|(t, last_pos)| {
+            now.duration_since(t) <= interval
+                && (pos.x - last_pos.x).abs() <= DOUBLE_CLICK_DISTANCE
+                && (pos.y - last_pos.y).abs() <= DOUBLE_CLICK_DISTANCE
+        });
+        if !is_double_click {
+            self.last_empty_click = Some((now, pos));
+            return;
+        }
+        if let Some(action) = self.config.double_click_action.clone() {
+            self.run_click_action(action);
+        }
+    }
+
+    /// whether the panel appears to be fully covered, in which case applets
+    /// are not sent frame callbacks until it is shown again
+    pub fn occluded(&self) -> bool {
+        self.frame_requested.is_some_and(|t| t.elapsed() > OCCLUSION_TIMEOUT)
+    }
+
+    /// run the action of the start icon
+    pub fn activate_start_button(&mut self) {
+        if let Some(icon) = self.config.start_icon.clone() {
+            self.run_click_action(icon.action);
+        }
+    }
+
+    fn run_click_action(&mut self, action: ClickAction) {
+        match action {
+            ClickAction::RunCommand(cmd) => {
+                if let Err(err) = self.applet_tx.try_send(AppletMsg::RunCommand(cmd)) {
+                    error!("Failed to run click command: {err}");
+                }
+            },
+            ClickAction::ToggleVisibility => self.toggle_visibility(),
+            ClickAction::EmitSignal(name) => {
+                info!("Panel {} emits signal {}", self.config.name, name);
+                let panel = self.config.name.clone();
+                if let Err(err) = self.applet_tx.try_send(AppletMsg::EmitSignal { panel, name }) {
+                    error!("Failed to emit signal: {err}");
+                }
+            },
+            ClickAction::ToggleCollapsed => self.set_collapsed(!self.collapsed),
+        }
+    }
+
+    /// override the configured opacity of the background at runtime, or
+    /// return to the configured opacity
+    pub fn set_opacity_override(&mut self, opacity: Option<f32>) {
+        if self.opacity_override == opacity {
+            return;
+        }
+        self.opacity_override = opacity;
+        self.is_dirty = true;
+        self.persist_state();
+    }
+
+    /// axes along which the content of applets is mirrored, 1.0 if mirrored
+    pub(crate) fn content_mirror(&self) -> [f32; 2] {
+        if !self.config.mirror_content {
+            return [0., 0.];
+        }
+        match self.config.anchor {
+            PanelAnchor::Right => [1., 0.],
+            PanelAnchor::Bottom => [0., 1.],
+            PanelAnchor::Left | PanelAnchor::Top => [0., 0.],
+        }
+    }
+
+    /// opacity of the panel, including any runtime override
+    pub fn opacity(&self) -> f32 {
+        self.opacity_override.unwrap_or(self.config.opacity)
+    }
+
+    pub fn crosswise(&self) -> i32 {
+        if self.config.is_horizontal() {
+            self.dimensions.h
+        } else {
+            self.dimensions.w
+        }
+    }
+
+    pub fn bg_color(&self) -> [f32; 4] {
+        let mut bg_color = if let Some(animatable_state) = self.animate_state.as_ref() {
+            animatable_state.cur.bg_color
+        } else {
+            self.colors.bg_color(self.opacity())
+        };
+        if let Some((opacity, fade)) = self.config.empty_output_opacity.zip(self.empty_output) {
+            bg_color[3] += (opacity.clamp(0., 1.) - bg_color[3]) * fade.level();
+        }
+        // every source of the alpha is bounded here
+        if let Some((min, max)) = self.config.opacity_bounds {
+            bg_color[3] = bg_color[3].max(min).min(max);
+        }
+        bg_color
+    }
+
+    /// whether the panel background covers the whole layer surface without
+    /// any transparency
+    pub fn is_fully_opaque(&self) -> bool {
+        self.bg_color()[3] >= 1.0
+            && self.border_radius() == 0
+            && self.gap() == 0
+            && self.anchor_gap == 0
+            && self.background_image.is_none()
+            && self.config.expand_to_edges()
+            && self.animate_state.is_none()
+    }
+
+    /// scale used for rendering the panel
+    /// while transitioning, the panel may be rendered at a reduced resolution,
+    /// relying on the viewport to upscale it
+    pub fn render_scale(&self) -> f64 {
+        let transitioning = matches!(
+            self.visibility,
+            Visibility::TransitionToHidden { .. }
+                | Visibility::TransitionToVisible { .. }
+                | Visibility::TransitionToPeek { .. }
+                | Visibility::TransitionFromPeek { .. }
+        );
+        if self.config.reduced_transition_resolution
+            && transitioning
+            && self.layer_viewport.is_some()
+        {
+            self.scale * TRANSITION_RENDER_SCALE
+        } else {
+            self.scale
+        }
+    }
+
+    /// saturation of the applets, which are desaturated while peeking if
+    /// configured
+    pub fn saturation(&self) -> f32 {
+        if !self.config.desaturate_peek {
+            return 1.;
+        }
+        let progress_norm = |progress: Duration| {
+            let total_t = self.config.get_hide_transition().unwrap_or_default();
+            smootherstep(progress.as_millis() as f32 / total_t.as_millis().max(1) as f32)
+        };
+        match self.visibility {
+            Visibility::Peek { .. } | Visibility::Hidden => 0.,
+            Visibility::TransitionToPeek { progress, start_margin: 0, .. } => {
+                1. - progress_norm(progress)
+            },
+            Visibility::TransitionFromPeek { progress, to_visible: true, .. } => {
+                progress_norm(progress)
+            },
+            Visibility::TransitionToPeek { .. } | Visibility::TransitionFromPeek { .. } => 0.,
+            Visibility::Visible
+            | Visibility::TransitionToHidden { .. }
+            | Visibility::TransitionToVisible { .. } => 1.,
+        }
+    }
+
+    /// lengthwise start and end of the display cutout on the panel's output, in
+    /// logical coordinates of the panel
+    pub fn cutout(&self) -> Option<(f64, f64)> {
+        let output = self.output.as_ref()?.1.name();
+        let width = *self.config.cutouts.get(&output)? as f64;
+        let length =
+            if self.config.is_horizontal() { self.dimensions.w } else { self.dimensions.h } as f64;
+        (width > 0.).then(|| (length / 2. - width / 2., length / 2. + width / 2.))
+    }
+
+    /// margin of the panel while it is hidden
+    fn hidden_margin(&self) -> i32 {
+        let panel_size =
+            if self.config.is_horizontal() { self.dimensions.h } else { self.dimensions.w };
+        -panel_size + self.config.get_hide_handle().unwrap_or_default() as i32
+    }
+
+    /// whether the last known pointer position is far enough inside the handle
+    /// of the hidden panel to reveal it
+    fn pointer_in_handle(&self) -> bool {
+        let hysteresis = self.config.get_hide_hysteresis().unwrap_or_default() as i32;
+        let Some(pos) = self.last_pointer_pos.filter(|_| hysteresis > 0) else {
+            return true;
+        };
+        // the handle is the part of the panel nearest to the edge of the output
+        let depth = match self.config.anchor() {
+            PanelAnchor::Top => self.dimensions.h - pos.y,
+            PanelAnchor::Bottom => pos.y + 1,
+            PanelAnchor::Left => self.dimensions.w - pos.x,
+            PanelAnchor::Right => pos.x + 1,
+        };
+        let handle = self.config.get_hide_handle().unwrap_or_default() as i32;
+        depth > hysteresis.min(handle - 1)
+    }
+
+    /// margin of the panel while it is peeking, if peeking is configured
+    fn peek_margin(&self) -> Option<i32> {
+        let panel_size =
+            if self.config.is_horizontal() { self.dimensions.h } else { self.dimensions.w };
+        self.config.get_peek_size().map(|peek_size| -panel_size + peek_size as i32)
+    }
+
+    pub fn border_radius(&self) -> u32 {
+        if let Some(animatable_state) = self.animate_state.as_ref() {
+            animatable_state.cur.border_radius
+        } else {
+            self.resolve_border_radius(self.config.border_radius)
+        }
+    }
+
+    /// a radius in logical pixels, with a full radius resolved to half the
+    /// current size of the panel
+    fn resolve_border_radius(&self, radius: BorderRadius) -> u32 {
+        match radius {
+            BorderRadius::Px(radius) => radius,
+            BorderRadius::Full => self.dimensions.w.min(self.dimensions.h).max(0) as u32 / 2,
+        }
+    }
+
+    pub fn gap(&self) -> u16 {
+        if let Some(animation) = self.gap_animation.as_ref() {
+            animation.gap().round() as u16
+        } else if let Some(animatable_state) = self.animate_state.as_ref() {
+            animatable_state.cur.gap
+        } else {
+            self.gap_override.unwrap_or(self.config.get_effective_anchor_gap() as u16)
+        }
+    }
+
+    /// animate the gap between the panel and the edge of its output to the
+    /// given gap, or back to the configured gap
+    ///
+    /// The gap is a transient runtime effect, e.g. for a dock lifting off the
+    /// edge, and is not saved to the config. An animation which is still
+    /// running is retargeted from the current gap.
+    pub fn animate_gap(&mut self, gap: Option<u16>, animation: ExpandAnimation) {
+        let from = self.gap_animation.as_ref().map_or(self.gap() as f32, GapAnimation::gap);
+        self.gap_override = gap;
+        let to = self.gap_override.unwrap_or(self.config.get_effective_anchor_gap() as u16);
+        if self.gap_animation.is_none() && from == to as f32 {
+            return;
+        }
+        self.gap_animation = Some(GapAnimation { from, to, started: Instant::now(), animation });
+        self.is_dirty = true;
+    }
+
+    /// drop a finished gap animation, redrawing while it is in progress
+    fn apply_gap_animation(&mut self) {
+        let Some(animation) = self.gap_animation.as_ref() else {
+            return;
+        };
+        if animation.progress() >= 1. {
+            self.gap_animation = None;
+        }
+        self.is_dirty = true;
+    }
+
+    /// raise the panel to the overlay layer for the given duration, e.g. to
+    /// reveal all panels above windows
+    ///
+    /// Boosting a panel which is already boosted extends the boost.
+    pub fn boost_layer(&mut self, duration: Duration) {
+        let Some(layer) = self.layer.as_ref() else {
+            return;
+        };
+        let until = Instant::now() + duration;
+        if self.layer_boost.is_none() {
+            layer.set_layer(Layer::Overlay);
+            layer.wl_surface().commit();
+        }
+        self.layer_boost = Some(self.layer_boost.map_or(until, |t| t.max(until)));
+    }
+
+    /// restore the configured layer once a boost ends
+    fn apply_layer_boost(&mut self) {
+        if !self.layer_boost.is_some_and(|t| t <= Instant::now()) {
+            return;
+        }
+        self.layer_boost = None;
+        let Some(layer) = self.layer.as_ref() else {
+            return;
+        };
+        layer.set_layer(match self.config.layer {
+            xdg_shell_wrapper_config::Layer::Background => Layer::Background,
+            xdg_shell_wrapper_config::Layer::Bottom => Layer::Bottom,
+            xdg_shell_wrapper_config::Layer::Top => Layer::Top,
+            xdg_shell_wrapper_config::Layer::Overlay => Layer::Overlay,
+        });
+        layer.wl_surface().commit();
+    }
+
+    /// show a system-wide progress from 0.0 to 1.0 along the anchored edge of
+    /// the panel, or stop showing it
+    ///
+    /// The fill animates smoothly from the progress shown before.
+    pub fn set_progress(&mut self, progress: Option<f32>) {
+        let Some(to) = progress.map(|p| p.clamp(0., 1.)) else {
+            if self.progress.take().is_some() {
+                self.is_dirty = true;
+            }
+            return;
+        };
+        if self.progress.is_some_and(|p| p.to == to) {
+            return;
+        }
+        let from = self.progress.map_or(0., |p| p.fill());
+        self.progress = Some(ProgressIndicator { from, to, started: Instant::now() });
+        self.is_dirty = true;
+    }
+
+    /// redraw while the fill of the progress indicator is animating
+    fn apply_progress_animation(&mut self) {
+        if self.progress.is_some_and(|p| p.progress() < 1.) {
+            self.is_dirty = true;
+        }
+    }
+
+    /// opacity an applet window is drawn with
+    pub(crate) fn applet_opacity(&self, id: &ObjectId) -> f32 {
+        let Some((base, hovered)) = self.config.hover_opacity else {
+            return 1.;
+        };
+        let level = self.hover_fades.get(id).map_or(0., |f| f.level());
+        base + (hovered - base) * level
+    }
+
+    /// start fading the applets the pointer entered or left, redrawing while
+    /// any are fading
+    fn apply_hover_fades(&mut self) {
+        if self.config.hover_opacity.is_none() {
+            self.hover_fades.clear();
+            return;
+        }
+        let hovered: HashSet<_> = self
+            .s_hovered_surface
+            .iter()
+            .filter_map(|h| h.surface.wl_surface().map(|s| s.id()))
+            .collect();
+        for (id, fade) in self.hover_fades.iter_mut() {
+            if fade.shown && !hovered.contains(id) {
+                *fade = Fade::start(Some(*fade), false);
+            }
+        }
+        for id in hovered {
+            let fade = self.hover_fades.get(&id).copied();
+            if fade.is_some_and(|f| f.shown) {
+                continue;
+            }
+            self.hover_fades.insert(id, Fade::start(fade, true));
+        }
+        self.hover_fades.retain(|_, f| f.shown || f.progress() < 1.);
+        if self.hover_fades.values().any(|f| f.progress() < 1.) {
+            self.is_dirty = true;
+        }
+    }
+
+    /// fade the popup backdrop in or out as popups open and close
+    fn apply_backdrop(&mut self) {
+        let shown = self.config.popup_backdrop.is_some() && !self.popups.is_empty();
+        if self.backdrop.map_or(shown, |f| f.shown) != shown {
+            self.backdrop = Some(Fade::start(self.backdrop, shown));
+        }
+        let Some(fade) = self.backdrop else {
+            return;
+        };
+        if fade.progress() < 1. {
+            self.backdrop_commit.increment();
+            self.is_dirty = true;
+        } else if !fade.shown {
+            self.backdrop = None;
+            self.is_dirty = true;
+        }
+    }
+
+    /// fade the background to the opacity configured for an output without
+    /// windows, relaying out the panel on every step so the background follows
+    fn apply_empty_output(&mut self) {
+        let shown = self.config.empty_output_opacity.is_some() && !self.output_has_toplevel;
+        if self.empty_output.map_or(shown, |f| f.shown) != shown {
+            self.empty_output = Some(Fade::start(self.empty_output, shown));
+        }
+        let Some(fade) = self.empty_output else {
+            return;
+        };
+        if fade.progress() < 1. {
+            self.is_dirty = true;
+        } else if !fade.shown {
+            self.empty_output = None;
+            self.is_dirty = true;
+        }
+    }
+
+    pub fn id(&self) -> String {
+        let id = format!(
+            "panel-{}-{}-{}",
+            self.config.name,
+            self.config.output,
+            self.output.as_ref().map(|o| o.2.name.clone().unwrap_or_default()).unwrap_or_default()
+        );
+        id
+    }
+
+    /// start revealing the hidden panel if a swipe began on it
+    pub fn reveal_swipe_begin(&mut self, surface: &c_wl_surface::WlSurface) {
+        if self.config.autohide().is_none()
+            || !matches!(self.visibility, Visibility::Hidden)
+            || !self.layer.as_ref().is_some_and(|l| l.wl_surface() == surface)
+        {
+            return;
+        }
+        self.reveal_swipe = Some(0.);
+    }
+
+    /// move the panel with the swipe revealing it
+    pub fn reveal_swipe_update(&mut self, dx: f64, dy: f64) {
+        let Some(distance) = self.reveal_swipe.as_mut() else {
+            return;
+        };
+        // swiping away from the edge pulls the panel out
+        *distance += match self.config.anchor() {
+            PanelAnchor::Left => dx,
+            PanelAnchor::Right => -dx,
+            PanelAnchor::Top => dy,
+            PanelAnchor::Bottom => -dy,
+        };
+        *distance = distance.max(0.);
+        self.is_dirty = true;
+    }
+
+    /// finish the swipe, snapping to visible if it was more than halfway
+    /// revealed, otherwise back to hidden
+    pub fn reveal_swipe_end(&mut self, cancelled: bool) {
+        let Some(distance) = self.reveal_swipe.take() else {
+            return;
+        };
+        let Visibility::TransitionToVisible { progress, prev_margin, .. } = self.visibility else {
+            return;
+        };
+        let panel_size =
+            if self.config.is_horizontal() { self.dimensions.h } else { self.dimensions.w };
+        if cancelled || distance < panel_size as f64 / 2. {
+            let total_t = self.config.get_hide_transition().unwrap();
+            self.visibility = Visibility::TransitionToHidden {
+                last_instant: Instant::now(),
+                progress: total_t.checked_sub(progress).unwrap_or_default(),
+                prev_margin,
+            };
+        } else {
+            self.visibility = Visibility::TransitionToVisible {
+                last_instant: Instant::now(),
+                progress,
+                prev_margin,
+            };
+        }
+        self.is_dirty = true;
+    }
+
+    pub fn handle_focus(&mut self) {
+        let (layer_surface, layer_shell_wl_surface) =
+            if let Some(layer_surface) = self.layer.as_ref() {
+                (layer_surface, layer_surface.wl_surface())
+            } else {
+                return;
+            };
+
+        let cur_hover = {
+            let c_focused_surface = self.c_focused_surface.borrow();
+            let c_hovered_surface = self.c_hovered_surface.borrow();
+            // no transition if not configured for autohide
+            let no_hover_focus =
+                c_focused_surface.iter().all(|f| matches!(f.2, FocusStatus::LastFocused(_)))
+                    && c_hovered_surface.iter().all(|f| matches!(f.2, FocusStatus::LastFocused(_)));
+            if self.config.autohide().is_none() {
+                if no_hover_focus && self.animate_state.is_none() {
+                    self.additional_gap = 0;
+                    self.visibility = Visibility::Hidden;
+                } else {
+                    self.visibility = Visibility::Visible;
+                }
+                return;
+            };
+
+            let f = c_hovered_surface.iter().fold(
+                if self.animate_state.is_some() || !self.output_has_toplevel || self.pinned {
+                    FocusStatus::Focused
+                } else {
+                    FocusStatus::LastFocused(self.start_instant)
+                },
+                |acc, (surface, _, f)| {
+                    if surface.is_alive()
+                        && (self.layer.as_ref().is_some_and(|s| *s.wl_surface() == *surface)
                             || self.popups.iter().any(|p| {
                                 &p.popup.c_popup.wl_surface() == &surface
                                     || self
@@ -501,10 +1956,30 @@ impl PanelSpace {
             f
         };
 
+        // a swipe revealing the panel drives the transition directly
+        if let Some(distance) = self.reveal_swipe {
+            let panel_size =
+                if self.config.is_horizontal() { self.dimensions.h } else { self.dimensions.w };
+            let total_t = self.config.get_hide_transition().unwrap();
+            let fraction = (distance / panel_size.max(1) as f64).clamp(0., 1.) as f32;
+            let cur_pix = ((1.0 - smootherstep(fraction)) * self.hidden_margin() as f32) as i32;
+            if self.config.exclusive_zone() {
+                self.set_exclusive_zone(layer_surface, panel_size - cur_pix);
+            }
+            self.anchor_gap = cur_pix;
+            self.is_dirty = true;
+            self.visibility = Visibility::TransitionToVisible {
+                last_instant: Instant::now(),
+                progress: total_t.mul_f32(fraction),
+                prev_margin: cur_pix,
+            };
+            return;
+        }
+
         let intellihide = self.overlap_notify.is_some();
         match self.visibility {
             Visibility::Hidden => {
-                if matches!(cur_hover, FocusStatus::Focused)
+                if matches!(cur_hover, FocusStatus::Focused) && self.pointer_in_handle()
                     || (intellihide && self.toplevel_overlaps.is_empty())
                 {
                     // start transition to visible
@@ -513,10 +1988,19 @@ impl PanelSpace {
                         PanelAnchor::Top | PanelAnchor::Bottom => -(self.dimensions.h),
                     } + self.config.get_hide_handle().unwrap() as i32;
                     self.is_dirty = true;
-                    self.visibility = Visibility::TransitionToVisible {
-                        last_instant: Instant::now(),
-                        progress: Duration::new(0, 0),
-                        prev_margin: margin,
+                    self.visibility = if self.config.get_peek_size().is_some() {
+                        Visibility::TransitionToPeek {
+                            last_instant: Instant::now(),
+                            progress: Duration::new(0, 0),
+                            prev_margin: margin,
+                            start_margin: margin,
+                        }
+                    } else {
+                        Visibility::TransitionToVisible {
+                            last_instant: Instant::now(),
+                            progress: Duration::new(0, 0),
+                            prev_margin: margin,
+                        }
                     }
                 }
             },
@@ -531,14 +2015,50 @@ impl PanelSpace {
                         && (!intellihide || !self.toplevel_overlaps.is_empty())
                     {
                         self.is_dirty = true;
-                        self.visibility = Visibility::TransitionToHidden {
-                            last_instant: Instant::now(),
-                            progress: Duration::new(0, 0),
-                            prev_margin: 0,
+                        self.visibility = if self.config.get_peek_size().is_some() {
+                            Visibility::TransitionToPeek {
+                                last_instant: Instant::now(),
+                                progress: Duration::new(0, 0),
+                                prev_margin: 0,
+                                start_margin: 0,
+                            }
+                        } else {
+                            Visibility::TransitionToHidden {
+                                last_instant: Instant::now(),
+                                progress: Duration::new(0, 0),
+                                prev_margin: 0,
+                            }
                         }
                     }
                 }
             },
+            Visibility::Peek { since } => {
+                let peek_margin = self.peek_margin().unwrap_or_else(|| self.hidden_margin());
+                if matches!(cur_hover, FocusStatus::Focused)
+                    || (intellihide && self.toplevel_overlaps.is_empty())
+                {
+                    // start transition to visible
+                    self.is_dirty = true;
+                    self.visibility = Visibility::TransitionFromPeek {
+                        last_instant: Instant::now(),
+                        progress: Duration::new(0, 0),
+                        prev_margin: peek_margin,
+                        to_visible: true,
+                    }
+                } else if Instant::now()
+                    .checked_duration_since(since)
+                    .is_some_and(|d| d > self.config.get_hide_wait().unwrap())
+                {
+                    // start transition to hidden
+                    self.is_dirty = true;
+                    self.visibility = Visibility::TransitionFromPeek {
+                        last_instant: Instant::now(),
+                        progress: Duration::new(0, 0),
+                        prev_margin: peek_margin,
+                        to_visible: false,
+                    }
+                }
+            },
             Visibility::TransitionToHidden { last_instant, progress, prev_margin } => {
                 let now = Instant::now();
                 let total_t = self.config.get_hide_transition().unwrap();
@@ -577,16 +2097,17 @@ impl PanelSpace {
 
                     if progress > total_t {
                         if self.config.exclusive_zone() {
-                            layer_surface.set_exclusive_zone(panel_size);
+                            self.set_exclusive_zone(layer_surface, panel_size);
                         }
 
                         self.anchor_gap = target;
                         self.additional_gap = 0;
                         self.visibility = Visibility::Hidden;
+                        self.persist_state();
                     } else {
                         if prev_margin != cur_pix {
                             if self.config.exclusive_zone() {
-                                layer_surface.set_exclusive_zone(panel_size - cur_pix);
+                                self.set_exclusive_zone(layer_surface, panel_size - cur_pix);
                             }
 
                             self.anchor_gap = cur_pix;
@@ -639,42 +2160,274 @@ impl PanelSpace {
 
                     if progress > total_t {
                         if self.config.exclusive_zone() {
-                            layer_surface.set_exclusive_zone(panel_size);
+                            self.set_exclusive_zone(layer_surface, panel_size);
                         }
 
                         self.anchor_gap = 0;
                         self.visibility = Visibility::Visible;
+                        self.persist_state();
+                    } else {
+                        if prev_margin != cur_pix {
+                            if self.config.exclusive_zone() {
+                                self.set_exclusive_zone(layer_surface, panel_size - cur_pix);
+                            }
+
+                            self.anchor_gap = cur_pix;
+                        }
+                        self.visibility = Visibility::TransitionToVisible {
+                            last_instant: now,
+                            progress,
+                            prev_margin: cur_pix,
+                        };
+                    }
+                }
+            },
+            Visibility::TransitionToPeek { last_instant, progress, prev_margin, start_margin } => {
+                let now = Instant::now();
+                let total_t = self.config.get_hide_transition().unwrap();
+                let delta_t = match now.checked_duration_since(last_instant) {
+                    Some(d) => d,
+                    None => return,
+                };
+                let prev_progress = progress;
+                let progress = match prev_progress.checked_add(delta_t) {
+                    Some(d) => d,
+                    None => return,
+                };
+                let progress_norm =
+                    smootherstep(progress.as_millis() as f32 / total_t.as_millis() as f32);
+                let from_visible = start_margin == 0;
+                self.is_dirty = true;
+
+                let reveal = matches!(cur_hover, FocusStatus::Focused)
+                    || (intellihide && self.toplevel_overlaps.is_empty());
+                if from_visible && reveal || !from_visible && !reveal {
+                    // return to where the transition started, smootherstep is
+                    // symmetric so the reversed progress continues from the
+                    // current margin
+                    self.visibility = Visibility::TransitionFromPeek {
+                        last_instant: now,
+                        progress: total_t.checked_sub(progress).unwrap_or_default(),
+                        prev_margin,
+                        to_visible: from_visible,
+                    }
+                } else {
+                    let panel_size = if self.config().is_horizontal() {
+                        self.dimensions.h
+                    } else {
+                        self.dimensions.w
+                    };
+                    let target = self.peek_margin().unwrap_or_else(|| self.hidden_margin());
+
+                    let cur_pix =
+                        start_margin + (progress_norm * (target - start_margin) as f32) as i32;
+
+                    if progress > total_t {
+                        if self.config.exclusive_zone() {
+                            self.set_exclusive_zone(layer_surface, panel_size);
+                        }
+
+                        self.anchor_gap = target;
+                        self.additional_gap = 0;
+                        self.visibility = Visibility::Peek { since: now };
+                        self.persist_state();
+                    } else {
+                        if prev_margin != cur_pix {
+                            if self.config.exclusive_zone() {
+                                self.set_exclusive_zone(layer_surface, panel_size - cur_pix);
+                            }
+
+                            self.anchor_gap = cur_pix;
+                        }
+                        if from_visible {
+                            self.close_popups(|_| false);
+                        }
+                        self.visibility = Visibility::TransitionToPeek {
+                            last_instant: now,
+                            progress,
+                            prev_margin: cur_pix,
+                            start_margin,
+                        };
+                    }
+                }
+            },
+            Visibility::TransitionFromPeek { last_instant, progress, prev_margin, to_visible } => {
+                let now = Instant::now();
+                let total_t = self.config.get_hide_transition().unwrap();
+                let delta_t = match now.checked_duration_since(last_instant) {
+                    Some(d) => d,
+                    None => return,
+                };
+                let prev_progress = progress;
+                let progress = match prev_progress.checked_add(delta_t) {
+                    Some(d) => d,
+                    None => return,
+                };
+                let progress_norm =
+                    smootherstep(progress.as_millis() as f32 / total_t.as_millis() as f32);
+                self.is_dirty = true;
+
+                let reveal = matches!(cur_hover, FocusStatus::Focused)
+                    || (intellihide && self.toplevel_overlaps.is_empty());
+                let hide = matches!(cur_hover, FocusStatus::LastFocused(_))
+                    && (!intellihide || !self.toplevel_overlaps.is_empty());
+                if to_visible && hide || !to_visible && reveal {
+                    // return to peek
+                    self.visibility = Visibility::TransitionToPeek {
+                        last_instant: now,
+                        progress: total_t.checked_sub(progress).unwrap_or_default(),
+                        prev_margin,
+                        start_margin: if to_visible { 0 } else { self.hidden_margin() },
+                    }
+                } else {
+                    let panel_size = if self.config().is_horizontal() {
+                        self.dimensions.h
+                    } else {
+                        self.dimensions.w
+                    };
+                    let start = self.peek_margin().unwrap_or_else(|| self.hidden_margin());
+                    let target = if to_visible { 0 } else { self.hidden_margin() };
+
+                    let cur_pix = start + (progress_norm * (target - start) as f32) as i32;
+
+                    if progress > total_t {
+                        if self.config.exclusive_zone() {
+                            self.set_exclusive_zone(layer_surface, panel_size);
+                        }
+
+                        self.anchor_gap = target;
+                        self.visibility =
+                            if to_visible { Visibility::Visible } else { Visibility::Hidden };
+                        self.persist_state();
                     } else {
                         if prev_margin != cur_pix {
                             if self.config.exclusive_zone() {
-                                layer_surface.set_exclusive_zone(panel_size - cur_pix);
+                                self.set_exclusive_zone(layer_surface, panel_size - cur_pix);
                             }
 
-                            self.anchor_gap = cur_pix;
-                        }
-                        self.visibility = Visibility::TransitionToVisible {
-                            last_instant: now,
-                            progress,
-                            prev_margin: cur_pix,
-                        };
-                    }
-                }
-            },
+                            self.anchor_gap = cur_pix;
+                        }
+                        self.visibility = Visibility::TransitionFromPeek {
+                            last_instant: now,
+                            progress,
+                            prev_margin: cur_pix,
+                            to_visible,
+                        };
+                    }
+                }
+            },
+        }
+    }
+
+    /// set the exclusive zone of the layer surface, keeping track of it
+    fn set_exclusive_zone(&self, layer_surface: &LayerSurface, zone: i32) {
+        // an empty panel which is hidden until applets are added reserves no space
+        let zone = if self.config.hidden_while_empty() { zone.min(0) } else { zone };
+        layer_surface.set_exclusive_zone(zone);
+        self.exclusive_zone.set(zone);
+    }
+
+    /// the exclusive zone currently set on the layer surface
+    pub fn current_exclusive_zone(&self) -> i32 {
+        self.exclusive_zone.get()
+    }
+
+    pub(crate) fn set_margin(
+        anchor: PanelAnchor,
+        (margin_start, margin_end): (i32, i32),
+        additional_gap: i32,
+        (start, end): (i32, i32),
+        pixel_grid: Option<f64>,
+        layer_surface: &LayerSurface,
+    ) {
+        let snap = |len: i32| pixel_grid.map_or(len, |scale| snap_to_pixel_grid(len, scale));
+        let (start, end, additional_gap) =
+            (snap(margin_start + start), snap(margin_end + end), snap(additional_gap));
+        match anchor {
+            PanelAnchor::Left => layer_surface.set_margin(start, 0, end, additional_gap),
+            PanelAnchor::Right => layer_surface.set_margin(start, additional_gap, end, 0),
+            PanelAnchor::Top => layer_surface.set_margin(additional_gap, end, 0, start),
+            PanelAnchor::Bottom => layer_surface.set_margin(0, end, additional_gap, start),
+        };
+    }
+
+    /// the scale to snap the panel to whole physical pixels at, if configured
+    pub(crate) fn pixel_grid(&self) -> Option<f64> {
+        self.config.snap_to_pixel_grid.then_some(self.scale)
+    }
+
+    /// snap the lengths of the panel which were not set by the compositor to
+    /// whole physical pixels, if configured
+    fn snap_size(&self, size: Size<i32, Logical>, (w, h): (u32, u32)) -> Size<i32, Logical> {
+        let Some(scale) = self.pixel_grid() else {
+            return size;
+        };
+        let snap = |len: i32, configured: u32| {
+            if configured == 0 {
+                snap_to_pixel_grid(len, scale).max(1)
+            } else {
+                len
+            }
+        };
+        (snap(size.w, w), snap(size.h, h)).into()
+    }
+
+    /// lengthwise margins at the start and end of the panel which leave the
+    /// rest of the output to other panels
+    pub(crate) fn length_margins_for(
+        config: &CosmicPanelConfig,
+        info: Option<&OutputInfo>,
+    ) -> (i32, i32) {
+        let (Some((start, end)), Some((w, h))) =
+            (config.get_length_range(), info.and_then(|i| i.logical_size))
+        else {
+            return (0, 0);
+        };
+        let len = if config.is_horizontal() { w } else { h } as f32;
+        ((start * len).round() as i32, ((1. - end) * len).round() as i32)
+    }
+
+    pub fn length_margins(&self) -> (i32, i32) {
+        self.with_corner_margins(Self::length_margins_for(
+            &self.config,
+            self.output.as_ref().map(|o| &o.2),
+        ))
+    }
+
+    /// add the margins leaving the corners to other panels
+    fn with_corner_margins(&self, (start, end): (i32, i32)) -> (i32, i32) {
+        (start + self.corner_margins.0, end + self.corner_margins.1)
+    }
+
+    /// limit the length of the panel to a fraction of the output length, or
+    /// remove the limit
+    pub fn set_length_budget(&mut self, budget: Option<f32>) {
+        if self.length_budget == budget {
+            return;
         }
+        self.length_budget = budget;
+        self.is_dirty = true;
     }
 
-    fn set_margin(
-        anchor: PanelAnchor,
-        margin: i32,
-        additional_gap: i32,
-        layer_surface: &LayerSurface,
-    ) {
-        match anchor {
-            PanelAnchor::Left => layer_surface.set_margin(margin, 0, margin, additional_gap),
-            PanelAnchor::Right => layer_surface.set_margin(margin, additional_gap, margin, 0),
-            PanelAnchor::Top => layer_surface.set_margin(additional_gap, margin, 0, margin),
-            PanelAnchor::Bottom => layer_surface.set_margin(0, margin, additional_gap, margin),
-        };
+    /// leave the corners at the start and end of the panel to the panels on
+    /// the adjacent edges
+    pub fn set_corner_margins(&mut self, margins: (i32, i32)) {
+        if self.corner_margins == margins {
+            return;
+        }
+        self.corner_margins = margins;
+        self.is_dirty = true;
+        if let Some(layer) = self.layer.as_ref() {
+            Self::set_margin(
+                self.config.anchor,
+                self.config.get_length_gaps(self.config.get_margin() as u32),
+                self.additional_gap,
+                self.length_margins(),
+                self.pixel_grid(),
+                layer,
+            );
+            layer.commit();
+        }
     }
 
     pub fn constrain_dim(
@@ -693,6 +2446,19 @@ impl PanelSpace {
             })
             .map(|(w, h)| (w as u32, h as u32));
 
+        // only part of the output length is available to a split panel, or to
+        // a panel sharing its edge with split panels
+        let fraction = self
+            .length_budget
+            .or_else(|| self.config.get_length_range().map(|(start, end)| end - start));
+        let output_dims = match (output_dims, fraction) {
+            (Some((w, h)), Some(fraction)) => Some(if self.config.is_horizontal() {
+                ((w as f32 * fraction) as u32, h)
+            } else {
+                (w, (h as f32 * fraction) as u32)
+            }),
+            (dims, _) => dims,
+        };
         let (constrained_w, constrained_h) =
             self.config.get_dimensions(output_dims, self.suggested_length, active_gap);
         if let Some(w_range) = constrained_w {
@@ -712,15 +2478,17 @@ impl PanelSpace {
                 self.scale,
                 smithay::utils::Transform::Flipped180,
             ));
-            let progress = (Instant::now().duration_since(animation_state.started_at).as_millis()
-                as f32)
-                / animation_state.duration.as_millis() as f32;
+            let progress = if animation_state.duration.is_zero() {
+                1.0
+            } else {
+                (Instant::now().duration_since(animation_state.started_at).as_millis() as f32)
+                    / animation_state.duration.as_millis() as f32
+            };
             self.is_dirty = true;
             if progress >= 1.0 {
                 tracing::info!("Animation finished, setting bg_color to end value");
                 if let CosmicPanelBackground::Color(c) = self.config.background {
-                    self.colors = PanelColors::new(self.colors.theme.clone())
-                        .with_color_override(Some([c[0], c[1], c[2], self.config.opacity]));
+                    self.colors.color_override = Some([c[0], c[1], c[2], self.config.opacity]);
                 } else {
                     self.colors.color_override = None;
                 }
@@ -730,7 +2498,7 @@ impl PanelSpace {
             }
 
             animation_state.progress = progress;
-            let progress = smootherstep(progress);
+            let progress = ease(animation_state.easing, progress);
             let new_cur = AnimatableState {
                 // TODO: blend in perceptual color space?
                 bg_color: [
@@ -763,6 +2531,122 @@ impl PanelSpace {
         }
     }
 
+    /// start moving an applet window from where it is drawn to its new
+    /// location, if its index in its section changed
+    ///
+    /// A window which is still moving is retargeted from its current position.
+    pub(crate) fn track_applet_move(
+        &mut self,
+        index: usize,
+        w: &Window,
+        target: Point<i32, Logical>,
+    ) {
+        let Some(id) = w.toplevel().map(|t| t.wl_surface().id()) else {
+            return;
+        };
+        let prev_index = self.applet_indices.insert(id.clone(), index);
+        if !matches!(prev_index, Some(prev) if prev != index)
+            || self.config.reorder_animation.duration == 0
+        {
+            return;
+        }
+        let Some(prev) = self.space.element_location(&CosmicMappedInternal::Window(w.clone()))
+        else {
+            return;
+        };
+        let from = prev.to_f64() + self.applet_move_offset(&id, prev);
+        if from != target.to_f64() {
+            self.applet_moves.insert(id, AppletMove { from, started: Instant::now() });
+        }
+    }
+
+    /// forget the indices and moves of applet windows which are gone, e.g.
+    /// because their applet exited or was removed from the config
+    ///
+    /// `windows` are the applet windows in the panel, windows in the overflow
+    /// popups are kept as well.
+    pub(crate) fn prune_applet_moves(&mut self, windows: &[Window]) {
+        let overflowed = [&self.overflow_left, &self.overflow_center, &self.overflow_right]
+            .into_iter()
+            .flat_map(|space| space.elements())
+            .filter_map(|e| e.toplevel());
+        let ids: HashSet<_> = windows
+            .iter()
+            .filter_map(|w| w.toplevel())
+            .chain(overflowed)
+            .map(|t| t.wl_surface().id())
+            .collect();
+        self.applet_indices.retain(|id, _| ids.contains(id));
+        self.applet_moves.retain(|id, _| ids.contains(id));
+    }
+
+    /// offset from its mapped location at which a moving applet window is
+    /// drawn
+    pub(crate) fn applet_move_offset(
+        &self,
+        id: &ObjectId,
+        target: Point<i32, Logical>,
+    ) -> Point<f64, Logical> {
+        let Some(applet_move) = self.applet_moves.get(id) else {
+            return Point::default();
+        };
+        let animation = self.config.reorder_animation;
+        let duration = Duration::from_millis(animation.duration.into());
+        let progress =
+            applet_move.started.elapsed().as_secs_f32() / duration.as_secs_f32().max(f32::EPSILON);
+        let remaining = 1. - ease(animation.easing, progress) as f64;
+        (applet_move.from - target.to_f64()).upscale(remaining)
+    }
+
+    /// offset from its mapped location at which an element is drawn
+    pub(crate) fn move_offset(
+        &self,
+        element: &CosmicMappedInternal,
+        location: Point<i32, Logical>,
+    ) -> Point<f64, Logical> {
+        match element {
+            CosmicMappedInternal::Window(w) => w
+                .toplevel()
+                .map(|t| self.applet_move_offset(&t.wl_surface().id(), location))
+                .unwrap_or_default(),
+            _ => Point::default(),
+        }
+    }
+
+    /// drop finished applet movements, redrawing while any are in progress
+    fn apply_applet_moves(&mut self) {
+        if self.applet_moves.is_empty() {
+            return;
+        }
+        let duration = Duration::from_millis(self.config.reorder_animation.duration.into());
+        self.applet_moves.retain(|_, m| m.started.elapsed() < duration);
+        self.is_dirty = true;
+    }
+
+    /// resize the panel in place after the mode of its output changed
+    ///
+    /// The length last suggested by the compositor is for the old mode, so it
+    /// is replaced by the length of the output until the next configure.
+    pub fn output_mode_changed(&mut self) {
+        let Some(logical_size) = self.output.as_ref().map(|o| o.2.logical_size) else {
+            return;
+        };
+        // a fractional margin depends on the size of the output
+        self.config.resolve_margin(logical_size);
+        if let Some((w, h)) = logical_size {
+            let (start, end) = self.length_margins();
+            let len = if self.config.is_horizontal() { w } else { h };
+            self.suggested_length = Some((len - start - end).max(1) as u32);
+        }
+        let dim = self.constrain_dim(self.dimensions, Some(self.gap() as u32));
+        let (width, height) = if self.config.is_horizontal() { (0, dim.h) } else { (dim.w, 0) };
+        self.pending_dimensions = Some((width, height).into());
+        // applets in the overflow popups may fit the new length, and those which
+        // no longer fit are moved back by the next layout
+        self.relax_all();
+        self.clear();
+    }
+
     pub fn set_additional_gap(&mut self, gap: i32) {
         if self.additional_gap == gap {
             return;
@@ -778,8 +2662,10 @@ impl PanelSpace {
             if let Some(layer) = self.layer.as_ref() {
                 Self::set_margin(
                     self.config.anchor,
-                    self.config.get_margin() as i32,
+                    self.config.get_length_gaps(self.config.get_margin() as u32),
                     self.additional_gap,
+                    self.length_margins(),
+                    self.pixel_grid(),
                     layer,
                 );
                 self.anchor_gap = 0;
@@ -798,6 +2684,13 @@ impl PanelSpace {
     ) -> Instant {
         self.space.refresh();
         self.apply_animation_state();
+        self.apply_applet_moves();
+        self.apply_gap_animation();
+        self.apply_layer_boost();
+        self.apply_progress_animation();
+        self.apply_hover_fades();
+        self.apply_backdrop();
+        self.apply_empty_output();
 
         self.handle_focus();
         let mut should_render = false;
@@ -825,31 +2718,43 @@ impl PanelSpace {
                         PanelAnchor::Top | PanelAnchor::Bottom => height,
                     };
 
-                    if self.config.autohide.is_none() && self.config.exclusive_zone() {
-                        self.layer.as_ref().unwrap().set_exclusive_zone(list_thickness as i32);
+                    if self.config.autohide().is_none() && self.config.exclusive_zone() {
+                        self.set_exclusive_zone(
+                            self.layer.as_ref().unwrap(),
+                            list_thickness as i32,
+                        );
                         if self.config.get_effective_anchor_gap() > 0 {
                             Self::set_margin(
                                 self.config.anchor,
-                                self.config.get_effective_anchor_gap() as i32,
+                                self.config.get_length_gaps(self.config.get_effective_anchor_gap()),
                                 self.additional_gap,
+                                self.length_margins(),
+                                self.pixel_grid(),
                                 layer_surface,
                             );
                             self.anchor_gap = 0;
                         }
-                    } else if self.config.autohide.is_some()
-                        && matches!(self.visibility, Visibility::Hidden)
+                    } else if self.config.autohide().is_some()
+                        && matches!(self.visibility, Visibility::Hidden | Visibility::Peek { .. })
                     {
                         if self.config.exclusive_zone() {
-                            layer_surface.set_exclusive_zone(list_thickness as i32);
+                            self.set_exclusive_zone(layer_surface, list_thickness as i32);
                         }
                         Self::set_margin(
                             self.config.anchor,
-                            self.config.get_margin() as i32,
+                            self.config.get_length_gaps(self.config.get_margin() as u32),
                             self.additional_gap,
+                            self.length_margins(),
+                            self.pixel_grid(),
                             layer_surface,
                         );
-                        self.anchor_gap = -(list_thickness as i32)
-                            + self.config.get_hide_handle().unwrap_or_default() as i32;
+                        let exposed = if matches!(self.visibility, Visibility::Peek { .. }) {
+                            self.config.get_peek_size()
+                        } else {
+                            self.config.get_hide_handle()
+                        };
+                        self.anchor_gap =
+                            -(list_thickness as i32) + exposed.unwrap_or_default() as i32;
                     }
                     layer_surface.wl_surface().commit();
                     layer_surface.wl_surface().frame(qh, layer_surface.wl_surface().clone());
@@ -858,7 +2763,10 @@ impl PanelSpace {
                 } else if self.layer.is_some() {
                     should_render = true;
                     if self.is_dirty {
-                        _ = self.layout_();
+                        // the frame would be discarded by the pending resize
+                        if let LayoutOutcome::NeedsResize(_) = self.layout_() {
+                            should_render = false;
+                        }
                     }
                 }
             },
@@ -869,7 +2777,7 @@ impl PanelSpace {
             self.popups.retain_mut(|p: &mut WrapperPopup| p.handle_events(popup_manager));
             self.handle_overflow_popup_events();
 
-            if prev == self.popups.len() && should_render {
+            if prev == self.popups.len() && should_render && self.applets_ready() {
                 if let Err(e) = self.render(renderer, time, throttle, qh) {
                     error!("Failed to render, error: {:?}", e);
                 }
@@ -910,6 +2818,7 @@ impl PanelSpace {
                         height = 1;
                     }
                     let dim = self.constrain_dim((width, height).into(), Some(self.gap() as u32));
+                    let dim = self.snap_size(dim, (w, h));
 
                     if first {
                         if self.additional_gap != 0 {
@@ -917,82 +2826,23 @@ impl PanelSpace {
                             // force update of the margin
                             self.set_additional_gap(additional_gap);
                         }
-                        let client_egl_surface = unsafe {
-                            ClientEglSurface::new(
-                                WlEglSurface::new(
-                                    self.layer.as_ref().unwrap().wl_surface().id(),
-                                    dim.w,
-                                    dim.h,
-                                )
-                                .unwrap(), // TODO remove unwrap
-                                self.layer.as_ref().unwrap().wl_surface().clone(),
-                            )
-                        };
-                        let new_egl_display = if let Some(renderer) = renderer.as_ref() {
-                            renderer.egl_context().display().clone()
-                        } else {
-                            let client_egl_display = ClientEglDisplay {
-                                display: self.c_display.as_ref().unwrap().clone(),
-                            };
-                            unsafe {
-                                EGLDisplay::new(client_egl_display)
-                                    .expect("Failed to create EGL display")
-                            }
-                        };
-
-                        let egl_context = EGLContext::new_with_config(
-                            &new_egl_display,
-                            GlAttributes {
-                                version: (2, 0),
-                                profile: None,
-                                debug: cfg!(debug_assertions),
-                                vsync: false,
-                            },
-                            PixelFormatRequirements::_8_bit(),
-                        )
-                        .expect("Failed to create EGL context");
-
-                        let mut new_renderer = if let Some(renderer) = renderer.take() {
-                            renderer
-                        } else {
-                            unsafe {
-                                let mut capabilities =
-                                    GlesRenderer::supported_capabilities(&egl_context)
-                                        .expect("Failed to query EGL Context");
-                                // capabilities.retain(|cap| *cap != Capability::);
-                                GlesRenderer::with_capabilities(egl_context, capabilities)
-                                    .expect("Failed to create EGL Surface")
-                            }
-                        };
-
-                        init_shaders(&mut new_renderer).expect("Failed to init shaders...");
-
-                        let egl_surface = Rc::new(unsafe {
-                            EGLSurface::new(
-                                &new_egl_display,
-                                new_renderer
-                                    .egl_context()
-                                    .pixel_format()
-                                    .expect("Failed to get pixel format from EGL context "),
-                                new_renderer.egl_context().config_id(),
-                                client_egl_surface,
-                            )
-                            .expect("Failed to create EGL Surface")
-                        });
-
-                        // bind before setting swap interval
-                        let _ = new_renderer.unbind();
-                        let _ = new_renderer.bind(egl_surface.clone());
-                        let swap_success =
-                            unsafe { SwapInterval(new_egl_display.get_display_handle().handle, 0) }
-                                == 1;
-                        if !swap_success {
-                            error!("Failed to set swap interval");
+                        if let Err(err) = self.init_egl(dim, renderer) {
+                            // keep waiting for the first configure so that the next one retries
+                            self.egl_failures += 1;
+                            error!(
+                                "Failed to initialize EGL for {} (attempt {}): {:?}",
+                                self.id(),
+                                self.egl_failures,
+                                err
+                            );
+                            self.space_event.replace(Some(SpaceEvent::WaitConfigure {
+                                first,
+                                width,
+                                height,
+                            }));
+                            return;
                         }
-                        let _ = new_renderer.unbind();
-
-                        renderer.replace(new_renderer);
-                        self.egl_surface.replace(egl_surface);
+                        self.egl_failures = 0;
                     }
                     if let (Some(renderer), Some(egl_surface)) =
                         (renderer.as_mut(), self.egl_surface.as_ref())
@@ -1013,6 +2863,7 @@ impl PanelSpace {
                         self.scale,
                         smithay::utils::Transform::Flipped180,
                     ));
+                    self.rendered_scale = self.scale;
                 },
                 SpaceEvent::Quit => (),
             },
@@ -1038,6 +2889,7 @@ impl PanelSpace {
                     height = 1;
                 }
                 let dim = self.constrain_dim((width, height).into(), Some(self.gap() as u32));
+                let dim = self.snap_size(dim, (w, h));
 
                 if let (Some(renderer), Some(egl_surface)) =
                     (renderer.as_mut(), self.egl_surface.as_ref())
@@ -1057,10 +2909,90 @@ impl PanelSpace {
                     self.scale,
                     smithay::utils::Transform::Flipped180,
                 ));
+                self.rendered_scale = self.scale;
             },
         }
     }
 
+    /// create the egl surface for the layer surface, and the renderer if there
+    /// is none yet
+    fn init_egl(
+        &mut self,
+        dim: Size<i32, Logical>,
+        renderer: &mut Option<GlesRenderer>,
+    ) -> anyhow::Result<()> {
+        let Some(layer) = self.layer.as_ref() else {
+            anyhow::bail!("Missing layer surface");
+        };
+        let wl_egl_surface = WlEglSurface::new(layer.wl_surface().id(), dim.w, dim.h)?;
+        let client_egl_surface =
+            unsafe { ClientEglSurface::new(wl_egl_surface, layer.wl_surface().clone()) };
+
+        let mut new_egl_display = if let Some(renderer) = renderer.as_ref() {
+            renderer.egl_context().display().clone()
+        } else {
+            let Some(display) = self.c_display.as_ref() else {
+                anyhow::bail!("Missing client display");
+            };
+            let client_egl_display = ClientEglDisplay { display: display.clone() };
+            unsafe { EGLDisplay::new(client_egl_display)? }
+        };
+
+        if renderer.is_none() {
+            let egl_context = match new_egl_context(&new_egl_display) {
+                Ok(egl_context) => egl_context,
+                Err(err) if enable_software_fallback() => {
+                    warn!("Failed to create a GL context: {:?}", err);
+                    // terminate the display so that it is initialized again
+                    // with the software driver
+                    drop(new_egl_display);
+                    let Some(display) = self.c_display.as_ref() else {
+                        anyhow::bail!("Missing client display");
+                    };
+                    let client_egl_display = ClientEglDisplay { display: display.clone() };
+                    new_egl_display = unsafe { EGLDisplay::new(client_egl_display)? };
+                    new_egl_context(&new_egl_display)?
+                },
+                Err(err) => return Err(err),
+            };
+
+            let new_renderer = unsafe {
+                let capabilities = GlesRenderer::supported_capabilities(&egl_context)?;
+                GlesRenderer::with_capabilities(egl_context, capabilities)?
+            };
+            renderer.replace(new_renderer);
+        }
+        let renderer = renderer.as_mut().unwrap();
+
+        init_shaders(renderer)?;
+        desaturate::init_shaders(renderer)?;
+
+        let Some(pixel_format) = renderer.egl_context().pixel_format() else {
+            anyhow::bail!("Failed to get pixel format from EGL context");
+        };
+        let egl_surface = Rc::new(unsafe {
+            EGLSurface::new(
+                &new_egl_display,
+                pixel_format,
+                renderer.egl_context().config_id(),
+                client_egl_surface,
+            )?
+        });
+
+        // bind before setting swap interval
+        let _ = renderer.unbind();
+        let _ = renderer.bind(egl_surface.clone());
+        let swap_success =
+            unsafe { SwapInterval(new_egl_display.get_display_handle().handle, 0) } == 1;
+        if !swap_success {
+            error!("Failed to set swap interval");
+        }
+        let _ = renderer.unbind();
+
+        self.egl_surface.replace(egl_surface);
+        Ok(())
+    }
+
     pub fn is_dark(&self, system_is_dark: bool) -> bool {
         match &self.config.background {
             CosmicPanelBackground::ThemeDefault | CosmicPanelBackground::Color(_) => system_is_dark,
@@ -1076,7 +3008,7 @@ impl PanelSpace {
         } else {
             let start = AnimatableState {
                 bg_color: self.colors.bg_color(self.config.opacity),
-                border_radius: self.config.border_radius,
+                border_radius: self.resolve_border_radius(self.config.border_radius),
                 expanded: if self.config.expand_to_edges { 1.0 } else { 0.0 },
                 gap: self.gap(),
             };
@@ -1090,16 +3022,31 @@ impl PanelSpace {
                 started_at: Instant::now(),
                 progress: 0.0,
                 duration: Duration::from_millis(300),
+                easing: Easing::default(),
             })
         }
 
         for e in self.space.elements() {
-            let CosmicMappedInternal::OverflowButton(b) = e else {
-                continue;
-            };
+            match e {
+                CosmicMappedInternal::OverflowButton(b) => {
+                    b.set_theme(colors.theme.clone());
+                    b.force_redraw();
+                },
+                CosmicMappedInternal::StartButton(b) => {
+                    b.set_theme(colors.theme.clone());
+                    b.force_redraw();
+                },
+                _ => {},
+            }
+        }
+        for b in self.applet_badges.values() {
             b.set_theme(colors.theme.clone());
             b.force_redraw();
         }
+        for (p, _) in self.crashed_applets.values() {
+            p.set_theme(colors.theme.clone());
+            p.force_redraw();
+        }
         for e in self
             .overflow_center
             .elements()
@@ -1129,24 +3076,76 @@ impl PanelSpace {
         self.space.refresh();
     }
 
-    pub fn apply_positioner_state(
-        &self,
-        positioner: &XdgPositioner,
-        pos_state: PositionerState,
-        s_surface: &PopupSurface,
+    /// show the applets of the mirrored panel, sharing its clients and
+    /// windows
+    pub(crate) fn mirror(
+        &mut self,
+        (left, center, right): (Clients, Clients, Clients),
+        windows: Vec<Window>,
+        dirty: bool,
     ) {
-        let PositionerState {
-            rect_size,
-            anchor_rect,
-            anchor_edges,
-            gravity,
-            constraint_adjustment,
-            offset,
-            reactive,
-            parent_size,
-            parent_configure: _,
-        } = pos_state;
-        let p_offset = if let Some(s) = self.space.elements().find(|w| {
+        self.clients_left = left;
+        self.clients_center = center;
+        self.clients_right = right;
+
+        let mapped: Vec<_> = self
+            .space
+            .elements()
+            .filter_map(|e| match e {
+                CosmicMappedInternal::Window(w) => Some(w.clone()),
+                _ => None,
+            })
+            .collect();
+        for w in mapped.iter().filter(|w| !windows.contains(w)) {
+            self.space.unmap_elem(&CosmicMappedInternal::Window(w.clone()));
+            self.is_dirty = true;
+        }
+        for w in windows.into_iter().filter(|w| !mapped.contains(w)) {
+            self.space.map_element(CosmicMappedInternal::Window(w), (0, 0), false);
+            self.is_dirty = true;
+        }
+        self.is_dirty |= dirty;
+    }
+
+    /// force a full redraw of the panel on the next render
+    ///
+    /// The damage tracker is replaced, so the whole surface is repainted
+    /// instead of only the damaged regions.
+    pub fn invalidate(&mut self) {
+        self.is_dirty = true;
+        self.damage_tracked_renderer = Some(OutputDamageTracker::new(
+            self.dimensions.to_f64().to_physical(self.scale).to_i32_round(),
+            self.scale,
+            smithay::utils::Transform::Flipped180,
+        ));
+    }
+
+    /// whether another output is adjacent to the panel's output at either end
+    /// of the panel
+    fn has_adjacent_output(&self) -> bool {
+        let Some(info) = self.output.as_ref().map(|o| &o.2) else {
+            return false;
+        };
+        let (Some((x, y)), Some((w, h))) = (info.logical_position, info.logical_size) else {
+            return false;
+        };
+        self.output_geometries.iter().any(|g| {
+            if self.config.is_horizontal() {
+                (g.loc.x == x + w || g.loc.x + g.size.w == x)
+                    && g.loc.y < y + h
+                    && y < g.loc.y + g.size.h
+            } else {
+                (g.loc.y == y + h || g.loc.y + g.size.h == y)
+                    && g.loc.x < x + w
+                    && x < g.loc.x + g.size.w
+            }
+        })
+    }
+
+    /// location of the parent of a popup, which its anchor rectangle is
+    /// relative to
+    pub(crate) fn popup_parent_offset(&self, s_surface: &PopupSurface) -> Point<i32, Logical> {
+        if let Some(s) = self.space.elements().find(|w| {
             s_surface
                 .get_parent_surface()
                 .is_some_and(|s| w.wl_surface().is_some_and(|w| w.as_ref() == &s))
@@ -1175,21 +3174,71 @@ impl PanelSpace {
         } else {
             tracing::warn!("No parent surface found for popup");
             (0, 0).into()
-        };
+        }
+    }
+
+    pub fn apply_positioner_state(
+        &self,
+        positioner: &XdgPositioner,
+        pos_state: PositionerState,
+        s_surface: &PopupSurface,
+    ) {
+        let PositionerState {
+            rect_size,
+            anchor_rect,
+            anchor_edges,
+            gravity,
+            constraint_adjustment,
+            offset,
+            reactive,
+            parent_size,
+            parent_configure: _,
+        } = pos_state;
+        let p_offset = self.popup_parent_offset(s_surface);
 
+        let rect_size = self.clamp_popup_size(rect_size);
         positioner.set_size(rect_size.w.max(1), rect_size.h.max(1));
-        positioner.set_anchor_rect(
-            anchor_rect.loc.x + p_offset.x,
-            anchor_rect.loc.y + p_offset.y,
-            anchor_rect.size.w,
-            anchor_rect.size.h,
-        );
+        let mut constraint_adjustment =
+            u32::from(constraint_adjustment).try_into().unwrap_or(ConstraintAdjustment::empty());
+        if let Some(pointer_pos) =
+            self.last_pointer_pos.filter(|_| self.popup_at_pointer(s_surface))
+        {
+            // zero sized anchor at the pointer, kept within the panel so the
+            // compositor can slide the popup back onto the output
+            let x = pointer_pos.x.clamp(0, self.dimensions.w.max(1) - 1);
+            let y = pointer_pos.y.clamp(0, self.dimensions.h.max(1) - 1);
+            positioner.set_anchor_rect(x, y, 0, 0);
+            constraint_adjustment |= ConstraintAdjustment::SlideX
+                | ConstraintAdjustment::SlideY
+                | ConstraintAdjustment::FlipX
+                | ConstraintAdjustment::FlipY;
+        } else {
+            positioner.set_anchor_rect(
+                anchor_rect.loc.x + p_offset.x,
+                anchor_rect.loc.y + p_offset.y,
+                anchor_rect.size.w,
+                anchor_rect.size.h,
+            );
+        }
         positioner.set_anchor(Anchor::try_from(anchor_edges as u32).unwrap_or(Anchor::None));
         positioner.set_gravity(Gravity::try_from(gravity as u32).unwrap_or(Gravity::None));
 
-        positioner.set_constraint_adjustment(
-            u32::from(constraint_adjustment).try_into().unwrap_or(ConstraintAdjustment::empty()),
-        );
+        // popups are clamped to the output by the constraint adjustment the
+        // applet requested
+        if self.config.popup_output_bounds == PopupOutputBounds::Span && self.has_adjacent_output()
+        {
+            // otherwise the compositor moves the popup back onto the output
+            constraint_adjustment.remove(if self.config.is_horizontal() {
+                ConstraintAdjustment::SlideX
+                    | ConstraintAdjustment::FlipX
+                    | ConstraintAdjustment::ResizeX
+            } else {
+                ConstraintAdjustment::SlideY
+                    | ConstraintAdjustment::FlipY
+                    | ConstraintAdjustment::ResizeY
+            });
+        }
+        positioner.set_constraint_adjustment(constraint_adjustment);
         positioner.set_offset(offset.x, offset.y);
         if positioner.version() >= 3 {
             if reactive {
@@ -1201,12 +3250,263 @@ impl PanelSpace {
         }
     }
 
+    /// grow the size of a popup to the configured minimum size, and shrink it
+    /// to fit the output if configured
+    pub fn clamp_popup_size(&self, mut size: Size<i32, Logical>) -> Size<i32, Logical> {
+        if let Some((min_w, min_h)) = self.config.popup_min_size {
+            size = (size.w.max(min_w as i32), size.h.max(min_h as i32)).into();
+        }
+        if let Some(max) = self.max_popup_size() {
+            size = (size.w.min(max.w), size.h.min(max.h)).into();
+        }
+        size
+    }
+
+    /// the largest size of a popup which fits on the output next to the panel
+    pub fn max_popup_size(&self) -> Option<Size<i32, Logical>> {
+        if self.config.oversized_popup == OversizedPopup::Unconstrained {
+            return None;
+        }
+        let info = &self.output.as_ref()?.2;
+        let (w, h) = info.logical_size.or_else(|| {
+            let mode = info.modes.iter().find(|m| m.current)?;
+            let scale = info.scale_factor.max(1);
+            Some((mode.dimensions.0 / scale, mode.dimensions.1 / scale))
+        })?;
+        let panel = self.crosswise() + self.gap() as i32;
+        let (w, h) = if self.config.is_horizontal() { (w, h - panel) } else { (w - panel, h) };
+        Some((w.max(1), h.max(1)).into())
+    }
+
+    /// whether the popup should be anchored to the pointer
+    /// only popups whose parent is an applet on the panel are affected
+    fn popup_at_pointer(&self, s_surface: &PopupSurface) -> bool {
+        let Some(client) = s_surface
+            .get_parent_surface()
+            .filter(|s| {
+                self.space.elements().any(|w| w.wl_surface().is_some_and(|w| w.as_ref() == s))
+            })
+            .and_then(|s| s.client())
+        else {
+            return false;
+        };
+        let left_guard = self.clients_left.lock().unwrap();
+        let center_guard = self.clients_center.lock().unwrap();
+        let right_guard = self.clients_right.lock().unwrap();
+        left_guard
+            .iter()
+            .chain(center_guard.iter())
+            .chain(right_guard.iter())
+            .find(|c| c.client.id() == client.id())
+            .and_then(|c| c.popup_at_pointer)
+            .unwrap_or(self.config.popup_at_pointer)
+    }
+
+    /// intercept a press on a hovered applet with the toggle click policy which
+    /// has an open popup, closing the popup instead
+    pub fn intercept_press(&mut self, seat_name: &str) -> bool {
+        let Some(client) = self
+            .s_hovered_surface
+            .iter()
+            .find(|h| h.seat_name == seat_name)
+            .and_then(|h| h.surface.wl_surface())
+            .filter(|s| self.space.elements().any(|w| w.wl_surface().is_some_and(|w| &w == s)))
+            .and_then(|s| s.client())
+        else {
+            return false;
+        };
+        let policy = {
+            let left_guard = self.clients_left.lock().unwrap();
+            let center_guard = self.clients_center.lock().unwrap();
+            let right_guard = self.clients_right.lock().unwrap();
+            left_guard
+                .iter()
+                .chain(center_guard.iter())
+                .chain(right_guard.iter())
+                .find(|c| c.client.id() == client.id())
+                .and_then(|c| c.click_policy)
+                .unwrap_or_default()
+        };
+        let owns_popup = self
+            .popups
+            .iter()
+            .any(|p| p.s_surface.wl_surface().client().is_some_and(|c| c.id() == client.id()));
+        // a press on the backdrop only dismisses the popup
+        let dismisses = self.config.popup_backdrop.is_some() && !self.popups.is_empty();
+        let toggles = policy == AppletClickPolicy::Toggle;
+        if !(if owns_popup { toggles } else { dismisses }) {
+            return false;
+        }
+        if owns_popup {
+            self.close_popups(|_| false);
+        } else {
+            self.dismiss_popups(|_| false);
+        }
+        self.intercepted_press = true;
+        true
+    }
+
+    /// whether the release of an intercepted press should be intercepted
+    pub fn intercept_release(&mut self) -> bool {
+        std::mem::take(&mut self.intercepted_press)
+    }
+
+    /// take the keyboard focus to navigate among the applets, starting with
+    /// the first one
+    pub fn start_keyboard_navigation(&mut self) {
+        let Some(layer) = self.layer.as_ref() else {
+            return;
+        };
+        layer.set_keyboard_interactivity(KeyboardInteractivity::Exclusive);
+        layer.wl_surface().commit();
+        self.keyboard_nav = Some(0);
+        self.keyboard_nav_commit.increment();
+        self.is_dirty = true;
+    }
+
+    /// stop navigating among the applets, returning the keyboard focus to the
+    /// compositor unless the panel is configured to keep it
+    pub fn stop_keyboard_navigation(&mut self) {
+        if self.keyboard_nav.take().is_none() {
+            return;
+        }
+        self.is_dirty = true;
+        let Some(layer) = self.layer.as_ref() else {
+            return;
+        };
+        layer.set_keyboard_interactivity(match self.config.keyboard_interactivity {
+            _ if self.popup_keyboard_grab => KeyboardInteractivity::OnDemand,
+            xdg_shell_wrapper_config::KeyboardInteractivity::None => KeyboardInteractivity::None,
+            xdg_shell_wrapper_config::KeyboardInteractivity::Exclusive => {
+                KeyboardInteractivity::Exclusive
+            },
+            xdg_shell_wrapper_config::KeyboardInteractivity::OnDemand => {
+                KeyboardInteractivity::OnDemand
+            },
+        });
+        layer.wl_surface().commit();
+    }
+
+    /// geometries of the applets and buttons which keyboard navigation moves
+    /// between, in the order of the panel
+    pub(crate) fn keyboard_nav_targets(&self) -> Vec<Rectangle<i32, Logical>> {
+        let mut targets = self
+            .space
+            .elements()
+            .filter(|e| {
+                matches!(
+                    e,
+                    CosmicMappedInternal::Window(_)
+                        | CosmicMappedInternal::OverflowButton(_)
+                        | CosmicMappedInternal::StartButton(_)
+                )
+            })
+            .filter_map(|e| {
+                let loc = self.space.element_location(e)?;
+                Some(Rectangle::from_loc_and_size(loc, e.bbox().size))
+            })
+            .filter(|r| r.size.w > 0 && r.size.h > 0)
+            .collect::<Vec<_>>();
+        if self.config.is_horizontal() {
+            targets.sort_by_key(|r| r.loc.x);
+        } else {
+            targets.sort_by_key(|r| r.loc.y);
+        }
+        targets
+    }
+
+    /// handle a key pressed while navigating among the applets with the
+    /// keyboard, returning the pointer events which activate the focused
+    /// applet, if any
+    ///
+    /// Returns `None` if the panel is not navigating, so the key is forwarded
+    /// as usual. Escape stops navigating, the arrow keys and Tab move the
+    /// focus, and Return or Space click the focused applet.
+    pub fn keyboard_nav_key(&mut self, keysym: Keysym) -> Option<Vec<PointerEvent>> {
+        let focused = self.keyboard_nav?;
+        let targets = self.keyboard_nav_targets();
+        if targets.is_empty() {
+            self.stop_keyboard_navigation();
+            return Some(Vec::new());
+        }
+        let focused = focused.min(targets.len() - 1);
+        match keysym {
+            Keysym::Escape => self.stop_keyboard_navigation(),
+            Keysym::Right | Keysym::Down | Keysym::Tab => {
+                self.keyboard_nav = Some((focused + 1) % targets.len());
+                self.keyboard_nav_commit.increment();
+                self.is_dirty = true;
+            },
+            Keysym::Left | Keysym::Up | Keysym::ISO_Left_Tab => {
+                self.keyboard_nav = Some((focused + targets.len() - 1) % targets.len());
+                self.keyboard_nav_commit.increment();
+                self.is_dirty = true;
+            },
+            Keysym::Return | Keysym::KP_Enter | Keysym::space => {
+                let surface = self.layer.as_ref()?.wl_surface().clone();
+                let rect = targets[focused];
+                let position =
+                    ((rect.loc.x + rect.size.w / 2) as f64, (rect.loc.y + rect.size.h / 2) as f64);
+                self.stop_keyboard_navigation();
+                // the serials and times are set when the events are handled
+                return Some(
+                    [
+                        PointerEventKind::Enter { serial: 0 },
+                        PointerEventKind::Press { time: 0, button: BTN_LEFT, serial: 0 },
+                        PointerEventKind::Release { time: 0, button: BTN_LEFT, serial: 0 },
+                    ]
+                    .into_iter()
+                    .map(|kind| PointerEvent { surface: surface.clone(), position, kind })
+                    .collect(),
+                );
+            },
+            _ => {},
+        }
+        Some(Vec::new())
+    }
+
+    /// whether the surfaces of the applet should be clipped to rounded corners
+    pub(crate) fn rounded_clip(&self, toplevel: &ToplevelSurface) -> bool {
+        if self.config.applet_corner_radius == 0 {
+            return false;
+        }
+        let Some(client) = toplevel.wl_surface().client() else {
+            return false;
+        };
+        let left_guard = self.clients_left.lock().unwrap();
+        let center_guard = self.clients_center.lock().unwrap();
+        let right_guard = self.clients_right.lock().unwrap();
+        left_guard
+            .iter()
+            .chain(center_guard.iter())
+            .chain(right_guard.iter())
+            .find(|c| c.client.id() == client.id())
+            .and_then(|c| c.rounded_clip)
+            .unwrap_or(true)
+    }
+
+    /// length reserved along the panel for the applet, if any
+    pub(crate) fn reserved_slot(&self, toplevel: &ToplevelSurface) -> Option<u32> {
+        let client = toplevel.wl_surface().client()?;
+        let left_guard = self.clients_left.lock().unwrap();
+        let center_guard = self.clients_center.lock().unwrap();
+        let right_guard = self.clients_right.lock().unwrap();
+        left_guard
+            .iter()
+            .chain(center_guard.iter())
+            .chain(right_guard.iter())
+            .find(|c| c.client.id() == client.id())
+            .and_then(|c| c.reserved_slot)
+    }
+
     pub fn update_config(
         &mut self,
-        config: CosmicPanelConfig,
+        mut config: CosmicPanelConfig,
         bg_color: Option<[f32; 4]>,
         animate: bool,
     ) {
+        config.apply_orientation_overrides();
+        config.resolve_margin(self.output.as_ref().and_then(|o| o.2.logical_size));
         let bg_color = bg_color.unwrap_or_else(|| self.colors.bg_color(config.opacity));
         // avoid animating if currently maximized
         if self.maximized {
@@ -1234,21 +3534,31 @@ impl PanelSpace {
                     -1
                 };
 
-                l.set_exclusive_zone(list_thickness);
+                self.set_exclusive_zone(l, list_thickness);
                 needs_commit = true;
             }
         }
 
-        if config.autohide.is_none() && self.config.autohide.is_some() {
+        if config.autohide().is_none() && self.config.autohide().is_some() {
             if let Some(l) = self.layer.as_ref() {
-                let margin = config.get_effective_anchor_gap() as i32;
-                Self::set_margin(config.anchor, margin, self.additional_gap, l);
+                let margin = config.get_length_gaps(config.get_effective_anchor_gap());
+                Self::set_margin(
+                    config.anchor,
+                    margin,
+                    self.additional_gap,
+                    self.with_corner_margins(Self::length_margins_for(
+                        config,
+                        self.output.as_ref().map(|o| &o.2),
+                    )),
+                    config.snap_to_pixel_grid.then_some(self.scale),
+                    l,
+                );
                 self.anchor_gap = 0;
                 let list_thickness = match self.config.anchor() {
                     PanelAnchor::Left | PanelAnchor::Right => self.dimensions.w,
                     PanelAnchor::Top | PanelAnchor::Bottom => self.dimensions.h,
                 };
-                l.set_exclusive_zone(list_thickness);
+                self.set_exclusive_zone(l, list_thickness);
                 let (width, height) = if self.config.is_horizontal() {
                     (0, self.dimensions.h)
                 } else {
@@ -1257,10 +3567,23 @@ impl PanelSpace {
                 l.set_size(width as u32, height as u32);
                 needs_commit = true;
             }
-        } else if self.config.get_effective_anchor_gap() != config.get_effective_anchor_gap() {
+        } else if self.config.get_effective_anchor_gap() != config.get_effective_anchor_gap()
+            || self.config.get_length_range() != config.get_length_range()
+            || self.config.gaps != config.gaps
+        {
             if let Some(l) = self.layer.as_ref() {
-                let margin = config.get_effective_anchor_gap() as i32;
-                Self::set_margin(config.anchor, margin, self.additional_gap, l);
+                let margin = config.get_length_gaps(config.get_effective_anchor_gap());
+                Self::set_margin(
+                    config.anchor,
+                    margin,
+                    self.additional_gap,
+                    self.with_corner_margins(Self::length_margins_for(
+                        config,
+                        self.output.as_ref().map(|o| &o.2),
+                    )),
+                    config.snap_to_pixel_grid.then_some(self.scale),
+                    l,
+                );
                 self.anchor_gap = 0;
                 needs_commit = true;
             }
@@ -1289,21 +3612,24 @@ impl PanelSpace {
         if animate {
             let start = AnimatableState {
                 bg_color: self.colors.bg_color(self.config.opacity),
-                border_radius: self.config.border_radius,
+                border_radius: self.resolve_border_radius(self.config.border_radius),
                 expanded: if self.config.expand_to_edges { 1.0 } else { 0.0 },
                 gap: self.gap(),
             };
             let end = AnimatableState {
                 bg_color,
-                border_radius: config.border_radius,
+                border_radius: self.resolve_border_radius(config.border_radius),
                 expanded: if config.expand_to_edges { 1.0 } else { 0.0 },
                 gap: config.get_effective_anchor_gap() as u16,
             };
+            let duration = Duration::from_millis(config.expand_animation.duration.into());
             if let Some(animated_state) = self.animate_state.as_mut() {
                 animated_state.start = animated_state.cur.clone();
                 animated_state.end = end;
                 animated_state.started_at = Instant::now();
                 animated_state.progress = 0.0;
+                animated_state.duration = duration;
+                animated_state.easing = config.expand_animation.easing;
             } else {
                 self.animate_state = Some(AnimateState {
                     cur: start.clone(),
@@ -1311,12 +3637,23 @@ impl PanelSpace {
                     end,
                     progress: 0.0,
                     started_at: Instant::now(),
-                    duration: Duration::from_millis(300), // TODO make configurable
+                    duration,
+                    easing: config.expand_animation.easing,
                 });
             }
         }
 
+        // restarted applets read whether they are sandboxed from their client,
+        // and the layout whether they are pinned
+        for clients in [&self.clients_left, &self.clients_center, &self.clients_right] {
+            for c in clients.lock().unwrap().iter_mut() {
+                c.sandboxed = config.sandboxed_applets.contains(&c.name);
+                c.pin = config.pinned_applets.contains(&c.instance_id());
+            }
+        }
+
         self.config = config;
+        self.load_background_image();
 
         self.clear();
     }
@@ -1389,11 +3726,11 @@ impl PanelSpace {
         }
         let bg_color = self.colors.bg_color(opacity);
         if !self.maximized {
-            self.update_config(config, Some(bg_color), self.config.autohide.is_none());
+            self.update_config(config, Some(bg_color), self.config.autohide().is_none());
             self.maximized = maximized;
         } else {
             self.maximized = maximized;
-            self.update_config(config, Some(bg_color), self.config.autohide.is_none());
+            self.update_config(config, Some(bg_color), self.config.autohide().is_none());
             if let Some(s) = self.animate_state.as_mut() {
                 s.end.bg_color[3] = self.config.opacity;
             }
@@ -1405,6 +3742,15 @@ impl PanelSpace {
 
 impl Drop for PanelSpace {
     fn drop(&mut self) {
+        for (_, token) in self.pending_starts.drain(..) {
+            self.loop_handle.remove(token);
+        }
+        if let Some(token) = self.awaiting_applets_timer.take() {
+            self.loop_handle.remove(token);
+        }
+        if let Some(token) = self.popup_retry_timer.take() {
+            self.loop_handle.remove(token);
+        }
         // request processes to stop
         let _ = self.applet_tx.try_send(AppletMsg::Cleanup(self.id()));
     }
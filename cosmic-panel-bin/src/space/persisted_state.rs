@@ -0,0 +1,47 @@
+use std::{fs, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Runtime state of a panel which may be restored after a restart
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PersistedState {
+    /// whether the panel was visible
+    pub visible: bool,
+    /// whether the panel was pinned, preventing it from hiding
+    pub pinned: bool,
+    /// opacity override which was applied at runtime
+    pub opacity: Option<f32>,
+    /// whether the collapsible applets were collapsed
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
+impl PersistedState {
+    fn path(key: &str) -> Option<PathBuf> {
+        xdg::BaseDirectories::with_prefix("cosmic-panel")
+            .ok()
+            .and_then(|dirs| dirs.place_state_file(format!("{key}.ron")).ok())
+    }
+
+    /// load the state for the panel with the given key
+    pub fn load(key: &str) -> Option<Self> {
+        let path = Self::path(key)?;
+        let s = fs::read_to_string(path).ok()?;
+        match ron::from_str(&s) {
+            Ok(state) => Some(state),
+            Err(err) => {
+                tracing::warn!("Failed to parse persisted state for {key}: {err}");
+                None
+            },
+        }
+    }
+
+    /// save the state for the panel with the given key
+    pub fn save(&self, key: &str) -> anyhow::Result<()> {
+        let Some(path) = Self::path(key) else {
+            anyhow::bail!("Failed to find state directory");
+        };
+        fs::write(path, ron::to_string(self)?)?;
+        Ok(())
+    }
+}
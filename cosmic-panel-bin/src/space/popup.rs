@@ -1,12 +1,17 @@
-use std::rc::Rc;
+use std::{collections::HashMap, rc::Rc, time::Duration};
 
 use crate::xdg_shell_wrapper::space::{ClientEglSurface, PanelPopup, WrapperPopupState};
-use cctk::wayland_client::Proxy;
-use sctk::shell::xdg::popup::{self};
+use cctk::wayland_client::{protocol::wl_seat::WlSeat, Proxy};
+use sctk::shell::{
+    wlr_layer::KeyboardInteractivity,
+    xdg::popup::{self},
+    WaylandSurface,
+};
 use smithay::{
     backend::{egl::EGLSurface, renderer::gles::GlesRenderer},
+    reexports::wayland_server::Resource,
     utils::Rectangle,
-    wayland::seat::WaylandFocus,
+    wayland::{seat::WaylandFocus, shell::xdg::PopupSurface},
 };
 use wayland_egl::WlEglSurface;
 
@@ -14,10 +19,26 @@ use super::PanelSpace;
 
 impl PanelSpace {
     pub(crate) fn close_popups<'a>(&mut self, exclude: impl Fn(&PanelPopup) -> bool) {
+        self.close_popups_opened_before(exclude, Duration::ZERO);
+    }
+
+    /// close popups on an implicit dismissal, like a press outside of them,
+    /// keeping those opened within the minimum popup lifetime
+    pub(crate) fn dismiss_popups(&mut self, exclude: impl Fn(&PanelPopup) -> bool) {
+        let min_lifetime = Duration::from_millis(self.config.popup_min_lifetime.into());
+        self.close_popups_opened_before(exclude, min_lifetime);
+    }
+
+    fn close_popups_opened_before(
+        &mut self,
+        exclude: impl Fn(&PanelPopup) -> bool,
+        min_lifetime: Duration,
+    ) {
         tracing::info!("Closing popups");
         let mut to_destroy = Vec::with_capacity(self.popups.len());
         self.popups.retain_mut(|p| {
-            if exclude(&p.popup) {
+            // guard against spurious clicks right after opening
+            if exclude(&p.popup) || p.opened.elapsed() < min_lifetime {
                 return true;
             }
 
@@ -33,6 +54,7 @@ impl PanelSpace {
         if self.overflow_popup.as_ref().is_some_and(|(p, _)| !exclude(&p)) {
             let (popup, _) = self.overflow_popup.take().unwrap();
             tracing::info!("Closing overflow popup: {:?}", popup.c_popup.wl_surface());
+            self.set_overflow_button_selected(None);
             to_destroy.push((
                 popup.c_popup.xdg_popup().clone(),
                 popup.c_popup.wl_surface().clone(),
@@ -53,6 +75,38 @@ impl PanelSpace {
             popup.destroy();
             surface.destroy();
         }
+
+        if self.popup_keyboard_grab && self.popups.is_empty() {
+            self.popup_keyboard_grab = false;
+            if let Some(layer) = self.layer.as_ref() {
+                layer.set_keyboard_interactivity(KeyboardInteractivity::None);
+                layer.wl_surface().commit();
+            }
+        }
+    }
+
+    /// grab the popup of an applet which requested a grab, so that it receives
+    /// keyboard input until it is closed
+    ///
+    /// The layer surface requests keyboard interactivity while the popup is
+    /// open if it has none, as compositors may not focus popups of a layer
+    /// surface without it.
+    pub(crate) fn grab_popup(&mut self, s_surface: &PopupSurface, seat: (u32, WlSeat)) -> bool {
+        let Some(p) = self.popups.iter().find(|p| &p.s_surface == s_surface) else {
+            return false;
+        };
+        p.popup.c_popup.xdg_popup().grab(&seat.1, seat.0);
+        if self.config.keyboard_interactivity
+            == xdg_shell_wrapper_config::KeyboardInteractivity::None
+            && !self.popup_keyboard_grab
+        {
+            if let Some(layer) = self.layer.as_ref() {
+                layer.set_keyboard_interactivity(KeyboardInteractivity::OnDemand);
+                layer.wl_surface().commit();
+                self.popup_keyboard_grab = true;
+            }
+        }
+        true
     }
 
     pub fn configure_panel_popup(
@@ -64,6 +118,10 @@ impl PanelSpace {
         let Some(renderer) = renderer else {
             return;
         };
+        let min_size = self.config.popup_min_size;
+        let max_size = self.max_popup_size();
+        let max_attempts = self.config.popup_surface_attempts.max(1);
+        let mut retry = false;
 
         if let Some((p, s_popup)) = self
             .popups
@@ -79,6 +137,15 @@ impl PanelSpace {
             if config.height == 0 {
                 config.height = p.wrapper_rectangle.size.h;
             }
+            if let Some((min_w, min_h)) = min_size {
+                config.width = config.width.max(min_w as i32);
+                config.height = config.height.max(min_h as i32);
+            }
+            let requested = (config.width, config.height);
+            if let Some(max) = max_size {
+                config.width = config.width.min(max.w);
+                config.height = config.height.min(max.h);
+            }
             let (width, height) = (config.width, config.height);
             p.wrapper_rectangle = Rectangle::from_loc_and_size(config.position, (width, height));
 
@@ -88,41 +155,44 @@ impl PanelSpace {
             };
 
             if let Some(s) = s_popup {
+                // let the applet fill the enforced size, content which does not
+                // fit into a clamped popup is clipped
+                if min_size.is_some()
+                    || requested != (width, height)
+                    || !matches!(config.kind, popup::ConfigureKind::Initial)
+                {
+                    s.with_pending_state(|pending| {
+                        pending.geometry.size = (width, height).into();
+                    });
+                }
                 _ = s.send_configure()
             }
 
             match config.kind {
                 popup::ConfigureKind::Initial => {
                     tracing::info!("Popup Initial Configure");
-                    let wl_egl_surface =
-                        match WlEglSurface::new(p.c_popup.wl_surface().id(), width, height) {
-                            Ok(s) => s,
-                            Err(err) => {
-                                tracing::error!("Failed to create WlEglSurface: {:?}", err);
-                                return;
-                            },
-                        };
-                    let client_egl_surface = unsafe {
-                        ClientEglSurface::new(wl_egl_surface, p.c_popup.wl_surface().clone())
-                    };
-                    let egl_surface = Rc::new(unsafe {
-                        EGLSurface::new(
-                            renderer.egl_context().display(),
-                            renderer
-                                .egl_context()
-                                .pixel_format()
-                                .expect("Failed to get pixel format from EGL context "),
-                            renderer.egl_context().config_id(),
-                            client_egl_surface,
-                        )
-                        .expect("Failed to initialize EGL Surface")
-                    });
-                    p.egl_surface.replace(egl_surface);
+                    if width <= 0 || height <= 0 {
+                        tracing::error!("Invalid popup size: {}x{}", width, height);
+                        return;
+                    }
+                    retry = init_popup_egl_surface(p, renderer, (width, height), max_attempts);
+                },
+                popup::ConfigureKind::Reactive | popup::ConfigureKind::Reposition { .. } => {
+                    tracing::debug!("Popup repositioned");
+                    if p.egl_surface.is_none() && p.egl_attempts > 0 {
+                        retry = init_popup_egl_surface(p, renderer, (width, height), max_attempts);
+                    }
+                    // the compositor may also have resized the popup
+                    if p.rectangle.size != (width, height).into() {
+                        p.state = Some(WrapperPopupState::Rectangle {
+                            x: p.rectangle.loc.x,
+                            y: p.rectangle.loc.y,
+                            width,
+                            height,
+                        });
+                    }
                     p.dirty = true;
-                    tracing::info!("Popup configured");
                 },
-                popup::ConfigureKind::Reactive => {},
-                popup::ConfigureKind::Reposition { token: _token } => {},
                 _ => {},
             };
         } else if self
@@ -139,6 +209,10 @@ impl PanelSpace {
             if config.height == 0 {
                 config.height = p.wrapper_rectangle.size.h;
             }
+            if let Some(max) = max_size {
+                config.width = config.width.min(max.w);
+                config.height = config.height.min(max.h);
+            }
             let (width, height) = (config.width, config.height);
             p.wrapper_rectangle = Rectangle::from_loc_and_size(config.position, (width, height));
 
@@ -152,40 +226,248 @@ impl PanelSpace {
                     tracing::info!("Popup Initial Configure");
                     let width_scaled = (width as f64 * self.scale) as i32;
                     let height_scaled = (height as f64 * self.scale) as i32;
-                    let wl_egl_surface = match WlEglSurface::new(
-                        p.c_popup.wl_surface().id(),
-                        width_scaled,
-                        height_scaled,
-                    ) {
-                        Ok(s) => s,
-                        Err(err) => {
-                            tracing::error!("Failed to create WlEglSurface: {:?}", err);
-                            return;
-                        },
-                    };
-                    let client_egl_surface = unsafe {
-                        ClientEglSurface::new(wl_egl_surface, p.c_popup.wl_surface().clone())
-                    };
-                    let egl_surface = Rc::new(unsafe {
-                        EGLSurface::new(
-                            renderer.egl_context().display(),
-                            renderer
-                                .egl_context()
-                                .pixel_format()
-                                .expect("Failed to get pixel format from EGL context "),
-                            renderer.egl_context().config_id(),
-                            client_egl_surface,
-                        )
-                        .expect("Failed to initialize EGL Surface")
-                    });
-                    p.egl_surface.replace(egl_surface);
-                    p.dirty = true;
-                    tracing::info!("Popup configured");
+                    if width_scaled <= 0 || height_scaled <= 0 {
+                        tracing::error!("Invalid popup size: {}x{}", width, height);
+                        return;
+                    }
+                    retry = init_popup_egl_surface(
+                        p,
+                        renderer,
+                        (width_scaled, height_scaled),
+                        max_attempts,
+                    );
+                },
+                popup::ConfigureKind::Reactive | popup::ConfigureKind::Reposition { .. } => {
+                    if p.egl_surface.is_none() && p.egl_attempts > 0 {
+                        let width_scaled = (width as f64 * self.scale) as i32;
+                        let height_scaled = (height as f64 * self.scale) as i32;
+                        retry = init_popup_egl_surface(
+                            p,
+                            renderer,
+                            (width_scaled, height_scaled),
+                            max_attempts,
+                        );
+                    }
                 },
-                popup::ConfigureKind::Reactive => {},
-                popup::ConfigureKind::Reposition { token: _token } => {},
                 _ => {},
             };
         }
+        if retry {
+            self.schedule_popup_retry();
+        }
+    }
+
+    /// retry to create the surfaces of popups which can't be repositioned
+    pub(crate) fn retry_popup_surfaces(&mut self, renderer: &mut GlesRenderer) {
+        self.popup_retry_timer = None;
+        let max_attempts = self.config.popup_surface_attempts.max(1);
+        let mut retry = false;
+        for p in self.popups.iter_mut().map(|p| &mut p.popup) {
+            if p.egl_surface.is_none() && p.egl_attempts > 0 {
+                let size = p.wrapper_rectangle.size;
+                retry |= init_popup_egl_surface(p, renderer, (size.w, size.h), max_attempts);
+            }
+        }
+        if let Some((p, _)) = self.overflow_popup.as_mut() {
+            if p.egl_surface.is_none() && p.egl_attempts > 0 {
+                let size = p.wrapper_rectangle.size;
+                let width_scaled = (size.w as f64 * self.scale) as i32;
+                let height_scaled = (size.h as f64 * self.scale) as i32;
+                retry |= init_popup_egl_surface(
+                    p,
+                    renderer,
+                    (width_scaled, height_scaled),
+                    max_attempts,
+                );
+            }
+        }
+        if retry {
+            self.schedule_popup_retry();
+        }
+    }
+
+    fn schedule_popup_retry(&mut self) {
+        if self.popup_retry_timer.is_some() {
+            return;
+        }
+        let id = self.id();
+        self.popup_retry_timer = self
+            .loop_handle
+            .insert_source(
+                calloop::timer::Timer::from_duration(POPUP_SURFACE_RETRY),
+                move |_, _, state| {
+                    state.space.retry_popup_surfaces(&id);
+                    calloop::timer::TimeoutAction::Drop
+                },
+            )
+            .map_err(|err| tracing::error!("Failed to schedule a popup surface retry: {}", err))
+            .ok();
+    }
+
+    /// reposition the reactive popups of applets which moved
+    ///
+    /// The positioner of each reactive popup is rebuilt from the state last
+    /// set by its applet, relative to the current location of its parent. With
+    /// `force`, reactive popups are repositioned even if their parent did not
+    /// move, e.g. after the output changed.
+    pub(crate) fn reposition_reactive_popups(&mut self, force: bool) {
+        let mut anchors = HashMap::new();
+        for p in &self.popups {
+            let pos_state = p.s_surface.with_pending_state(|s| s.positioner);
+            if !pos_state.reactive || p.popup.positioner.version() < 3 {
+                continue;
+            }
+            let id = p.s_surface.wl_surface().id();
+            let offset = self.popup_parent_offset(&p.s_surface);
+            let moved = self.reactive_popup_anchors.get(&id).is_some_and(|prev| *prev != offset);
+            anchors.insert(id, offset);
+            if !moved && !force {
+                continue;
+            }
+            self.apply_positioner_state(&p.popup.positioner, pos_state, &p.s_surface);
+            p.popup.c_popup.reposition(&p.popup.positioner, 0);
+            p.popup.c_popup.wl_surface().commit();
+        }
+        self.reactive_popup_anchors = anchors;
+    }
+}
+
+/// delay before retrying to create the surface of a popup which can't be
+/// repositioned
+const POPUP_SURFACE_RETRY: Duration = Duration::from_millis(100);
+
+/// what follows a failed attempt to create the surface of a popup
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PopupRetry {
+    /// retry on the configure answering a reposition of the popup
+    Reposition,
+    /// retry on a timer, as positioners before version 3 can't reposition
+    Timer,
+    /// give up on the surface
+    GiveUp,
+}
+
+/// count a failed attempt to create the surface of a popup
+fn popup_retry(attempts: &mut u32, max_attempts: u32, positioner_version: u32) -> PopupRetry {
+    *attempts = (*attempts + 1).min(max_attempts);
+    if *attempts >= max_attempts {
+        PopupRetry::GiveUp
+    } else if positioner_version >= 3 {
+        PopupRetry::Reposition
+    } else {
+        PopupRetry::Timer
+    }
+}
+
+/// create the egl surface of a popup which has none yet
+///
+/// A failed attempt asks the compositor to reposition the popup, so that it is
+/// retried on the following configure, until `max_attempts` attempts failed.
+/// Returns whether the attempt must be retried on a timer instead, as the
+/// popup can't be repositioned.
+fn init_popup_egl_surface(
+    p: &mut PanelPopup,
+    renderer: &mut GlesRenderer,
+    (width, height): (i32, i32),
+    max_attempts: u32,
+) -> bool {
+    if p.egl_attempts >= max_attempts {
+        return false;
+    }
+    if let Some(egl_surface) = create_popup_egl_surface(p, renderer, width, height) {
+        p.egl_surface.replace(egl_surface);
+        p.egl_attempts = 0;
+        p.dirty = true;
+        tracing::info!("Popup configured");
+        return false;
+    }
+    match popup_retry(&mut p.egl_attempts, max_attempts, p.positioner.version()) {
+        PopupRetry::GiveUp => {
+            tracing::error!(
+                "Giving up on the surface of popup {:?} after {} attempts",
+                p.c_popup.wl_surface(),
+                max_attempts
+            );
+            false
+        },
+        retry => {
+            tracing::warn!(
+                "Retrying to create the surface of popup {:?} ({}/{})",
+                p.c_popup.wl_surface(),
+                p.egl_attempts,
+                max_attempts
+            );
+            if retry == PopupRetry::Timer {
+                return true;
+            }
+            // a reposition is answered with a configure, even without changes
+            p.c_popup.reposition(&p.positioner, 0);
+            p.c_popup.wl_surface().commit();
+            false
+        },
+    }
+}
+
+fn create_popup_egl_surface(
+    p: &PanelPopup,
+    renderer: &mut GlesRenderer,
+    width: i32,
+    height: i32,
+) -> Option<Rc<EGLSurface>> {
+    let wl_egl_surface = match WlEglSurface::new(p.c_popup.wl_surface().id(), width, height) {
+        Ok(s) => s,
+        Err(err) => {
+            tracing::error!("Failed to create WlEglSurface: {:?}", err);
+            return None;
+        },
+    };
+    let client_egl_surface =
+        unsafe { ClientEglSurface::new(wl_egl_surface, p.c_popup.wl_surface().clone()) };
+    let Some(pixel_format) = renderer.egl_context().pixel_format() else {
+        tracing::error!("Failed to get pixel format from EGL context");
+        return None;
+    };
+    match unsafe {
+        EGLSurface::new(
+            renderer.egl_context().display(),
+            pixel_format,
+            renderer.egl_context().config_id(),
+            client_egl_surface,
+        )
+    } {
+        Ok(s) => Some(Rc::new(s)),
+        Err(err) => {
+            tracing::error!("Failed to initialize EGL Surface: {:?}", err);
+            None
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failed_popup_surfaces_are_retried_until_the_last_attempt() {
+        let mut attempts = 0;
+        assert_eq!(popup_retry(&mut attempts, 3, 3), PopupRetry::Reposition);
+        assert_eq!(attempts, 1);
+        assert_eq!(popup_retry(&mut attempts, 3, 3), PopupRetry::Reposition);
+        assert_eq!(popup_retry(&mut attempts, 3, 3), PopupRetry::GiveUp);
+        assert_eq!(attempts, 3);
+        // the counter stays at the limit
+        assert_eq!(popup_retry(&mut attempts, 3, 3), PopupRetry::GiveUp);
+        assert_eq!(attempts, 3);
+    }
+
+    #[test]
+    fn popups_which_cant_be_repositioned_are_retried_on_a_timer() {
+        let mut attempts = 0;
+        assert_eq!(popup_retry(&mut attempts, 2, 2), PopupRetry::Timer);
+        assert_eq!(popup_retry(&mut attempts, 2, 2), PopupRetry::GiveUp);
+
+        // a single attempt is never retried
+        let mut attempts = 0;
+        assert_eq!(popup_retry(&mut attempts, 1, 1), PopupRetry::GiveUp);
     }
 }
@@ -1,9 +1,13 @@
-use std::{collections::HashSet, time::Duration};
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
 
 use crate::iced::elements::{CosmicMappedInternal, PopupMappedInternal};
 
 use super::{
-    corner_element::{RoundedRectangleShader, RoundedRectangleShaderElement},
+    corner_element::{RoundedClipShader, RoundedRectangleShader, RoundedRectangleShaderElement},
+    desaturate::{AppletShading, DesaturateShader},
     layout::OverflowSection,
     PanelSpace,
 };
@@ -11,30 +15,60 @@ use cctk::wayland_client::{Proxy, QueueHandle};
 use itertools::Itertools;
 
 use crate::xdg_shell_wrapper::shared_state::GlobalState;
-use cosmic_panel_config::PanelAnchor;
+use cosmic_panel_config::{BadgeCorner, BorderRadius, PanelAnchor};
 use sctk::shell::WaylandSurface;
 use smithay::{
     backend::renderer::{
+        buffer_dimensions,
         damage::OutputDamageTracker,
         element::{
             memory::MemoryRenderBufferRenderElement,
+            solid::SolidColorRenderElement,
             surface::{render_elements_from_surface_tree, WaylandSurfaceRenderElement},
             utils::CropRenderElement,
             AsRenderElements, RenderElement, UnderlyingStorage,
         },
-        gles::{GlesError, GlesFrame, GlesRenderer},
+        gles::{GlesError, GlesFrame, GlesRenderer, GlesTexProgram},
+        utils::CommitCounter,
         Bind, Color32F, Frame, Renderer, Unbind,
     },
     reexports::wayland_server::Resource,
-    utils::{Buffer, Physical, Point, Rectangle},
-    wayland::seat::WaylandFocus,
+    utils::{Buffer, Logical, Physical, Point, Rectangle, Size},
+    wayland::{seat::WaylandFocus, shell::xdg::ToplevelSurface},
 };
 
+/// logical thickness of the progress indicator
+const PROGRESS_THICKNESS: i32 = 3;
+
+/// interval between render statistics logged at the debug level
+const RENDER_STATS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// timing and size of the last frame rendered for a panel
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RenderStats {
+    /// time spent building, drawing and presenting the frame
+    pub duration: Duration,
+    /// number of elements in the frame
+    pub elements: usize,
+    /// area of the damaged regions of the frame, in physical pixels
+    pub damage_area: i64,
+    /// number of frames rendered since the panel was created
+    pub frames: u64,
+}
+
 pub(crate) enum PanelRenderElement {
     Wayland(WaylandSurfaceRenderElement<GlesRenderer>),
     Crop(CropRenderElement<WaylandSurfaceRenderElement<GlesRenderer>>),
     RoundedRectangle(RoundedRectangleShaderElement),
     Iced(MemoryRenderBufferRenderElement<GlesRenderer>),
+    /// drawn desaturated or mirrored in its applet
+    Desaturated(Box<PanelRenderElement>, GlesTexProgram, AppletShading),
+    /// clipped to a rounded rectangle with the given radius, and drawn
+    /// desaturated or mirrored in its applet
+    RoundedClip(Box<PanelRenderElement>, GlesTexProgram, f32, AppletShading),
+    /// solid fill, such as the highlight of an applet which accepts drops or a
+    /// section divider
+    Highlight(SolidColorRenderElement),
 }
 
 impl smithay::backend::renderer::element::Element for PanelRenderElement {
@@ -44,6 +78,9 @@ impl smithay::backend::renderer::element::Element for PanelRenderElement {
             Self::Crop(e) => e.id(),
             Self::RoundedRectangle(e) => e.id(),
             Self::Iced(e) => e.id(),
+            Self::Desaturated(e, ..) => e.id(),
+            Self::RoundedClip(e, ..) => e.id(),
+            Self::Highlight(e) => e.id(),
         }
     }
 
@@ -53,6 +90,9 @@ impl smithay::backend::renderer::element::Element for PanelRenderElement {
             Self::Crop(e) => e.current_commit(),
             Self::RoundedRectangle(e) => e.current_commit(),
             Self::Iced(e) => e.current_commit(),
+            Self::Desaturated(e, ..) => e.current_commit(),
+            Self::RoundedClip(e, ..) => e.current_commit(),
+            Self::Highlight(e) => e.current_commit(),
         }
     }
 
@@ -62,6 +102,9 @@ impl smithay::backend::renderer::element::Element for PanelRenderElement {
             Self::Crop(e) => e.src(),
             Self::RoundedRectangle(e) => e.src(),
             Self::Iced(e) => e.src(),
+            Self::Desaturated(e, ..) => e.src(),
+            Self::RoundedClip(e, ..) => e.src(),
+            Self::Highlight(e) => e.src(),
         }
     }
 
@@ -72,6 +115,21 @@ impl smithay::backend::renderer::element::Element for PanelRenderElement {
             Self::RoundedRectangle(e) => e.geometry(scale),
             // XXX hack don't know how else to avoid scaling twice
             Self::Iced(e) => e.geometry(1.0.into()),
+            Self::Desaturated(e, _, shading) => shading.mirrored(e.geometry(scale)),
+            Self::RoundedClip(e, _, _, shading) => shading.mirrored(e.geometry(scale)),
+            Self::Highlight(e) => e.geometry(scale),
+        }
+    }
+
+    fn alpha(&self) -> f32 {
+        match self {
+            Self::Wayland(e) => e.alpha(),
+            Self::Crop(e) => e.alpha(),
+            Self::RoundedRectangle(e) => e.alpha(),
+            Self::Iced(e) => e.alpha(),
+            Self::Desaturated(e, ..) => e.alpha(),
+            Self::RoundedClip(e, ..) => e.alpha(),
+            Self::Highlight(e) => e.alpha(),
         }
     }
 }
@@ -90,6 +148,18 @@ impl RenderElement<GlesRenderer> for PanelRenderElement {
             Self::Crop(e) => e.draw(frame, src, dst, damage, opaque_regions),
             Self::RoundedRectangle(e) => e.draw(frame, src, dst, damage, opaque_regions),
             Self::Iced(e) => e.draw(frame, src, dst, damage, opaque_regions),
+            Self::Desaturated(e, program, shading) => {
+                DesaturateShader::draw_with(frame, program, *shading, src, dst, |frame| {
+                    e.draw(frame, src, dst, damage, opaque_regions)
+                })
+            },
+            Self::RoundedClip(e, program, radius, shading) => {
+                // the corners are transparent, so nothing is opaque
+                RoundedClipShader::draw_with(frame, program, *shading, *radius, src, dst, |frame| {
+                    e.draw(frame, src, dst, damage, &[])
+                })
+            },
+            Self::Highlight(e) => e.draw(frame, src, dst, damage, opaque_regions),
         }
     }
 
@@ -99,6 +169,9 @@ impl RenderElement<GlesRenderer> for PanelRenderElement {
             PanelRenderElement::Crop(e) => e.underlying_storage(renderer),
             PanelRenderElement::RoundedRectangle(e) => e.underlying_storage(renderer),
             PanelRenderElement::Iced(e) => e.underlying_storage(renderer),
+            PanelRenderElement::Desaturated(e, ..) => e.underlying_storage(renderer),
+            PanelRenderElement::RoundedClip(e, ..) => e.underlying_storage(renderer),
+            PanelRenderElement::Highlight(e) => e.underlying_storage(renderer),
         }
     }
 }
@@ -120,6 +193,35 @@ impl PanelSpace {
 
         let clear_color = [0., 0., 0., 0.];
 
+        let render_scale = self.render_scale();
+        if render_scale != self.rendered_scale {
+            if let Some(egl_surface) = self.egl_surface.as_ref() {
+                let scaled_size = self.dimensions.to_f64().to_physical(render_scale).to_i32_round();
+                renderer.unbind()?;
+                renderer.bind(egl_surface.clone())?;
+                egl_surface.resize(scaled_size.w.max(1), scaled_size.h.max(1), 0, 0);
+                self.damage_tracked_renderer = Some(OutputDamageTracker::new(
+                    scaled_size,
+                    render_scale,
+                    smithay::utils::Transform::Flipped180,
+                ));
+            }
+            self.rendered_scale = render_scale;
+            self.is_dirty = true;
+        }
+
+        // the saturation is not tracked by the elements, so redraw everything
+        let saturation = self.saturation();
+        if saturation != self.rendered_saturation {
+            self.damage_tracked_renderer = Some(OutputDamageTracker::new(
+                self.dimensions.to_f64().to_physical(render_scale).to_i32_round(),
+                render_scale,
+                smithay::utils::Transform::Flipped180,
+            ));
+            self.rendered_saturation = saturation;
+            self.is_dirty = true;
+        }
+
         if self.is_dirty && self.has_frame {
             let hovered_clients: HashSet<_> = self
                 .s_hovered_surface
@@ -128,18 +230,22 @@ impl PanelSpace {
                 .filter_map(|c| c.surface.wl_surface().map(|s| s.id()))
                 .collect();
             tracing::trace!("Rendering space");
-            let my_renderer = match self.damage_tracked_renderer.as_mut() {
-                Some(r) => r,
-                None => return Ok(()),
-            };
+            if self.damage_tracked_renderer.is_none() {
+                return Ok(());
+            }
             renderer.unbind()?;
             renderer.bind(self.egl_surface.as_ref().unwrap().clone())?;
             // if not visible, just clear and exit early
-            let not_visible = self.config.autohide.is_some()
-                && matches!(self.visibility, crate::xdg_shell_wrapper::space::Visibility::Hidden);
-            let dim = self.dimensions.to_f64().to_physical(self.scale).to_i32_round();
+            let not_visible = self.config.hidden_while_empty()
+                || self.config.autohide().is_some()
+                    && matches!(
+                        self.visibility,
+                        crate::xdg_shell_wrapper::space::Visibility::Hidden
+                    );
+            let dim = self.dimensions.to_f64().to_physical(render_scale).to_i32_round();
             // TODO check to make sure this is not going to cause damage issues
             if not_visible {
+                let my_renderer = self.damage_tracked_renderer.as_mut().unwrap();
                 if let Ok(mut frame) = renderer.render(dim, smithay::utils::Transform::Normal) {
                     _ = frame.clear(
                         Color32F::new(0.0, 0.0, 0.0, 0.0),
@@ -157,7 +263,7 @@ impl PanelSpace {
                     // reset the damage tracker
                     *my_renderer = OutputDamageTracker::new(
                         dim,
-                        self.scale,
+                        render_scale,
                         smithay::utils::Transform::Flipped180,
                     );
                 }
@@ -168,124 +274,27 @@ impl PanelSpace {
                 return Ok(());
             }
 
-            let anim_gap_physical = (self.anchor_gap as f64) * self.scale;
-            let anim_gap_translation = Point::from(match self.config.anchor {
-                PanelAnchor::Left => (anim_gap_physical, 0.),
-                PanelAnchor::Right => (-anim_gap_physical, 0.),
-                PanelAnchor::Top => (0., anim_gap_physical),
-                PanelAnchor::Bottom => (0., -anim_gap_physical),
-            })
-            .to_i32_round();
-            if let Some((o, _info)) = &self.output.as_ref().map(|(_, o, info)| (o, info)) {
-                let mut elements: Vec<PanelRenderElement> = (self.config.anchor_gap
-                    || self.anchor_gap != 0
-                    || self.config.border_radius > 0)
-                    .then(|| {
-                        PanelRenderElement::RoundedRectangle(RoundedRectangleShader::element(
-                            renderer,
-                            Rectangle::from_loc_and_size((0, 0), dim.to_logical(1)),
-                            self.panel_rect_settings,
-                        ))
-                    })
-                    .into_iter()
-                    .chain(
-                        self.space
-                            .elements()
-                            .filter_map(|w| {
-                                let loc = self
-                                    .space
-                                    .element_location(w)
-                                    .unwrap_or_default()
-                                    .to_f64()
-                                    .to_physical(self.scale)
-                                    .to_i32_round()
-                                    + anim_gap_translation;
-
-                                if let CosmicMappedInternal::OverflowButton(b) = w {
-                                    return Some(
-                                        b.render_elements(
-                                            renderer,
-                                            loc,
-                                            smithay::utils::Scale::from(self.scale),
-                                            1.0,
-                                        )
-                                        .into_iter()
-                                        .map(PanelRenderElement::Iced)
-                                        .collect::<Vec<_>>(),
-                                    );
-                                }
-                                w.toplevel().map(|t| {
-                                    let configured_size = t.current_state().size.map(|s| {
-                                        let mut r = Rectangle::from_loc_and_size(
-                                            self.space
-                                                .element_location(w)
-                                                .unwrap_or_default()
-                                                .to_f64()
-                                                .to_physical_precise_round(self.scale),
-                                            s.to_f64().to_physical_precise_round(self.scale),
-                                        );
-                                        if r.size.w == 0 {
-                                            r.size.w = i32::MAX;
-                                        }
-                                        if r.size.h == 0 {
-                                            r.size.h = i32::MAX;
-                                        }
-                                        r
-                                    });
-
-                                    render_elements_from_surface_tree(
-                                        renderer,
-                                        t.wl_surface(),
-                                        loc,
-                                        self.scale,
-                                        1.0,
-                                        smithay::backend::renderer::element::Kind::Unspecified,
-                                    )
-                                    .into_iter()
-                                    .filter_map(|r: WaylandSurfaceRenderElement<GlesRenderer>| {
-                                        if let Some(configured_size) = configured_size {
-                                            return CropRenderElement::from_element(
-                                                r,
-                                                self.scale,
-                                                configured_size,
-                                            )
-                                            .map(PanelRenderElement::Crop);
-                                        }
-
-                                        Some(PanelRenderElement::Wayland(r))
-                                    })
-                                    .collect::<Vec<_>>()
-                                })
-                            })
-                            .flatten(),
-                    )
-                    .collect_vec();
-
-                if let Some(bg) = self.background_element.as_ref().map(|e| {
-                    let pos = e.with_program(|p| p.logical_pos);
-                    e.render_elements(
+            if let Some(o) = self.output.as_ref().map(|(_, o, _)| o.clone()) {
+                let started = Instant::now();
+                let elements = self.render_elements(renderer, render_scale);
+                let my_renderer = self.damage_tracked_renderer.as_mut().unwrap();
+                let damage_area = my_renderer
+                    .render_output(
                         renderer,
-                        Point::from((
-                            (pos.0 as f64 * self.scale) as i32,
-                            (pos.1 as f64 * self.scale) as i32,
-                        )) + anim_gap_translation,
-                        self.scale.into(),
-                        1.0,
+                        self.egl_surface.as_ref().unwrap().buffer_age().unwrap_or_default()
+                            as usize,
+                        &elements,
+                        clear_color,
                     )
-                    .into_iter()
-                    .map(PanelRenderElement::Iced)
-                }) {
-                    elements.extend(bg);
-                };
-
-                _ = my_renderer.render_output(
-                    renderer,
-                    self.egl_surface.as_ref().unwrap().buffer_age().unwrap_or_default() as usize,
-                    &elements,
-                    clear_color,
-                );
+                    .ok()
+                    .and_then(|res| {
+                        res.damage
+                            .map(|d| d.iter().map(|r| r.size.w as i64 * r.size.h as i64).sum())
+                    })
+                    .unwrap_or_default();
 
                 self.egl_surface.as_ref().unwrap().swap_buffers(None)?;
+                self.record_render_stats(started.elapsed(), elements.len(), damage_area);
 
                 for window in self.space.elements().filter_map(|w| {
                     if let CosmicMappedInternal::Window(w) = w {
@@ -294,7 +303,7 @@ impl PanelSpace {
                         None
                     }
                 }) {
-                    let output = *o;
+                    let output = o.clone();
                     let throttle =
                         if window.wl_surface().is_some_and(|s| hovered_clients.contains(&s.id())) {
                             throttle
@@ -306,7 +315,7 @@ impl PanelSpace {
                             )
                         };
                     window.send_frame(
-                        o,
+                        &o,
                         Duration::from_millis(time as u64),
                         throttle,
                         move |_, _| Some(output.clone()),
@@ -315,6 +324,7 @@ impl PanelSpace {
                 let wl_surface = self.layer.as_ref().unwrap().wl_surface().clone();
                 wl_surface.frame(qh, wl_surface.clone());
                 wl_surface.commit();
+                self.frame_requested.get_or_insert_with(Instant::now);
 
                 self.is_dirty = false;
                 self.has_frame = false;
@@ -461,4 +471,578 @@ impl PanelSpace {
 
         Ok(())
     }
+
+    /// the render elements of the panel, from top to bottom
+    pub(crate) fn render_elements(
+        &mut self,
+        renderer: &mut GlesRenderer,
+        render_scale: f64,
+    ) -> Vec<PanelRenderElement> {
+        let dim = self.dimensions.to_f64().to_physical(render_scale).to_i32_round();
+        let saturation = self.saturation();
+        let mirror = self.content_mirror();
+        let clipped: HashSet<_> = self
+            .space
+            .elements()
+            .filter_map(|w| w.toplevel())
+            .filter(|t| self.rounded_clip(t))
+            .map(|t| t.wl_surface().id())
+            .collect();
+        let clip_program = (!clipped.is_empty()).then(|| RoundedClipShader::get(renderer));
+        let shade_program =
+            (saturation < 1. || mirror != [0., 0.]).then(|| DesaturateShader::get(renderer));
+        let clip_radius = self.config.applet_corner_radius as f32 * render_scale as f32;
+        let anim_gap_physical = (self.anchor_gap as f64) * render_scale;
+        let anim_gap_translation = Point::from(match self.config.anchor {
+            PanelAnchor::Left => (anim_gap_physical, 0.),
+            PanelAnchor::Right => (-anim_gap_physical, 0.),
+            PanelAnchor::Top => (0., anim_gap_physical),
+            PanelAnchor::Bottom => (0., -anim_gap_physical),
+        })
+        .to_i32_round();
+        let rounded = self.config.border_radius != BorderRadius::Px(0);
+        let mut elements: Vec<PanelRenderElement> =
+            (self.config.anchor_gap || self.anchor_gap != 0 || rounded)
+                .then(|| {
+                    PanelRenderElement::RoundedRectangle(RoundedRectangleShader::element(
+                        renderer,
+                        Rectangle::from_loc_and_size((0, 0), dim.to_logical(1)),
+                        self.panel_rect_settings.scaled((render_scale / self.scale) as f32),
+                    ))
+                })
+                .into_iter()
+                .chain(
+                    self.space
+                        .elements()
+                        .filter_map(|w| {
+                            // reordered applets glide to their new location
+                            let location = self.space.element_location(w).unwrap_or_default();
+                            let location = location.to_f64() + self.move_offset(w, location);
+                            let loc = location.to_physical(render_scale).to_i32_round()
+                                + anim_gap_translation;
+
+                            let scale = smithay::utils::Scale::from(render_scale);
+                            match w {
+                                CosmicMappedInternal::OverflowButton(b) => {
+                                    return Some(
+                                        b.render_elements(renderer, loc, scale, 1.0)
+                                            .into_iter()
+                                            .map(PanelRenderElement::Iced)
+                                            .collect::<Vec<_>>(),
+                                    );
+                                },
+                                CosmicMappedInternal::StartButton(b) => {
+                                    return Some(
+                                        b.render_elements(renderer, loc, scale, 1.0)
+                                            .into_iter()
+                                            .map(PanelRenderElement::Iced)
+                                            .collect::<Vec<_>>(),
+                                    );
+                                },
+                                _ => {},
+                            }
+                            w.toplevel().map(|t| {
+                                let clip = clip_program
+                                    .clone()
+                                    .filter(|_| clipped.contains(&t.wl_surface().id()));
+                                let (crop_size, applet_scale) = self.applet_crop(w, t);
+                                let mut applet_size =
+                                    self.space.element_bbox(w).unwrap_or_default().size.to_f64();
+                                if let Some(crop_size) = crop_size {
+                                    if crop_size.w > 0. {
+                                        applet_size.w = applet_size.w.min(crop_size.w);
+                                    }
+                                    if crop_size.h > 0. {
+                                        applet_size.h = applet_size.h.min(crop_size.h);
+                                    }
+                                }
+                                let bbox = Rectangle::from_loc_and_size(
+                                    loc,
+                                    applet_size.to_physical_precise_round(render_scale),
+                                );
+                                let configured_size = crop_size.map(|s| {
+                                    let mut r = Rectangle::from_loc_and_size(
+                                        location.to_physical_precise_round(render_scale),
+                                        s.to_physical_precise_round(render_scale),
+                                    );
+                                    if r.size.w == 0 {
+                                        r.size.w = i32::MAX;
+                                    }
+                                    if r.size.h == 0 {
+                                        r.size.h = i32::MAX;
+                                    }
+                                    r
+                                });
+
+                                render_elements_from_surface_tree(
+                                    renderer,
+                                    t.wl_surface(),
+                                    loc,
+                                    render_scale * applet_scale,
+                                    self.applet_opacity(&t.wl_surface().id()),
+                                    smithay::backend::renderer::element::Kind::Unspecified,
+                                )
+                                .into_iter()
+                                .filter_map(|r: WaylandSurfaceRenderElement<GlesRenderer>| {
+                                    if let Some(configured_size) = configured_size {
+                                        return CropRenderElement::from_element(
+                                            r,
+                                            render_scale,
+                                            configured_size,
+                                        )
+                                        .map(PanelRenderElement::Crop);
+                                    }
+
+                                    Some(PanelRenderElement::Wayland(r))
+                                })
+                                .map(|e| {
+                                    let shading = AppletShading {
+                                        saturation,
+                                        mirror,
+                                        bbox,
+                                        buffer_size: buffer_size(&e, renderer),
+                                    };
+                                    match (clip.as_ref(), shade_program.as_ref()) {
+                                        (Some(program), _) => PanelRenderElement::RoundedClip(
+                                            Box::new(e),
+                                            program.clone(),
+                                            clip_radius,
+                                            shading,
+                                        ),
+                                        (None, Some(program)) => PanelRenderElement::Desaturated(
+                                            Box::new(e),
+                                            program.clone(),
+                                            shading,
+                                        ),
+                                        (None, None) => e,
+                                    }
+                                })
+                                .collect::<Vec<_>>()
+                            })
+                        })
+                        .flatten(),
+                )
+                .collect_vec();
+
+        elements.extend(self.section_divider_elements(render_scale, anim_gap_translation));
+
+        if self.drag_target {
+            let targets = self.drop_target_clients();
+            let highlights = self
+                .space
+                .elements()
+                .filter(|w| {
+                    w.toplevel()
+                        .and_then(|t| t.wl_surface().client())
+                        .is_some_and(|c| targets.contains(&c.id()))
+                })
+                .filter_map(|w| self.space.element_bbox(w))
+                .collect_vec();
+            self.drag_highlight_ids
+                .resize_with(highlights.len(), smithay::backend::renderer::element::Id::new);
+            let accent = self.colors.theme.cosmic().accent_color();
+            let alpha = 0.3;
+            let color =
+                Color32F::new(accent.red * alpha, accent.green * alpha, accent.blue * alpha, alpha);
+            // drawn above the applets
+            let highlights =
+                highlights.into_iter().zip(self.drag_highlight_ids.iter()).map(|(bbox, id)| {
+                    let mut geo = bbox.to_f64().to_physical(render_scale).to_i32_round();
+                    geo.loc += anim_gap_translation;
+                    PanelRenderElement::Highlight(SolidColorRenderElement::new(
+                        id.clone(),
+                        geo,
+                        CommitCounter::default(),
+                        color,
+                        smithay::backend::renderer::element::Kind::Unspecified,
+                    ))
+                });
+            elements = highlights.chain(elements).collect();
+        }
+
+        let crashed = self.crashed_applet_elements(renderer, render_scale, anim_gap_translation);
+        elements = crashed.into_iter().chain(elements).collect();
+
+        // drawn above the applets and their highlights
+        let badges = self.badge_elements(renderer, render_scale, anim_gap_translation);
+        elements = badges.into_iter().chain(elements).collect();
+
+        // below the rounded mask of the panel, so it has the same shape
+        if let Some(backdrop) = self.backdrop_element(render_scale, anim_gap_translation) {
+            let below_mask = elements
+                .iter()
+                .position(|e| matches!(e, PanelRenderElement::RoundedRectangle(_)))
+                .map_or(0, |i| i + 1);
+            elements.insert(below_mask, backdrop);
+        }
+
+        if let Some(focus) = self.keyboard_nav_element(render_scale, anim_gap_translation) {
+            elements.insert(0, focus);
+        }
+
+        if let Some(progress) = self.progress_element(render_scale, anim_gap_translation) {
+            elements.insert(0, progress);
+        }
+
+        if let Some((bg_image, bg)) =
+            self.background_image.as_mut().zip(self.background_element.as_ref())
+        {
+            let (pos, size) =
+                bg.with_program(|p| (p.logical_pos, (p.logical_width, p.logical_height)));
+            let loc = Point::from((
+                (pos.0 as f64 * render_scale) as i32,
+                (pos.1 as f64 * render_scale) as i32,
+            )) + anim_gap_translation;
+            let size =
+                Size::<i32, Logical>::from(size).to_f64().to_physical(render_scale).to_i32_round();
+            elements.extend(bg_image.element(renderer, loc, size).map(PanelRenderElement::Iced));
+        } else if let Some(bg) = self.background_element.as_ref().map(|e| {
+            let pos = e.with_program(|p| p.logical_pos);
+            e.render_elements(
+                renderer,
+                Point::from((
+                    (pos.0 as f64 * render_scale) as i32,
+                    (pos.1 as f64 * render_scale) as i32,
+                )) + anim_gap_translation,
+                render_scale.into(),
+                1.0,
+            )
+            .into_iter()
+            .map(PanelRenderElement::Iced)
+        }) {
+            elements.extend(bg);
+        };
+        elements
+    }
+
+    /// render elements of the badges of the mapped applets, at the configured
+    /// corner of each applet
+    fn badge_elements(
+        &self,
+        renderer: &mut GlesRenderer,
+        render_scale: f64,
+        translation: Point<i32, Physical>,
+    ) -> Vec<PanelRenderElement> {
+        if self.applet_badges.is_empty() {
+            return Vec::new();
+        }
+        let badges = [&self.clients_left, &self.clients_center, &self.clients_right]
+            .into_iter()
+            .flat_map(|clients| {
+                clients
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter_map(|c| {
+                        Some((c.client.id(), self.applet_badges.get(&c.instance_id())?.clone()))
+                    })
+                    .collect_vec()
+            })
+            .collect_vec();
+        let corner = self.config.badge_corner;
+        self.space
+            .elements()
+            .filter_map(|w| {
+                let client = w.toplevel()?.wl_surface().client()?.id();
+                let (_, badge) = badges.iter().find(|(c, _)| *c == client)?;
+                let bbox = self.space.element_bbox(w)?;
+                let size = badge.with_program(|p| p.size);
+                let x = match corner {
+                    BadgeCorner::TopLeft | BadgeCorner::BottomLeft => bbox.loc.x,
+                    BadgeCorner::TopRight | BadgeCorner::BottomRight => {
+                        bbox.loc.x + bbox.size.w - size.w
+                    },
+                };
+                let y = match corner {
+                    BadgeCorner::TopLeft | BadgeCorner::TopRight => bbox.loc.y,
+                    BadgeCorner::BottomLeft | BadgeCorner::BottomRight => {
+                        bbox.loc.y + bbox.size.h - size.h
+                    },
+                };
+                let loc = Point::<i32, Logical>::from((x, y))
+                    .to_f64()
+                    .to_physical(render_scale)
+                    .to_i32_round()
+                    + translation;
+                Some(
+                    badge
+                        .render_elements(renderer, loc, render_scale.into(), 1.0)
+                        .into_iter()
+                        .map(PanelRenderElement::Iced)
+                        .collect_vec(),
+                )
+            })
+            .flatten()
+            .collect()
+    }
+
+    /// render elements of the placeholders in the slots of crashed applets
+    fn crashed_applet_elements(
+        &self,
+        renderer: &mut GlesRenderer,
+        render_scale: f64,
+        translation: Point<i32, Physical>,
+    ) -> Vec<PanelRenderElement> {
+        self.crashed_applets
+            .values()
+            .flat_map(|(placeholder, loc)| {
+                let loc = loc.to_f64().to_physical(render_scale).to_i32_round() + translation;
+                placeholder
+                    .render_elements(renderer, loc, render_scale.into(), 1.0)
+                    .into_iter()
+                    .map(PanelRenderElement::Iced)
+            })
+            .collect()
+    }
+
+    /// render element of the system-wide progress, filling along the anchored
+    /// edge of the panel
+    fn progress_element(
+        &self,
+        render_scale: f64,
+        translation: Point<i32, Physical>,
+    ) -> Option<PanelRenderElement> {
+        let fill = self.progress?.fill();
+        let ((x, y), (w, h)) = self
+            .background_element
+            .as_ref()?
+            .with_program(|p| (p.logical_pos, (p.logical_width, p.logical_height)));
+        let thickness = PROGRESS_THICKNESS.min(w).min(h);
+        let rect = match self.config.anchor {
+            PanelAnchor::Top => {
+                Rectangle::from_loc_and_size((x, y), ((w as f32 * fill).round() as i32, thickness))
+            },
+            PanelAnchor::Bottom => Rectangle::from_loc_and_size(
+                (x, y + h - thickness),
+                ((w as f32 * fill).round() as i32, thickness),
+            ),
+            PanelAnchor::Left => {
+                Rectangle::from_loc_and_size((x, y), (thickness, (h as f32 * fill).round() as i32))
+            },
+            PanelAnchor::Right => Rectangle::from_loc_and_size(
+                (x + w - thickness, y),
+                (thickness, (h as f32 * fill).round() as i32),
+            ),
+        };
+        if rect.size.w <= 0 || rect.size.h <= 0 {
+            return None;
+        }
+        let mut geo = rect.to_f64().to_physical(render_scale).to_i32_round();
+        geo.loc += translation;
+        let accent = self.colors.theme.cosmic().accent_color();
+        Some(PanelRenderElement::Highlight(SolidColorRenderElement::new(
+            self.progress_id.clone(),
+            geo,
+            CommitCounter::default(),
+            Color32F::new(accent.red, accent.green, accent.blue, 1.),
+            smithay::backend::renderer::element::Kind::Unspecified,
+        )))
+    }
+
+    /// render element of the highlight of the applet focused by keyboard
+    /// navigation
+    fn keyboard_nav_element(
+        &self,
+        render_scale: f64,
+        translation: Point<i32, Physical>,
+    ) -> Option<PanelRenderElement> {
+        let focused = self.keyboard_nav?;
+        let targets = self.keyboard_nav_targets();
+        let rect = targets.get(focused).or(targets.last())?;
+        let mut geo = rect.to_f64().to_physical(render_scale).to_i32_round();
+        geo.loc += translation;
+        let accent = self.colors.theme.cosmic().accent_color();
+        let alpha = 0.3;
+        Some(PanelRenderElement::Highlight(SolidColorRenderElement::new(
+            self.keyboard_nav_id.clone(),
+            geo,
+            self.keyboard_nav_commit,
+            Color32F::new(accent.red * alpha, accent.green * alpha, accent.blue * alpha, alpha),
+            smithay::backend::renderer::element::Kind::Unspecified,
+        )))
+    }
+
+    /// render element of the backdrop dimming the panel while a popup is open
+    fn backdrop_element(
+        &self,
+        render_scale: f64,
+        translation: Point<i32, Physical>,
+    ) -> Option<PanelRenderElement> {
+        let alpha = self.config.popup_backdrop?.clamp(0., 1.) * self.backdrop?.level();
+        if alpha <= 0. {
+            return None;
+        }
+        let (loc, size) = self
+            .background_element
+            .as_ref()?
+            .with_program(|p| (p.logical_pos, (p.logical_width, p.logical_height)));
+        let mut geo = Rectangle::<i32, Logical>::from_loc_and_size(loc, size)
+            .to_f64()
+            .to_physical(render_scale)
+            .to_i32_round();
+        geo.loc += translation;
+        Some(PanelRenderElement::Highlight(SolidColorRenderElement::new(
+            self.backdrop_id.clone(),
+            geo,
+            self.backdrop_commit,
+            Color32F::new(0., 0., 0., alpha),
+            smithay::backend::renderer::element::Kind::Unspecified,
+        )))
+    }
+
+    /// render elements of the dividers between the wings and the center
+    fn section_divider_elements(
+        &mut self,
+        render_scale: f64,
+        translation: Point<i32, Physical>,
+    ) -> Vec<PanelRenderElement> {
+        let Some(divider) = self.config.section_divider else {
+            return Vec::new();
+        };
+        let color =
+            divider.color.map(|[r, g, b, a]| [r * a, g * a, b * a, a]).unwrap_or_else(|| {
+                let c = self.colors.theme.cosmic().background.divider;
+                [c.red * c.alpha, c.green * c.alpha, c.blue * c.alpha, c.alpha]
+            });
+        self.section_divider_ids
+            .resize_with(self.section_dividers.len(), smithay::backend::renderer::element::Id::new);
+        self.section_dividers
+            .iter()
+            .zip(self.section_divider_ids.iter())
+            .map(|(rect, id)| {
+                let mut geo = rect.to_f64().to_physical(render_scale).to_i32_round();
+                geo.loc += translation;
+                PanelRenderElement::Highlight(SolidColorRenderElement::new(
+                    id.clone(),
+                    geo,
+                    CommitCounter::default(),
+                    Color32F::from(color),
+                    smithay::backend::renderer::element::Kind::Unspecified,
+                ))
+            })
+            .collect()
+    }
+
+    /// the logical size an applet window is cropped to, if any, and the factor
+    /// its surface is scaled by
+    ///
+    /// Windows are cropped to their configured size, and applets which are
+    /// thicker than the panel may be cropped or scaled down to its thickness.
+    fn applet_crop(
+        &self,
+        w: &CosmicMappedInternal,
+        t: &ToplevelSurface,
+    ) -> (Option<Size<f64, Logical>>, f64) {
+        let bbox = self.space.element_bbox(w).unwrap_or_default().size.to_f64();
+        let thickness = if self.config.is_horizontal() { bbox.h } else { bbox.w };
+        let (fit_thickness, scale) = self.config.fit_applet_thickness(thickness);
+        let mut crop_size = t.current_state().size.map(|s| s.to_f64().upscale(scale));
+        if fit_thickness < thickness && scale == 1. {
+            let size = crop_size.get_or_insert(bbox);
+            if self.config.is_horizontal() {
+                size.h = fit_thickness;
+            } else {
+                size.w = fit_thickness;
+            }
+        }
+        (crop_size, scale)
+    }
+
+    /// render the panel to an offscreen texture instead of its layer surface
+    ///
+    /// The render elements are the same as those drawn by `render`, so this
+    /// may be used to inspect the panel without a compositor. The panel is
+    /// rendered at the scale of its output, even while it is transitioning.
+    #[cfg(feature = "headless")]
+    pub fn render_offscreen(
+        &mut self,
+        renderer: &mut GlesRenderer,
+    ) -> anyhow::Result<image::RgbaImage> {
+        use smithay::backend::{
+            allocator::Fourcc,
+            renderer::{gles::GlesTexture, ExportMem, Offscreen},
+        };
+
+        let scale = self.scale;
+        let buffer_size = offscreen_size(self.dimensions, scale);
+        let texture: GlesTexture = renderer.create_buffer(Fourcc::Abgr8888, buffer_size)?;
+        renderer.bind(texture)?;
+        let elements = self.render_elements(renderer, scale);
+        let mut damage_tracker = OutputDamageTracker::new(
+            (buffer_size.w, buffer_size.h),
+            scale,
+            smithay::utils::Transform::Normal,
+        );
+        damage_tracker
+            .render_output(renderer, 0, &elements, [0., 0., 0., 0.])
+            .map_err(|err| anyhow::anyhow!("Failed to render offscreen: {:?}", err))?;
+        let mapping = renderer.copy_framebuffer(
+            Rectangle::from_loc_and_size((0, 0), buffer_size),
+            Fourcc::Abgr8888,
+        )?;
+        let data = renderer.map_texture(&mapping)?.to_vec();
+        renderer.unbind()?;
+        image::RgbaImage::from_raw(buffer_size.w as u32, buffer_size.h as u32, data)
+            .ok_or_else(|| anyhow::anyhow!("Offscreen buffer has an unexpected size"))
+    }
+
+    /// statistics of the last frame rendered for the panel
+    pub fn render_stats(&self) -> RenderStats {
+        self.render_stats
+    }
+
+    fn record_render_stats(&mut self, duration: Duration, elements: usize, damage_area: i64) {
+        self.render_stats =
+            RenderStats { duration, elements, damage_area, frames: self.render_stats.frames + 1 };
+
+        let (logged, logged_frames) = self.render_stats_logged;
+        if logged.elapsed() < RENDER_STATS_INTERVAL {
+            return;
+        }
+        let stats = self.render_stats();
+        tracing::debug!(
+            panel = %self.config.name,
+            fps = (stats.frames - logged_frames) as f64 / logged.elapsed().as_secs_f64(),
+            duration = ?stats.duration,
+            elements = stats.elements,
+            damage_area = stats.damage_area,
+            "Render stats"
+        );
+        self.render_stats_logged = (Instant::now(), stats.frames);
+    }
+}
+
+/// size of the buffer sampled by an applet element, which is assumed to cover
+/// the whole buffer if it has none
+fn buffer_size(e: &PanelRenderElement, renderer: &mut GlesRenderer) -> Size<i32, Buffer> {
+    match e.underlying_storage(renderer) {
+        Some(UnderlyingStorage::Wayland(buffer)) => buffer_dimensions(&buffer),
+        _ => None,
+    }
+    .unwrap_or_else(|| e.src().size.to_i32_round())
+}
+
+/// size of the buffer a panel of the given logical size is rendered offscreen
+/// to
+#[cfg(feature = "headless")]
+fn offscreen_size(dimensions: Size<i32, Logical>, scale: f64) -> Size<i32, Buffer> {
+    let dim = dimensions.to_f64().to_physical(scale).to_i32_round::<i32>();
+    Size::from((dim.w.max(1), dim.h.max(1)))
+}
+
+#[cfg(all(test, feature = "headless"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offscreen_size_follows_the_output_scale() {
+        assert_eq!(offscreen_size((1920, 32).into(), 1.), (1920, 32).into());
+        assert_eq!(offscreen_size((1920, 32).into(), 1.5), (2880, 48).into());
+        assert_eq!(offscreen_size((1280, 41).into(), 1.25), (1600, 51).into());
+    }
+
+    #[test]
+    fn empty_panel_has_a_buffer() {
+        assert_eq!(offscreen_size((0, 0).into(), 2.), (1, 1).into());
+    }
 }
@@ -1,16 +1,17 @@
 use std::{
     cell::{Cell, RefCell},
+    collections::HashMap,
     ffi::OsString,
     fs, mem,
     os::{fd::OwnedFd, unix::prelude::AsRawFd},
     rc::Rc,
-    sync::{Arc, Mutex},
+    sync::{atomic::Ordering, Arc, Mutex},
     time::{Duration, Instant},
 };
 
 use crate::{
     iced::elements::{target::SpaceTarget, PopupMappedInternal},
-    space::panel_space::ClientShrinkSize,
+    space::panel_space::{ClientShrinkSize, Clients},
     space_container::SpaceContainer,
     xdg_shell_wrapper::{
         client::handlers::overlap::{OverlapNotificationV1, OverlapNotifyV1},
@@ -25,10 +26,13 @@ use crate::{
         wp_security_context::{SecurityContext, SecurityContextManager},
         wp_viewporter::ViewporterState,
     },
+    PanelCalloopMsg,
 };
 use anyhow::bail;
 use cosmic::iced::id;
-use cosmic_panel_config::{CosmicPanelConfig, CosmicPanelOuput, Side, NAME};
+use cosmic_panel_config::{
+    CosmicPanelConfig, CosmicPanelOuput, CrashRestart, DuplicateApplets, Side, NAME,
+};
 use freedesktop_desktop_entry::{self, DesktopEntry, Iter};
 use itertools::izip;
 use launch_pad::process::Process;
@@ -36,9 +40,12 @@ use sctk::{
     compositor::{CompositorState, Region},
     globals::GlobalData,
     output::OutputInfo,
-    reexports::client::{
-        protocol::{wl_output as c_wl_output, wl_surface as c_wl_surface},
-        Connection, Proxy, QueueHandle,
+    reexports::{
+        calloop,
+        client::{
+            protocol::{wl_output as c_wl_output, wl_surface as c_wl_surface},
+            Connection, Proxy, QueueHandle,
+        },
     },
     seat::pointer::{PointerEvent, BTN_LEFT},
     shell::{
@@ -59,7 +66,7 @@ use smithay::{
     reexports::wayland_server::{
         self, protocol::wl_surface::WlSurface as s_WlSurface, DisplayHandle, Resource,
     },
-    utils::{Logical, Rectangle, Size},
+    utils::{Logical, Point, Rectangle, Size},
     wayland::{
         compositor::{with_states, SurfaceAttributes},
         fractional_scale::with_fractional_scale,
@@ -68,14 +75,18 @@ use smithay::{
     },
 };
 use tokio::sync::oneshot;
-use tracing::{error, error_span, info, info_span, trace};
+use tracing::{error, error_span, info, info_span, trace, warn};
 use wayland_backend::server::ClientId;
+use wayland_protocols::wp::security_context::v1::client::wp_security_context_v1::WpSecurityContextV1;
 use wayland_protocols_wlr::layer_shell::v1::client::zwlr_layer_shell_v1;
 
 use crate::{
+    applet_discovery::discover_applets,
     iced::elements::CosmicMappedInternal,
     space::{
-        panel_space::{AppletAutoClickAnchor, PanelClient},
+        panel_space::{
+            instance_id, AppletAutoClickAnchor, AppletClickPolicy, PanelClient, SOFTWARE_RENDERING,
+        },
         AppletMsg,
     },
 };
@@ -147,7 +158,7 @@ impl WrapperSpace for PanelSpace {
     ) -> anyhow::Result<()> {
         tracing::info!("adding popup");
         self.apply_positioner_state(&positioner, positioner_state, &s_surface);
-        let c_wl_surface = compositor_state.create_surface(qh);
+        let rect_size = self.clamp_popup_size(positioner_state.rect_size);
         let mut clear_exclude = Vec::new();
         let mut parent_parents = Vec::new();
         let parent = self
@@ -200,7 +211,30 @@ impl WrapperSpace for PanelSpace {
             }
         }
 
+        // guard against applets which leak popups, each with its own surface,
+        // by nesting them, as the popups outside of the chain of parents of
+        // the new popup are closed
+        if let Some(max) = self.config.max_applet_popups {
+            let open = popup_depth(s_surface.get_parent_surface(), |s| {
+                self.popups
+                    .iter()
+                    .find(|p| p.s_surface.wl_surface() == s)
+                    .map(|p| p.s_surface.get_parent_surface())
+            });
+            if open >= max as usize {
+                warn!(
+                    panel = %self.config.name,
+                    "Dismissing popup of an applet which already has {} open popups",
+                    open
+                );
+                s_surface.send_popup_done();
+                anyhow::bail!("too many popups for the applet");
+            }
+        }
+
         self.close_popups(|p| clear_exclude.contains(&p.c_popup));
+
+        let c_wl_surface = compositor_state.create_surface(qh);
         let c_popup = popup::Popup::from_surface(
             parent.as_ref().map(|p| p.xdg_surface()),
             &positioner,
@@ -220,11 +254,12 @@ impl WrapperSpace for PanelSpace {
                 (cached.geometry, attr.input_region.clone())
             })
         {
+            let geometry_size = self.clamp_popup_size(s_window_geometry.size);
             c_popup.xdg_surface().set_window_geometry(
                 s_window_geometry.loc.x,
                 s_window_geometry.loc.y,
-                s_window_geometry.size.w.max(1),
-                s_window_geometry.size.h.max(1),
+                geometry_size.w.max(1),
+                geometry_size.h.max(1),
             );
             for r in input_regions.rects {
                 input_region.add(0, 0, r.1.size.w, r.1.size.h);
@@ -245,10 +280,7 @@ impl WrapperSpace for PanelSpace {
                 });
             });
             let viewport = v.get_viewport(&c_wl_surface, qh);
-            viewport.set_destination(
-                positioner_state.rect_size.w.max(1),
-                positioner_state.rect_size.h.max(1),
-            );
+            viewport.set_destination(rect_size.w.max(1), rect_size.h.max(1));
             viewport
         });
         if fractional_scale.is_none() {
@@ -263,17 +295,18 @@ impl WrapperSpace for PanelSpace {
         self.popups.push(WrapperPopup {
             popup: PanelPopup {
                 damage_tracked_renderer: OutputDamageTracker::new(
-                    positioner_state.rect_size.to_f64().to_physical(self.scale).to_i32_round(),
+                    rect_size.to_f64().to_physical(self.scale).to_i32_round(),
                     self.scale,
                     smithay::utils::Transform::Flipped180,
                 ),
                 c_popup,
                 egl_surface: None,
+                egl_attempts: 0,
                 dirty: false,
-                rectangle: Rectangle::from_loc_and_size((0, 0), positioner_state.rect_size),
+                rectangle: Rectangle::from_loc_and_size((0, 0), rect_size),
                 state: cur_popup_state,
                 input_region: Some(input_region),
-                wrapper_rectangle: Rectangle::from_loc_and_size((0, 0), positioner_state.rect_size),
+                wrapper_rectangle: Rectangle::from_loc_and_size((0, 0), rect_size),
                 positioner,
                 has_frame: true,
                 fractional_scale,
@@ -284,6 +317,7 @@ impl WrapperSpace for PanelSpace {
                     .unwrap_or(self.layer.as_ref().unwrap().wl_surface().clone()),
             },
             s_surface,
+            opened: Instant::now(),
         });
 
         Ok(())
@@ -297,6 +331,7 @@ impl WrapperSpace for PanelSpace {
     ) -> anyhow::Result<()> {
         popup.with_pending_state(|pending| {
             pending.geometry = Rectangle::from_loc_and_size((0, 0), pos_state.rect_size);
+            pending.positioner = pos_state;
         });
         if let Some(p) = self.popups.iter().find(|wp| &wp.s_surface == &popup) {
             let positioner = &p.popup.positioner;
@@ -337,50 +372,34 @@ impl WrapperSpace for PanelSpace {
         qh: &QueueHandle<GlobalState>,
         security_context_manager: Option<SecurityContextManager>,
     ) -> anyhow::Result<()> {
+        // mirrors show the applets of the mirrored panel
+        if self.mirror_of.is_some() {
+            return Ok(());
+        }
         info!("Spawning applets");
         let mut left_guard = self.clients_left.lock().unwrap();
         let mut center_guard = self.clients_center.lock().unwrap();
         let mut right_guard = self.clients_right.lock().unwrap();
 
         if left_guard.is_empty() && center_guard.is_empty() && right_guard.is_empty() {
-            *left_guard = self
-                .config
-                .plugins_left()
-                .as_ref()
-                .cloned()
-                .unwrap_or_default()
-                .into_iter()
-                .map(|name| {
-                    let (c, s) = get_client_sock(&mut display);
-                    PanelClient::new(name, c, Some(s))
-                })
-                .collect();
-
-            *center_guard = self
-                .config
-                .plugins_center()
-                .as_ref()
-                .cloned()
-                .unwrap_or_default()
-                .into_iter()
-                .map(|name| {
-                    let (c, s) = get_client_sock(&mut display);
-                    PanelClient::new(name, c, Some(s))
-                })
-                .collect();
-
-            *right_guard = self
-                .config
-                .plugins_right()
-                .as_ref()
-                .cloned()
-                .unwrap_or_default()
-                .into_iter()
-                .map(|name| {
-                    let (c, s) = get_client_sock(&mut display);
-                    PanelClient::new(name, c, Some(s))
-                })
-                .collect();
+            for (_, token) in self.pending_starts.drain(..) {
+                self.loop_handle.remove(token);
+            }
+            let [discovered_left, discovered_center, discovered_right] =
+                discover_applets(&self.config);
+            let with_discovered = |listed: Option<Vec<String>>, discovered: Vec<String>| {
+                listed.unwrap_or_default().into_iter().chain(discovered).collect()
+            };
+            let [left, center, right] = applet_instances(
+                [
+                    with_discovered(self.config.plugins_left(), discovered_left),
+                    with_discovered(self.config.plugins_center(), discovered_center),
+                    with_discovered(self.config.plugins_right(), discovered_right),
+                ],
+                self.config.duplicate_applets,
+            );
+            [*left_guard, *center_guard, *right_guard] =
+                instance_clients(&mut display, [left, center, right]);
 
             let mut desktop_ids: Vec<_> = left_guard
                 .iter_mut()
@@ -415,10 +434,19 @@ impl WrapperSpace for PanelSpace {
                 Vec::new();
             let locales = freedesktop_desktop_entry::get_languages_from_env();
 
-            for path in Iter::new(freedesktop_desktop_entry::default_paths()) {
+            // entries in the applets directory take precedence
+            let paths = self
+                .config
+                .applets_dir
+                .clone()
+                .into_iter()
+                .chain(freedesktop_desktop_entry::default_paths())
+                .collect::<Vec<_>>();
+            for path in Iter::new(paths) {
                 // This way each applet is at most started once,
-                // even if multiple desktop files in different directories match
-                if let Some(position) =
+                // even if multiple desktop files in different directories match,
+                // while every instance of an applet is matched
+                while let Some(position) =
                     desktop_ids.iter().position(|(PanelClient { ref name, .. }, ..)| {
                         Some(OsString::from(name).as_os_str()) == path.file_stem()
                     })
@@ -462,6 +490,27 @@ impl WrapperSpace for PanelSpace {
                                 panel_client.is_notification_applet =
                                     Some(entry.desktop_entry("X-NotificationsApplet").is_some());
 
+                                panel_client.popup_at_pointer = entry
+                                    .desktop_entry("X-CosmicPopupAtPointer")
+                                    .and_then(|x| x.parse::<bool>().ok());
+
+                                panel_client.click_policy = entry
+                                    .desktop_entry("X-CosmicClickPolicy")
+                                    .and_then(|x| x.parse::<AppletClickPolicy>().ok());
+
+                                panel_client.rounded_clip = entry
+                                    .desktop_entry("X-CosmicRoundedClip")
+                                    .and_then(|x| x.parse::<bool>().ok());
+
+                                panel_client.reserved_slot = entry
+                                    .desktop_entry("X-CosmicReservedSlot")
+                                    .and_then(|x| x.parse::<u32>().ok());
+
+                                panel_client.pin = self
+                                    .config
+                                    .pinned_applets
+                                    .contains(&panel_client.instance_id());
+
                                 panel_clients.push((panel_client, my_list, panel_side));
                             }
                         }
@@ -495,6 +544,8 @@ impl WrapperSpace for PanelSpace {
                 let is_notification_applet = panel_client.is_notification_applet.unwrap_or(false);
                 let requests_wayland_display =
                     panel_client.requests_wayland_display.unwrap_or(false);
+                let sandboxed = self.config.sandboxed_applets.contains(&panel_client.name);
+                panel_client.sandboxed = sandboxed;
 
                 let mut exec_iter = Shlex::new(panel_client.exec.as_deref().unwrap());
                 let exec = exec_iter.next().expect("exec parameter must contain at least on word");
@@ -514,37 +565,42 @@ impl WrapperSpace for PanelSpace {
                     ron::ser::to_string(&self.config.get_effective_applet_size(panel_side))
                         .unwrap_or_default();
                 applet_env.push(("COSMIC_PANEL_SIZE".to_string(), config_size));
-                if requests_wayland_display {
-                    if let Some(security_context_manager) = security_context_manager.as_ref() {
-                        match security_context_manager.create_listener::<SpaceContainer>(qh) {
-                            Ok(security_context) => {
-                                security_context.set_sandbox_engine(NAME.to_string());
-                                security_context.commit();
-
-                                let data = security_context.data::<SecurityContext>().unwrap();
-                                let privileged_socket = data.conn.lock().unwrap().take().unwrap();
-                                applet_env.push((
-                                    "X_PRIVILEGED_WAYLAND_SOCKET".to_string(),
-                                    privileged_socket.as_raw_fd().to_string(),
-                                ));
-
-                                fds.push(privileged_socket.into());
-                                panel_client.security_ctx = Some(security_context);
-                            },
-                            Err(why) => {
-                                error!(?why, "Failed to create a listener");
-                            },
-                        }
-                    }
+                if panel_client.instance > 0 {
+                    applet_env.push((
+                        "COSMIC_PANEL_APPLET_INSTANCE".to_string(),
+                        panel_client.instance.to_string(),
+                    ));
+                }
+                if let Some((security_context, host_socket)) = security_context_manager
+                    .as_ref()
+                    .filter(|_| requests_wayland_display)
+                    .and_then(|m| host_security_context(m, qh, &panel_client.name, sandboxed))
+                {
+                    applet_env.push((
+                        "X_PRIVILEGED_WAYLAND_SOCKET".to_string(),
+                        host_socket.as_raw_fd().to_string(),
+                    ));
+                    fds.push(host_socket);
+                    panel_client.security_ctx = Some(security_context);
                 }
 
                 for (key, val) in &env_vars {
-                    if !requests_wayland_display && *key == "WAYLAND_DISPLAY" {
+                    // sandboxed applets only reach the host through their
+                    // security context
+                    if (!requests_wayland_display || sandboxed) && *key == "WAYLAND_DISPLAY" {
                         continue;
                     }
                     applet_env.push((key.clone(), val.clone()));
                 }
+                // the applets share the GPU which failed the panel
+                if SOFTWARE_RENDERING.load(Ordering::SeqCst) {
+                    applet_env.push(("LIBGL_ALWAYS_SOFTWARE".to_string(), "1".to_string()));
+                }
+                if let Some(extra_env) = self.config.applet_env.get(&panel_client.name) {
+                    applet_env.extend(extra_env.iter().cloned());
+                }
                 applet_env.push(("WAYLAND_SOCKET".to_string(), socket.as_raw_fd().to_string()));
+                panel_client.env = applet_env.clone();
 
                 fds.push(socket.into());
                 trace!("child: {}, {:?} {:?}", &exec, args, applet_env);
@@ -554,6 +610,7 @@ impl WrapperSpace for PanelSpace {
                 let display_handle = display.clone();
                 let applet_tx_clone = self.applet_tx.clone();
                 let id_clone = panel_client.name.clone();
+                let instance = panel_client.instance;
                 let id_clone_info = panel_client.name.clone();
                 let id_clone_err = panel_client.name.clone();
                 let client_id = panel_client.client.id();
@@ -561,6 +618,8 @@ impl WrapperSpace for PanelSpace {
                 let client_id_err = panel_client.client.id();
                 let security_context_manager_clone = security_context_manager.clone();
                 let qh_clone = qh.clone();
+                let crash_restart = self.config.crash_restart;
+                let panel_tx = self.panel_tx.clone();
 
                 let mut process = Process::new()
                     .with_executable(&exec)
@@ -600,45 +659,51 @@ impl WrapperSpace for PanelSpace {
                             });
                         }
                         let my_list = my_list.clone();
+                        // the applet may have been sandboxed since it was spawned
+                        let sandboxed =
+                            my_list.lock().unwrap().iter().any(|c| {
+                                c.name == id_clone && c.instance == instance && c.sandboxed
+                            });
                         let mut display_handle = display_handle.clone();
                         let applet_tx_clone = applet_tx_clone.clone();
                         let (c, client_socket) = get_client_sock(&mut display_handle);
                         let raw_client_socket = client_socket.as_raw_fd();
                         let mut applet_env = Vec::with_capacity(1);
                         let mut fds: Vec<OwnedFd> = Vec::with_capacity(2);
-                        let should_restart = is_restarting && err_code.is_some();
-                        let security_context = if requests_wayland_display && should_restart {
-                            security_context_manager_clone.as_ref().and_then(
-                                |security_context_manager| {
-                                    security_context_manager
-                                        .create_listener::<SpaceContainer>(&qh_clone)
-                                        .ok()
-                                        .map(|security_context| {
-                                            security_context.set_sandbox_engine(NAME.to_string());
-                                            security_context.commit();
-
-                                            let data =
-                                                security_context.data::<SecurityContext>().unwrap();
-                                            let privileged_socket =
-                                                data.conn.lock().unwrap().take().unwrap();
-                                            applet_env.push((
-                                                "X_PRIVILEGED_WAYLAND_SOCKET".to_string(),
-                                                privileged_socket.as_raw_fd().to_string(),
-                                            ));
-                                            fds.push(privileged_socket.into());
-                                            security_context
-                                        })
-                                },
-                            )
+                        let restart_delay = if is_restarting && err_code.is_some() {
+                            let delay = record_crash(&my_list, &id_clone, instance, crash_restart);
+                            if delay.is_none() {
+                                error!("{}: crashed too often, no longer restarting", id_clone);
+                                _ = panel_tx.send(PanelCalloopMsg::AppletCrashed(instance_id(
+                                    &id_clone, instance,
+                                )));
+                            }
+                            delay
                         } else {
                             None
                         };
+                        let should_restart = restart_delay.is_some();
+                        let security_context = security_context_manager_clone
+                            .as_ref()
+                            .filter(|_| requests_wayland_display && should_restart)
+                            .and_then(|m| host_security_context(m, &qh_clone, &id_clone, sandboxed))
+                            .map(|(security_context, host_socket)| {
+                                applet_env.push((
+                                    "X_PRIVILEGED_WAYLAND_SOCKET".to_string(),
+                                    host_socket.as_raw_fd().to_string(),
+                                ));
+                                fds.push(host_socket);
+                                security_context
+                            });
 
                         async move {
                             if !should_restart {
                                 _ = pman.stop_process(key).await;
                                 return;
                             }
+                            if let Some(delay) = restart_delay {
+                                tokio::time::sleep(delay).await;
+                            }
 
                             if is_notification_applet {
                                 let (tx, rx) = oneshot::channel();
@@ -680,7 +745,7 @@ impl WrapperSpace for PanelSpace {
                                 .lock()
                                 .unwrap()
                                 .iter_mut()
-                                .find(|PanelClient { name, .. }| name == &id_clone)
+                                .find(|c| c.name == id_clone && c.instance == instance)
                             {
                                 old_client.client = c;
                                 old_client.security_ctx = security_context;
@@ -706,6 +771,35 @@ impl WrapperSpace for PanelSpace {
 
                     AppletMsg::NewProcess(self.id(), process.with_env(applet_env))
                 };
+                let start_delay =
+                    self.config.start_delay_ms.get(&panel_client.name).copied().unwrap_or_default();
+                if start_delay > 0 {
+                    let applet_tx = self.applet_tx.clone();
+                    let panel_id = self.id();
+                    let id = panel_client.instance_id();
+                    let mut msg = Some(msg);
+                    match self.loop_handle.insert_source(
+                        calloop::timer::Timer::from_duration(Duration::from_millis(
+                            start_delay.into(),
+                        )),
+                        move |_, _, state: &mut GlobalState| {
+                            if let Some(msg) = msg.take() {
+                                if let Err(e) = applet_tx.try_send(msg) {
+                                    error!("{e}");
+                                }
+                            }
+                            state.space.applet_started(&panel_id, &id);
+                            calloop::timer::TimeoutAction::Drop
+                        },
+                    ) {
+                        // the slot of the applet is reserved until it is started
+                        Ok(token) => self.pending_starts.push((panel_client.instance_id(), token)),
+                        Err(err) => {
+                            error!("Failed to schedule the start of {}: {}", panel_client.name, err)
+                        },
+                    }
+                    continue;
+                }
                 match self.applet_tx.try_send(msg) {
                     Ok(_) => {},
                     Err(e) => error!("{e}"),
@@ -713,6 +807,13 @@ impl WrapperSpace for PanelSpace {
             }
 
             info!("Done spawning applets");
+            drop(left_guard);
+            drop(center_guard);
+            drop(right_guard);
+            // badges set before the applets were spawned
+            for (applet, count) in std::mem::take(&mut self.pending_badges) {
+                self.set_applet_badge(&applet, count);
+            }
             Ok(())
         } else {
             anyhow::bail!("Clients have already been spawned!");
@@ -834,6 +935,21 @@ impl WrapperSpace for PanelSpace {
                 .find(|(_, f)| f.1 == seat_name)
                 .map(|(i, f)| (i, f.0.clone()))
         } {
+            let has_popup = !self.popups.is_empty() || self.overflow_popup.is_some();
+            let on_panel = self.layer.as_ref().is_some_and(|s| s.wl_surface() == &prev_foc.1)
+                || self.popups.iter().any(|p| p.popup.c_popup.wl_surface() == &prev_foc.1)
+                || self
+                    .overflow_popup
+                    .as_ref()
+                    .is_some_and(|(p, _)| p.c_popup.wl_surface() == &prev_foc.1);
+            if has_popup && !on_panel {
+                // the grab is scoped to this panel, so a press anywhere else, e.g. on a
+                // panel on another output, dismisses the popups
+                if press {
+                    self.dismiss_popups(|_| false);
+                }
+                return None;
+            }
             let target = self.s_hovered_surface.iter().find_map(|h| {
                 if h.seat_name.as_str() == seat_name {
                     Some(h.surface.clone())
@@ -844,13 +960,18 @@ impl WrapperSpace for PanelSpace {
             if target.is_none() {
                 // close popups when panel is pressed
                 if self.layer.as_ref().map(|s| s.wl_surface()) == Some(&prev_foc.1) && press {
-                    self.close_popups(|_| false);
+                    self.dismiss_popups(|_| false);
+                    if let Some(pos) = self.last_pointer_pos {
+                        if !self.handle_click_zones(pos) {
+                            self.handle_empty_click(pos);
+                        }
+                    }
                 }
             }
             target
         } else {
             if press {
-                self.close_popups(|_| false);
+                self.dismiss_popups(|_| false);
             }
             // no hover found
             // if has keyboard focus remove it and close popups
@@ -910,6 +1031,7 @@ impl WrapperSpace for PanelSpace {
                 self.s_hovered_surface.last().cloned()
             }
         } else if self.layer.as_ref().is_some_and(|s| *s.wl_surface() == c_wl_surface) {
+            self.last_pointer_pos = Some((x, y).into());
             // if not on this panel's client surface return None
 
             // FIXME
@@ -921,10 +1043,18 @@ impl WrapperSpace for PanelSpace {
 
                 let mut size = match e {
                     CosmicMappedInternal::OverflowButton(b) => b.bbox().size,
+                    CosmicMappedInternal::StartButton(b) => b.bbox().size,
                     CosmicMappedInternal::Window(w) => w.bbox().size,
                     _ => return None,
                 }
                 .to_f64();
+                // applets thicker than the panel may be drawn scaled down
+                let scale = if matches!(e, CosmicMappedInternal::Window(_)) {
+                    let thickness = if self.config.is_horizontal() { size.h } else { size.w };
+                    self.config.fit_applet_thickness(thickness).1
+                } else {
+                    1.
+                };
 
                 if let Some(configured_size) = e.toplevel().and_then(|t| t.current_state().size) {
                     if configured_size.w > 0 {
@@ -934,15 +1064,47 @@ impl WrapperSpace for PanelSpace {
                         size.h = size.h.min(configured_size.h as f64);
                     }
                 }
-                let bbox = Rectangle::from_loc_and_size(location.to_f64(), size);
+                let size = size.upscale(scale);
+                // an applet which draws only in popups still takes the clicks
+                // on the whole length of its reserved slot
+                let mut slot = size;
+                if let Some(reserved) = e.toplevel().and_then(|t| self.reserved_slot(t)) {
+                    if self.config.is_horizontal() {
+                        slot.w = slot.w.max(reserved as f64);
+                    } else {
+                        slot.h = slot.h.max(reserved as f64);
+                    }
+                }
+                let bbox = Rectangle::from_loc_and_size(location.to_f64(), slot);
                 if bbox.contains((x as f64, y as f64)) {
-                    SpaceTarget::try_from(e.clone()).ok().map(|s| (e.clone(), location, s))
+                    SpaceTarget::try_from(e.clone())
+                        .ok()
+                        .map(|s| (e.clone(), location, s, size, scale))
                 } else {
                     None
                 }
             });
 
-            if let Some((target, relative_loc, space_target)) = space_focus {
+            if let Some((target, relative_loc, space_target, size, scale)) = space_focus {
+                // mirrored applets receive the pointer mirrored within their
+                // bounds, and scaled applets the pointer scaled like their
+                // surface
+                let pos = Point::<f64, Logical>::from((x as f64, y as f64));
+                let mut local = pos - relative_loc.to_f64();
+                if matches!(target, CosmicMappedInternal::Window(_)) {
+                    let [mirror_x, mirror_y] = self.content_mirror();
+                    if mirror_x > 0. {
+                        local.x = size.w - local.x;
+                    }
+                    if mirror_y > 0. {
+                        local.y = size.h - local.y;
+                    }
+                    // the pointer in the reserved slot outside of the surface
+                    // is delivered at the nearest point of the surface
+                    local.x = local.x.clamp(0., size.w);
+                    local.y = local.y.clamp(0., size.h);
+                }
+                let s_pos = pos - local.downscale(scale);
                 let geo =
                     target.bbox().to_f64().to_physical(1.0).to_logical(self.scale).to_i32_round();
                 if let Some(prev_kbd) = prev_foc {
@@ -967,7 +1129,7 @@ impl WrapperSpace for PanelSpace {
                 };
 
                 if let Some((_, prev_foc)) = prev_hover.as_mut() {
-                    prev_foc.s_pos = relative_loc.to_f64();
+                    prev_foc.s_pos = s_pos;
                     prev_foc.c_pos = geo.loc;
                     prev_foc.surface = space_target;
                     Some(prev_foc.clone())
@@ -976,7 +1138,7 @@ impl WrapperSpace for PanelSpace {
                         surface: space_target,
                         seat_name: seat_name.to_string(),
                         c_pos: geo.loc,
-                        s_pos: relative_loc.to_f64(),
+                        s_pos,
                     });
                     self.s_hovered_surface.last().cloned()
                 }
@@ -1221,14 +1383,26 @@ impl WrapperSpace for PanelSpace {
         ret.map(|f| (f, generated_pointer_events))
     }
 
-    fn keyboard_leave(&mut self, seat_name: &str, _: Option<c_wl_surface::WlSurface>) {
+    fn keyboard_leave(&mut self, seat_name: &str, surface: Option<c_wl_surface::WlSurface>) {
         self.s_focused_surface.retain(|(_, name)| name != seat_name);
+        // the compositor took the keyboard focus from the panel
+        if surface.map_or(true, |s| self.layer.as_ref().is_some_and(|l| l.wl_surface() == &s)) {
+            self.stop_keyboard_navigation();
+        }
 
-        self.close_popups(|_| false);
+        self.dismiss_popups(|_| false);
     }
 
-    fn keyboard_enter(&mut self, _: &str, _: c_wl_surface::WlSurface) -> Option<s_WlSurface> {
-        None
+    fn keyboard_enter(
+        &mut self,
+        _: &str,
+        c_wl_surface: c_wl_surface::WlSurface,
+    ) -> Option<s_WlSurface> {
+        // key events for a popup go to the applet which owns it
+        self.popups
+            .iter()
+            .find(|p| p.popup.c_popup.wl_surface() == &c_wl_surface)
+            .map(|p| p.s_surface.wl_surface().clone())
     }
 
     fn pointer_leave(&mut self, seat_name: &str, _s: Option<c_wl_surface::WlSurface>) {
@@ -1277,17 +1451,8 @@ impl WrapperSpace for PanelSpace {
         s_output: Output,
         info: OutputInfo,
     ) -> anyhow::Result<bool> {
-        let old = self.output.replace((c_output, s_output, info.clone()));
-
-        if old.is_some_and(|old| old.2.logical_size != info.logical_size) {
-            let (width, height) = if self.config.is_horizontal() {
-                (0, self.dimensions.h)
-            } else {
-                (self.dimensions.w, 0)
-            };
-            self.pending_dimensions = Some((width, height).into());
-            self.clear();
-        }
+        self.output.replace((c_output, s_output, info));
+        self.reposition_reactive_popups(true);
         Ok(true)
     }
 
@@ -1315,9 +1480,6 @@ impl WrapperSpace for PanelSpace {
             self.overflow_right.map_output(s_output, output_info.location);
 
             match &self.config.output {
-                CosmicPanelOuput::Active => {
-                    bail!("output does not match config")
-                },
                 CosmicPanelOuput::Name(config_name)
                     if output_info.name != Some(config_name.to_string()) =>
                 {
@@ -1331,6 +1493,7 @@ impl WrapperSpace for PanelSpace {
         } else if !matches!(self.config.output, CosmicPanelOuput::Active) {
             bail!("output does not match config");
         }
+        self.config.resolve_margin(output_info.as_ref().and_then(|info| info.logical_size));
         let dimensions: Size<i32, Logical> =
             self.constrain_dim((0, 0).into(), Some(self.gap() as u32));
 
@@ -1364,16 +1527,45 @@ impl WrapperSpace for PanelSpace {
         client_surface.set_size(dimensions.w.try_into().unwrap(), dimensions.h.try_into().unwrap());
 
         client_surface.set_anchor(self.config.anchor.into());
+        if self.config.get_length_range().is_some() || self.config.gaps.is_some() {
+            PanelSpace::set_margin(
+                self.config.anchor,
+                self.config.get_length_gaps(self.config.get_margin() as u32),
+                self.additional_gap,
+                PanelSpace::length_margins_for(&self.config, output_info.as_ref()),
+                self.pixel_grid(),
+                &client_surface,
+            );
+        }
 
         let input_region = Region::new(compositor_state)?;
         client_surface.wl_surface().set_input_region(Some(input_region.wl_region()));
         self.input_region.replace(input_region);
+        self.opaque_region.replace(Region::new(compositor_state)?);
 
         let fractional_scale =
             fractional_scale_manager.map(|f| f.fractional_scaling(client_surface.wl_surface(), qh));
 
         let viewport = viewport.map(|v| v.get_viewport(client_surface.wl_surface(), qh));
 
+        // seed the scale from the output so the first frame has the right size,
+        // the preferred fractional scale replaces it once it is received
+        if let Some(output_info) = output_info.as_ref() {
+            let scale = output_info.scale_factor.max(1);
+            self.scale = scale as f64;
+            if fractional_scale.is_none() {
+                client_surface.wl_surface().set_buffer_scale(scale);
+            }
+            if let Some(s_output) = s_output.as_ref() {
+                s_output.change_current_state(
+                    None,
+                    None,
+                    Some(smithay::output::Scale::Integer(scale)),
+                    None,
+                );
+            }
+        }
+
         client_surface.commit();
         if let Some(notify) = self.overlap_notify.as_ref() {
             let notification = notify.notify.notify_on_overlap(
@@ -1410,6 +1602,7 @@ impl WrapperSpace for PanelSpace {
         self.left_overflow_popup_id = id::Id::new(format!("left_overflow_popup_{}", self.id()));
         self.right_overflow_popup_id = id::Id::new(format!("right_overflow_popup_{}", self.id()));
         self.center_overflow_popup_id = id::Id::new(format!("center_overflow_popup_{}", self.id()));
+        self.restore_state();
 
         if let Err(err) = self.spawn_clients(
             self.s_display.clone().unwrap(),
@@ -1418,6 +1611,7 @@ impl WrapperSpace for PanelSpace {
         ) {
             error!(?err, "Failed to spawn clients");
         }
+        self.sync_crashed_applets();
         Ok(())
     }
 
@@ -1435,6 +1629,7 @@ impl WrapperSpace for PanelSpace {
     fn frame(&mut self, surface: &c_wl_surface::WlSurface, _time: u32) {
         if Some(surface) == self.layer.as_ref().map(|l| l.wl_surface()) {
             self.has_frame = true;
+            self.frame_requested = None;
         } else if let Some(p) =
             self.popups.iter_mut().find(|p| surface == p.popup.c_popup.wl_surface())
         {
@@ -1627,3 +1822,197 @@ impl WrapperSpace for PanelSpace {
         // TODO handle the preferred transform
     }
 }
+
+/// longest delay before restarting a crashed applet
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+/// sandbox engine of the security contexts of sandboxed applets, which is not
+/// granted the privileged protocols of the panel
+const SANDBOX_ENGINE: &str = "com.system76.CosmicPanel.Sandboxed";
+
+/// record a crash of the named applet, returning how long to wait before
+/// restarting it, or `None` if it crashed too often to be restarted
+///
+/// The delay doubles with each crash within the configured window of the
+/// previous ones.
+fn record_crash(
+    clients: &Clients,
+    name: &str,
+    instance: u32,
+    policy: CrashRestart,
+) -> Option<Duration> {
+    let mut clients = clients.lock().unwrap();
+    let delay = Duration::from_millis(policy.delay.into());
+    let Some(client) = clients.iter_mut().find(|c| c.name == name && c.instance == instance) else {
+        return Some(delay);
+    };
+    let now = Instant::now();
+    let window = Duration::from_millis(policy.window.into());
+    client.crashes.retain(|t| now.duration_since(*t) < window);
+    client.crashes.push(now);
+    let crashes = client.crashes.len() as u32;
+    if crashes > policy.max_crashes {
+        client.crashed = true;
+        return None;
+    }
+    Some(delay.saturating_mul(1 << (crashes - 1).min(16)).min(MAX_RESTART_DELAY))
+}
+
+/// the applets of each section with the index of their instance, dropping
+/// applets listed more than once in the panel unless instances are configured
+fn applet_instances(
+    sections: [Vec<String>; 3],
+    duplicates: DuplicateApplets,
+) -> [Vec<(String, u32)>; 3] {
+    let mut counts: HashMap<String, u32> = HashMap::new();
+    sections.map(|applets| {
+        applets
+            .into_iter()
+            .filter_map(|name| {
+                let count = counts.entry(name.clone()).or_default();
+                let instance = *count;
+                *count += 1;
+                if instance > 0 && duplicates == DuplicateApplets::Dedup {
+                    warn!("Applet {} is listed more than once, starting it once", name);
+                    return None;
+                }
+                Some((name, instance))
+            })
+            .collect()
+    })
+}
+
+/// number of popups among the given parent surface of a new popup and its
+/// parents, given the parent of a surface if it is a popup
+fn popup_depth<T>(parent: Option<T>, popup_parent: impl Fn(&T) -> Option<Option<T>>) -> usize {
+    let mut depth = 0;
+    let mut surface = parent;
+    while let Some(parent) = surface.as_ref().and_then(&popup_parent) {
+        depth += 1;
+        surface = parent;
+    }
+    depth
+}
+
+/// a client for each instance of the applets of each section
+fn instance_clients(
+    display: &mut DisplayHandle,
+    sections: [Vec<(String, u32)>; 3],
+) -> [Vec<PanelClient>; 3] {
+    sections.map(|applets| {
+        applets
+            .into_iter()
+            .map(|(name, instance)| {
+                let (c, s) = get_client_sock(display);
+                let mut client = PanelClient::new(name, c, Some(s));
+                client.instance = instance;
+                client
+            })
+            .collect()
+    })
+}
+
+/// create a security context for the connection of an applet to the host
+/// compositor, returning it with the socket of the connection
+///
+/// Sandboxed applets get a context with their own sandbox engine and app id,
+/// so the compositor does not grant them the privileged protocols of the
+/// panel.
+fn host_security_context(
+    manager: &SecurityContextManager,
+    qh: &QueueHandle<GlobalState>,
+    name: &str,
+    sandboxed: bool,
+) -> Option<(WpSecurityContextV1, OwnedFd)> {
+    let security_context = match manager.create_listener::<SpaceContainer>(qh) {
+        Ok(security_context) => security_context,
+        Err(why) => {
+            error!(?why, "Failed to create a listener");
+            return None;
+        },
+    };
+    if sandboxed {
+        security_context.set_sandbox_engine(SANDBOX_ENGINE.to_string());
+        security_context.set_app_id(name.to_string());
+    } else {
+        security_context.set_sandbox_engine(NAME.to_string());
+    }
+    security_context.commit();
+
+    let data = security_context.data::<SecurityContext>().unwrap();
+    let socket = data.conn.lock().unwrap().take().unwrap();
+    Some((security_context, socket.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sections(left: &[&str], center: &[&str], right: &[&str]) -> [Vec<String>; 3] {
+        [left, center, right].map(|s| s.iter().map(|name| name.to_string()).collect())
+    }
+
+    #[test]
+    fn duplicates_are_numbered_across_sections() {
+        let [left, center, right] = applet_instances(
+            sections(&["a", "b"], &["a"], &["c", "a"]),
+            DuplicateApplets::Instances,
+        );
+        assert_eq!(left, vec![("a".to_string(), 0), ("b".to_string(), 0)]);
+        assert_eq!(center, vec![("a".to_string(), 1)]);
+        assert_eq!(right, vec![("c".to_string(), 0), ("a".to_string(), 2)]);
+    }
+
+    #[test]
+    fn dedup_keeps_the_first_occurrence() {
+        let [left, center, right] =
+            applet_instances(sections(&["a", "b", "a"], &["b"], &["c"]), DuplicateApplets::Dedup);
+        assert_eq!(left, vec![("a".to_string(), 0), ("b".to_string(), 0)]);
+        assert!(center.is_empty());
+        assert_eq!(right, vec![("c".to_string(), 0)]);
+    }
+
+    #[test]
+    fn default_starts_every_occurrence() {
+        let [left, ..] = applet_instances(sections(&["a", "a"], &[], &[]), Default::default());
+        assert_eq!(left, vec![("a".to_string(), 0), ("a".to_string(), 1)]);
+    }
+
+    #[test]
+    fn nested_popups_hit_the_cap() {
+        // a popup of the applet window `w`, with a nested popup in it
+        let parents = HashMap::from([("a", "w"), ("b", "a")]);
+        let depth = |parent| popup_depth(parent, |s| parents.get(s).map(|p| Some(*p)));
+        let max = 2;
+        assert_eq!(depth(Some("w")), 0);
+        assert!(depth(Some("a")) < max);
+        // a third popup nested in `b` exceeds the cap
+        assert_eq!(depth(Some("b")), 2);
+        assert!(depth(Some("b")) >= max);
+        assert_eq!(depth(None), 0);
+    }
+
+    #[test]
+    fn instances_keep_separate_state() {
+        let display = wayland_server::Display::<()>::new().unwrap();
+        let [left, ..] = instance_clients(
+            &mut display.handle(),
+            applet_instances(sections(&["a", "a"], &[], &[]), DuplicateApplets::Instances),
+        );
+        assert_eq!(left.iter().map(PanelClient::instance_id).collect::<Vec<_>>(), ["a", "a#1"]);
+        assert_ne!(left[0].client.id(), left[1].client.id());
+
+        let clients: Clients = Arc::new(Mutex::new(left));
+        let policy = CrashRestart { delay: 1, max_crashes: 1, window: 60_000 };
+        assert!(record_crash(&clients, "a", 1, policy).is_some());
+        assert!(record_crash(&clients, "a", 1, policy).is_none());
+        let crashed = clients
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|c| c.crashed)
+            .map(PanelClient::instance_id)
+            .collect::<Vec<_>>();
+        assert_eq!(crashed, ["a#1"]);
+    }
+}
@@ -1,19 +1,23 @@
 use std::{
     cell::RefCell,
     collections::{HashMap, HashSet},
+    path::PathBuf,
     rc::Rc,
     sync::Arc,
+    time::Duration,
 };
 
 use crate::{
+    applet_discovery,
+    iced::elements::CosmicMappedInternal,
     minimize::MinimizeApplet,
-    space::{AppletMsg, PanelColors, PanelSpace},
+    space::{move_plugin, Alignment, AppletMsg, OverflowSection, PanelColors, PanelSpace},
     xdg_shell_wrapper::{
         self,
         client::handlers::overlap::OverlapNotifyV1,
         client_state::ClientFocus,
         shared_state::GlobalState,
-        space::{Visibility, WrapperSpace},
+        space::{Visibility, VisibilityKind, WrapperSpace},
         wp_fractional_scaling::FractionalScalingManager,
         wp_security_context::SecurityContextManager,
         wp_viewporter::ViewporterState,
@@ -26,18 +30,30 @@ use cctk::{
     wayland_client::{self, protocol::wl_seat::WlSeat},
     workspace::WorkspaceGroup,
 };
-use cosmic::{cosmic_config::CosmicConfigEntry, iced::id, theme};
+use cosmic::{
+    cosmic_config::{ConfigSet, CosmicConfigEntry},
+    iced::id,
+    theme,
+};
 use cosmic_panel_config::{
-    CosmicPanelBackground, CosmicPanelConfig, CosmicPanelContainerConfig, CosmicPanelOuput,
-    PanelAnchor,
+    CornerOwnership, CosmicPanelBackground, CosmicPanelConfig, CosmicPanelContainerConfig,
+    CosmicPanelOuput, ExpandAnimation, PanelAnchor,
 };
 use cosmic_theme::{Theme, ThemeMode};
+use itertools::Itertools;
 use notify::RecommendedWatcher;
 use sctk::{
     output::OutputInfo,
     reexports::{
         calloop,
-        client::{protocol::wl_output::WlOutput, Connection, QueueHandle},
+        client::{
+            protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+            Connection, QueueHandle,
+        },
+    },
+    seat::{
+        keyboard::Keysym,
+        pointer::{CursorIcon, PointerEvent},
     },
     shell::wlr_layer::LayerShell,
 };
@@ -45,6 +61,8 @@ use smithay::{
     backend::renderer::gles::GlesRenderer,
     output::Output,
     reexports::wayland_server::{self, backend::ClientId},
+    utils::{Logical, Point, Rectangle, Size},
+    wayland::shell::xdg::PopupSurface,
 };
 use tokio::sync::mpsc;
 use tracing::{error, info};
@@ -62,17 +80,28 @@ pub struct SpaceContainer {
     pub panel_tx: calloop::channel::Sender<PanelCalloopMsg>,
     pub(crate) outputs: Vec<(WlOutput, Output, OutputInfo)>,
     pub(crate) watchers: HashMap<String, RecommendedWatcher>,
+    /// watchers of the applets directories of panels, by panel name
+    pub(crate) applet_dir_watchers: HashMap<String, (PathBuf, RecommendedWatcher)>,
+    /// applets found in the applets directory of each panel, by panel name,
+    /// which are not listed in its config
+    pub(crate) discovered_applets: HashMap<String, [Vec<String>; 3]>,
     pub(crate) maximized_toplevels: Vec<(ZcosmicToplevelHandleV1, ToplevelInfo)>,
     pub(crate) toplevels: Vec<(ZcosmicToplevelHandleV1, ToplevelInfo)>,
     pub(crate) workspace_groups: Vec<WorkspaceGroup>,
     pub(crate) is_dark: bool,
     pub(crate) light_theme: cosmic::Theme,
     pub(crate) dark_theme: cosmic::Theme,
+    /// whether the light theme failed to load
+    pub(crate) light_theme_failed: bool,
+    /// whether the dark theme failed to load
+    pub(crate) dark_theme_failed: bool,
     pub(crate) security_context_manager: Option<SecurityContextManager>,
     /// map from output name to minimized applet info
     pub(crate) minimized_applets: HashMap<String, MinimizeApplet>,
     pub(crate) loop_handle: calloop::LoopHandle<'static, GlobalState>,
     pub(crate) overlap_notify: Option<OverlapNotifyV1>,
+    /// name of the output which last had the keyboard or pointer focus
+    pub(crate) focused_output: Option<String>,
 }
 
 impl SpaceContainer {
@@ -88,14 +117,16 @@ impl SpaceContainer {
             .unwrap_or_default()
             .is_dark;
 
-        let light = Theme::light_config()
-            .ok()
-            .and_then(|c| Theme::get_entry(&c).ok())
-            .unwrap_or_else(Theme::light_default);
-        let dark = Theme::dark_config()
-            .ok()
-            .and_then(|c| Theme::get_entry(&c).ok())
-            .unwrap_or_else(Theme::dark_default);
+        let light = Theme::light_config().ok().and_then(|c| Theme::get_entry(&c).ok());
+        let dark = Theme::dark_config().ok().and_then(|c| Theme::get_entry(&c).ok());
+        let (light_theme_failed, dark_theme_failed) = (light.is_none(), dark.is_none());
+        let light = light.unwrap_or_else(Theme::light_default);
+        let dark = dark.unwrap_or_else(Theme::dark_default);
+        let discovered_applets = config
+            .config_list
+            .iter()
+            .map(|c| (c.name.clone(), applet_discovery::discover_applets(c)))
+            .collect();
 
         Self {
             connection: None,
@@ -109,45 +140,67 @@ impl SpaceContainer {
             panel_tx,
             outputs: vec![],
             watchers: HashMap::new(),
+            applet_dir_watchers: HashMap::new(),
+            discovered_applets,
             maximized_toplevels: Vec::with_capacity(1),
             toplevels: Vec::new(),
             workspace_groups: Vec::new(),
             is_dark,
             light_theme: cosmic::Theme::system(Arc::new(light)),
             dark_theme: cosmic::Theme::system(Arc::new(dark)),
+            light_theme_failed,
+            dark_theme_failed,
             security_context_manager: None,
             minimized_applets: HashMap::new(),
             loop_handle,
             overlap_notify: None,
+            focused_output: None,
         }
     }
 
-    pub fn set_dark(&mut self, theme: theme::CosmicTheme) {
+    /// set the dark theme, and whether it failed to load
+    pub fn set_dark(&mut self, theme: theme::CosmicTheme, failed: bool) {
         self.dark_theme = cosmic::Theme::system(Arc::new(theme));
+        self.dark_theme_failed = failed;
+        let fallback_bg = self.fallback_bg(true);
 
         for space in &mut self.space_list {
             let is_dark = space.is_dark(self.is_dark);
             if is_dark {
                 space.set_theme(
                     PanelColors::new(self.dark_theme.clone())
-                        .with_color_override(space.config.bg_color_override()),
+                        .with_color_override(space.config.bg_color_override())
+                        .with_fallback_bg(fallback_bg),
                 );
             }
         }
+        self.invalidate();
     }
 
-    pub fn set_light(&mut self, theme: theme::CosmicTheme) {
+    /// set the light theme, and whether it failed to load
+    pub fn set_light(&mut self, theme: theme::CosmicTheme, failed: bool) {
         self.light_theme = cosmic::Theme::system(Arc::new(theme));
+        self.light_theme_failed = failed;
+        let fallback_bg = self.fallback_bg(false);
 
         for space in &mut self.space_list {
             let is_dark = space.is_dark(self.is_dark);
             if !is_dark {
                 space.set_theme(
                     PanelColors::new(self.light_theme.clone())
-                        .with_color_override(space.config.bg_color_override()),
+                        .with_color_override(space.config.bg_color_override())
+                        .with_fallback_bg(fallback_bg),
                 );
             }
         }
+        self.invalidate();
+    }
+
+    /// force a full redraw of every panel on the next render
+    pub fn invalidate(&mut self) {
+        for space in &mut self.space_list {
+            space.invalidate();
+        }
     }
 
     pub fn cur_theme(&self) -> cosmic::Theme {
@@ -158,6 +211,24 @@ impl SpaceContainer {
         }
     }
 
+    /// the configured fallback background, if the light or dark theme failed
+    /// to load
+    fn fallback_bg(&self, is_dark: bool) -> Option<[f32; 3]> {
+        let failed = if is_dark { self.dark_theme_failed } else { self.light_theme_failed };
+        self.config.fallback_background.filter(|_| failed)
+    }
+
+    /// colors of a new panel with the given background
+    pub(crate) fn panel_colors(&self, background: &CosmicPanelBackground) -> PanelColors {
+        let is_dark = match background {
+            CosmicPanelBackground::ThemeDefault | CosmicPanelBackground::Color(_) => self.is_dark,
+            CosmicPanelBackground::Dark => true,
+            CosmicPanelBackground::Light => false,
+        };
+        let theme = if is_dark { self.dark_theme.clone() } else { self.light_theme.clone() };
+        PanelColors::new(theme).with_fallback_bg(self.fallback_bg(is_dark))
+    }
+
     pub fn cleanup_client(&mut self, old_client_id: ClientId) {
         for s in &mut self.space_list {
             // cleanup leftover windows
@@ -196,28 +267,88 @@ impl SpaceContainer {
         self.is_dark = is_dark;
         if changed {
             let cur = self.cur_theme();
+            let fallback_bg = self.fallback_bg(is_dark);
             for space in &mut self.space_list {
                 if matches!(space.config.background, CosmicPanelBackground::ThemeDefault) {
                     space.set_theme(
                         PanelColors::new(cur.clone())
-                            .with_color_override(space.config.bg_color_override()),
+                            .with_color_override(space.config.bg_color_override())
+                            .with_fallback_bg(fallback_bg),
                     );
                 }
             }
+            self.invalidate();
         }
     }
 
     /// apply a removed entry to the space list
     pub fn remove_space(&mut self, name: String) {
-        self.space_list.retain(|s| s.config.name != name);
+        self.space_list.retain(|s| {
+            s.config.name != name && !s.mirror_of.as_ref().is_some_and(|c| c.name == name)
+        });
         self.config.config_list.retain(|c| c.name != name);
         self.watchers.remove(&name);
+        self.applet_dir_watchers.remove(&name);
+    }
+
+    /// reset the named panel to the default configuration, keeping its name and
+    /// output
+    ///
+    /// Only the entry of the panel is persisted, with the output it has on
+    /// disk, and the panel is recreated on each of its outputs.
+    pub fn reset_space(&mut self, name: &str) {
+        let Some(old) = self.config.config_list.iter_mut().find(|c| c.name == name) else {
+            error!("No panel named {} to reset", name);
+            return;
+        };
+        let helper = match CosmicPanelConfig::cosmic_config(name) {
+            Ok(helper) => helper,
+            Err(err) => {
+                error!("Failed to load the config of panel {}: {:?}", name, err);
+                return;
+            },
+        };
+        let output = match CosmicPanelConfig::get_entry(&helper) {
+            Ok(entry) | Err((_, entry)) => entry.output,
+        };
+        let entry = CosmicPanelConfig { name: name.to_string(), output, ..Default::default() };
+        *old = entry.clone();
+        if let Err(err) = entry.write_entry(&helper) {
+            error!("Failed to write the reset panel config: {:?}", err);
+        }
+        for s in self.space_list.iter().filter(|s| s.config.name == name) {
+            if let Some((wl_output, ..)) = s.output.as_ref() {
+                _ = self
+                    .panel_tx
+                    .send(PanelCalloopMsg::RestartSpace(entry.clone(), wl_output.clone()));
+            }
+        }
+    }
+
+    /// start or stop watching the applets directory of a panel
+    pub(crate) fn watch_applets_dir(&mut self, entry: &CosmicPanelConfig) {
+        let Some(dir) = entry.applets_dir.as_ref() else {
+            self.applet_dir_watchers.remove(&entry.name);
+            return;
+        };
+        if self.applet_dir_watchers.get(&entry.name).is_some_and(|(path, _)| path == dir) {
+            return;
+        }
+        match applet_discovery::watch_applets_dir(entry.name.clone(), dir, self.panel_tx.clone()) {
+            Ok(watcher) => {
+                self.applet_dir_watchers.insert(entry.name.clone(), (dir.clone(), watcher));
+            },
+            Err(err) => {
+                error!("Failed to watch applets directory {:?}: {}", dir, err);
+                self.applet_dir_watchers.remove(&entry.name);
+            },
+        }
     }
 
     /// apply a new or updated entry to the space list
     pub fn update_space(
         &mut self,
-        mut entry: CosmicPanelConfig,
+        entry: CosmicPanelConfig,
         compositor_state: &sctk::compositor::CompositorState,
         fractional_scale_manager: Option<&FractionalScalingManager>,
         viewport: Option<&ViewporterState>,
@@ -226,25 +357,42 @@ impl SpaceContainer {
         force_output: Option<WlOutput>,
         overlap_notify: Option<OverlapNotifyV1>,
     ) {
+        self.watch_applets_dir(&entry);
+        // applets added to or removed from the applets directory require the
+        // applets to be spawned again, though the entry is unchanged
+        let discovered = applet_discovery::discover_applets(&entry);
+        let discovery_changed =
+            self.discovered_applets.get(&entry.name).is_some_and(|d| d != &discovered);
+        self.discovered_applets.insert(entry.name.clone(), discovered);
+
         // if the output is set to "all", we need to check if the config is the same for
         // all outputs if the output is set to a specific output, we need to
         // make sure it doesn't exist on another output
+        let panel_output_count = self
+            .outputs
+            .iter()
+            .filter(|(_, o, _)| !entry.external_only || !self.config.is_primary_output(&o.name()))
+            .filter(|(_, o, _)| !entry.exclude_outputs.contains(&o.name()))
+            .count();
         let mut output_count = if matches!(entry.output, CosmicPanelOuput::All) {
-            self.outputs.len()
+            panel_output_count
         } else {
             self.space_list.iter().filter(|s| s.config.name == entry.name).count()
         } as isize;
 
         if force_output.is_none()
+            && !discovery_changed
             && self.space_list.iter_mut().any(|s| {
-                let ret = if matches!(entry.output, CosmicPanelOuput::All) {
-                    entry.output = s.config.output.clone();
-                    let ret = s.config == entry;
-                    entry.output = CosmicPanelOuput::All;
-                    ret
-                } else {
-                    s.config == entry
-                };
+                let mut effective = entry.clone();
+                if matches!(entry.output, CosmicPanelOuput::All) {
+                    effective.output = s.config.output.clone();
+                }
+                if let Some((_, output, _)) = s.output.as_ref() {
+                    effective.apply_anchor_override(&output.name());
+                }
+                effective.apply_orientation_overrides();
+                effective.resolve_margin(s.output.as_ref().and_then(|o| o.2.logical_size));
+                let ret = s.config == effective;
                 if ret {
                     output_count -= 1;
                 }
@@ -265,7 +413,7 @@ impl SpaceContainer {
         let output_count_mismatch = match entry.output {
             CosmicPanelOuput::All => {
                 self.space_list.iter().filter(|s| s.config.name == entry.name).count()
-                    != self.outputs.len()
+                    != panel_output_count
             },
             CosmicPanelOuput::Name(_) => {
                 self.space_list.iter().filter(|s| s.config.name == entry.name).count() != 1
@@ -295,8 +443,12 @@ impl SpaceContainer {
         // or if the output is the same, but the priority changes to conflict with an
         // adjacent panel or if applet size changes
         let must_recreate =
+        // restarting the panel on a specific output
+        force_output.is_some()
+        // applets were added to or removed from the applets directory
+        || discovery_changed
         // implies that there is at least one output which needs to be recreated
-        output_count_mismatch
+        || output_count_mismatch
         || self.config.config_list.iter().any(|c| {
             // size changed
             c.name == entry.name && c.size != entry.size
@@ -312,32 +464,61 @@ impl SpaceContainer {
                 && (c.is_horizontal() != entry.is_horizontal()
                 || c.size != entry.size
                 || c.background != entry.background
+                || c.background_vertical != entry.background_vertical
                 || c.plugins_center != entry.plugins_center
                 || c.plugins_wings != entry.plugins_wings)))
             // Priority change to conflict with adjacent panel
             || c.name != entry.name
                 && Some(c.anchor) != opposite_anchor
+                && c.overlaps_lengthwise(&entry)
                 && ((old_priority < c.get_priority() && new_priority > c.get_priority() || old_priority > c.get_priority() && new_priority < c.get_priority()))}
-            || c.name != entry.name && old_priority != new_priority && c.anchor == entry.anchor
+            || c.name != entry.name
+                && old_priority != new_priority
+                && c.anchor == entry.anchor
+                && c.overlaps_lengthwise(&entry)
         );
 
         self.config.config_list.retain(|c| c.name != entry.name);
         self.config.config_list.push(entry.clone());
 
         if !must_recreate {
-            let bg_color = match entry.background {
-                CosmicPanelBackground::Color(c) => Some([c[0], c[1], c[2], entry.opacity]),
-                _ => None,
-            };
-
+            let mut restart_outputs = Vec::new();
+            let mut gap_changed = false;
             for space in &mut self.space_list {
                 if space.config.name != entry.name {
                     continue;
                 }
 
-                entry.output = space.config.output.clone();
-                space.update_config(entry.clone(), bg_color, true);
+                let mut effective = entry.clone();
+                effective.output = space.config.output.clone();
+                if let Some((wl_output, output, _)) = space.output.as_ref() {
+                    effective.apply_anchor_override(&output.name());
+                    // anchor changes require re-creation of the panel on this output only
+                    if effective.anchor != space.config.anchor {
+                        restart_outputs.push(wl_output.clone());
+                        continue;
+                    }
+                }
+                effective.apply_orientation_overrides();
+                gap_changed |=
+                    effective.get_effective_anchor_gap() != space.config.get_effective_anchor_gap();
+                let bg_color = effective.bg_color_override();
+                space.update_config(effective, bg_color, true);
+            }
+            for wl_output in restart_outputs {
+                _ = self.panel_tx.send(PanelCalloopMsg::RestartSpace(entry.clone(), wl_output));
             }
+            // a changed gap replaces any transient gap, and moves smoothly
+            if gap_changed {
+                self.animate_panel_gap(&entry.name, None, entry.expand_animation);
+            }
+            self.update_mirrors(
+                compositor_state,
+                fractional_scale_manager,
+                viewport,
+                layer_state,
+                qh,
+            );
             self.apply_toplevel_changes();
             return;
         }
@@ -354,19 +535,26 @@ impl SpaceContainer {
         });
 
         let outputs: Vec<_> = match &entry.output {
+            _ if !entry.creates_surface() => {
+                info!("{} has no applets, skipping surface creation", entry.name);
+                vec![]
+            },
             CosmicPanelOuput::Active => {
+                let (c_output, s_output, output_info) =
+                    match self.active_output_for(&entry).cloned() {
+                        Some((c_output, s_output, output_info)) => {
+                            (Some(c_output), Some(s_output), Some(output_info))
+                        },
+                        None => (None, None, None),
+                    };
+                let mut config = entry.clone();
+                config.apply_orientation_overrides();
                 let mut space = PanelSpace::new(
-                    entry.clone(),
+                    config.clone(),
                     self.c_focused_surface.clone(),
                     self.c_hovered_surface.clone(),
                     self.applet_tx.clone(),
-                    match entry.background {
-                        CosmicPanelBackground::ThemeDefault | CosmicPanelBackground::Color(_) => {
-                            self.cur_theme()
-                        },
-                        CosmicPanelBackground::Dark => self.dark_theme.clone(),
-                        CosmicPanelBackground::Light => self.light_theme.clone(),
-                    },
+                    self.panel_colors(&config.background),
                     self.s_display.clone().unwrap(),
                     self.security_context_manager.clone(),
                     self.connection.as_ref().unwrap(),
@@ -381,9 +569,9 @@ impl SpaceContainer {
                     layer_state,
                     connection,
                     qh,
-                    None,
-                    None,
-                    None,
+                    c_output,
+                    s_output,
+                    output_info,
                 ) {
                     error!("Failed to create space for active output: {}", err);
                 } else {
@@ -391,7 +579,14 @@ impl SpaceContainer {
                 }
                 vec![]
             },
-            CosmicPanelOuput::All => self.outputs.iter().collect(),
+            CosmicPanelOuput::All => self
+                .outputs
+                .iter()
+                .filter(|(_, o, _)| {
+                    !entry.external_only || !self.config.is_primary_output(&o.name())
+                })
+                .filter(|(_, o, _)| !entry.exclude_outputs.contains(&o.name()))
+                .collect(),
             CosmicPanelOuput::Name(name) => {
                 self.outputs.iter().filter(|(_, output, _)| &output.name() == name).collect()
             },
@@ -424,7 +619,7 @@ impl SpaceContainer {
                 if !is_recreated {
                     continue;
                 }
-                let visible = if c.autohide.is_none() || !has_toplevel {
+                let visible = if c.autohide().is_none() || !has_toplevel {
                     Visibility::Visible
                 } else {
                     Visibility::Hidden
@@ -440,18 +635,14 @@ impl SpaceContainer {
                     new_config.maximize();
                 }
                 new_config.output = CosmicPanelOuput::Name(output_name.clone());
+                new_config.apply_anchor_override(&output_name);
+                new_config.apply_orientation_overrides();
                 let mut space = PanelSpace::new(
                     new_config.clone(),
                     self.c_focused_surface.clone(),
                     self.c_hovered_surface.clone(),
                     self.applet_tx.clone(),
-                    match entry.background {
-                        CosmicPanelBackground::ThemeDefault | CosmicPanelBackground::Color(_) => {
-                            self.cur_theme()
-                        },
-                        CosmicPanelBackground::Dark => self.dark_theme.clone(),
-                        CosmicPanelBackground::Light => self.light_theme.clone(),
-                    },
+                    self.panel_colors(&new_config.background),
                     self.s_display.clone().unwrap(),
                     self.security_context_manager.clone(),
                     self.connection.as_ref().unwrap(),
@@ -479,9 +670,566 @@ impl SpaceContainer {
                 }
             }
         }
+        self.update_mirrors(compositor_state, fractional_scale_manager, viewport, layer_state, qh);
         self.apply_toplevel_changes();
     }
 
+    /// logical geometries of the outputs
+    pub(crate) fn output_geometries(&self) -> Vec<Rectangle<i32, Logical>> {
+        self.outputs
+            .iter()
+            .filter_map(|(_, _, info)| {
+                let (loc, size) = info.logical_position.zip(info.logical_size)?;
+                Some(Rectangle::from_loc_and_size(loc, size))
+            })
+            .collect()
+    }
+
+    /// whether a button event is intercepted by a panel instead of being
+    /// forwarded to the hovered applet
+    pub fn intercept_button(&mut self, seat_name: &str, press: bool) -> bool {
+        self.space_list.iter_mut().any(|s| {
+            if press {
+                s.intercept_press(seat_name)
+            } else {
+                s.intercept_release()
+            }
+        })
+    }
+
+    /// the visibility of each panel, with its name and output
+    pub fn panel_visibility(&self) -> Vec<(String, Option<String>, VisibilityKind)> {
+        self.space_list
+            .iter()
+            .map(|s| {
+                (
+                    s.config.name.clone(),
+                    s.output.as_ref().map(|(_, o, _)| o.name()),
+                    s.visibility_kind(),
+                )
+            })
+            .collect()
+    }
+
+    /// the geometry of each panel, with its name and output: its anchor, its
+    /// logical size, its gap to the anchored edge and the exclusive zone set
+    /// on its layer surface
+    pub fn panel_geometry(
+        &self,
+    ) -> Vec<(String, Option<String>, PanelAnchor, Size<i32, Logical>, i32, i32)> {
+        self.space_list
+            .iter()
+            .map(|s| {
+                (
+                    s.config.name.clone(),
+                    s.output.as_ref().map(|(_, o, _)| o.name()),
+                    s.config.anchor,
+                    s.dimensions,
+                    s.anchor_gap,
+                    s.current_exclusive_zone(),
+                )
+            })
+            .collect()
+    }
+
+    /// the applets which crashed too often to be restarted, with the name and
+    /// output of their panel
+    pub fn crashed_applets(&self) -> Vec<(String, Option<String>, String)> {
+        self.space_list
+            .iter()
+            // mirrors share the applets of the panels they mirror
+            .filter(|s| s.mirror_of.is_none())
+            .flat_map(|s| {
+                let output = s.output.as_ref().map(|(_, o, _)| o.name());
+                s.crashed_applet_ids()
+                    .into_iter()
+                    .map(move |applet| (s.config.name.clone(), output.clone(), applet))
+            })
+            .collect()
+    }
+
+    /// show placeholders in the slots of applets which are no longer
+    /// restarted or whose start is delayed
+    pub fn sync_crashed_applets(&mut self) {
+        for s in &mut self.space_list {
+            s.sync_crashed_applets();
+        }
+    }
+
+    /// draw the panels which are still waiting for their applets after the
+    /// timeout elapsed
+    pub(crate) fn applets_wait_elapsed(&mut self) {
+        for s in self.space_list.iter_mut().filter(|s| s.awaiting_applets.is_some()) {
+            s.is_dirty = true;
+        }
+    }
+
+    /// retry to create the surfaces of the popups of a panel which can't be
+    /// repositioned
+    pub(crate) fn retry_popup_surfaces(&mut self, panel_id: &str) {
+        let Some(renderer) = self.renderer.as_mut() else {
+            return;
+        };
+        if let Some(s) = self.space_list.iter_mut().find(|s| s.id() == panel_id) {
+            s.retry_popup_surfaces(renderer);
+        }
+    }
+
+    /// remove the placeholder of an applet with a start delay once it is
+    /// started
+    pub(crate) fn applet_started(&mut self, panel_id: &str, name: &str) {
+        if let Some(s) = self.space_list.iter_mut().find(|s| s.id() == panel_id) {
+            s.applet_started(name);
+        }
+    }
+
+    /// grab the popup of an applet which requested a grab on the given seat
+    pub(crate) fn grab_popup(&mut self, s_surface: &PopupSurface, seat: (u32, WlSeat)) {
+        for s in &mut self.space_list {
+            if s.grab_popup(s_surface, seat.clone()) {
+                break;
+            }
+        }
+    }
+
+    /// force the named panel to stay visible, or let it hide again
+    pub fn set_panel_pinned(&mut self, name: &str, pinned: bool) {
+        for s in self.space_list.iter_mut().filter(|s| s.config.name == name) {
+            s.set_pinned(pinned);
+        }
+    }
+
+    /// move an applet of the named panel to the given index of a section
+    ///
+    /// Only the plugin lists are written to the config of the panel, as they
+    /// are on disk, so that the move is kept when the panel is restarted.
+    pub fn move_applet_section(&mut self, name: &str, applet: &str, to: Alignment, index: usize) {
+        let Some(entry) = self.config.config_list.iter_mut().find(|c| c.name == name) else {
+            error!("No panel named {} to move applet {} in", name, applet);
+            return;
+        };
+        for s in self.space_list.iter_mut().filter(|s| s.config.name == name) {
+            s.move_applet_section(applet, to, index);
+        }
+        let helper = match CosmicPanelConfig::cosmic_config(name) {
+            Ok(helper) => helper,
+            Err(err) => {
+                error!("Failed to load the config of panel {}: {:?}", name, err);
+                return;
+            },
+        };
+        let mut on_disk = match CosmicPanelConfig::get_entry(&helper) {
+            Ok(entry) | Err((_, entry)) => entry,
+        };
+        move_plugin(&mut on_disk, applet, to, index);
+        // the watcher sees the loaded lists unchanged, and does not respawn
+        entry.plugins_wings = on_disk.plugins_wings.clone();
+        entry.plugins_center = on_disk.plugins_center.clone();
+        if let Err(err) = helper
+            .set("plugins_wings", &on_disk.plugins_wings)
+            .and_then(|_| helper.set("plugins_center", &on_disk.plugins_center))
+        {
+            error!("Failed to persist the moved applet {}: {:?}", applet, err);
+        }
+    }
+
+    /// hide or show an applet of the named panel without respawning it, or of
+    /// every panel if the name is empty
+    pub fn set_applet_visible(&mut self, name: &str, applet: &str, visible: bool) {
+        for s in self.space_list.iter_mut().filter(|s| name.is_empty() || s.config.name == name) {
+            s.set_applet_visible(applet, visible);
+        }
+    }
+
+    /// override the opacity of the named panel at runtime, or return to the
+    /// configured opacity
+    pub fn set_panel_opacity(&mut self, name: &str, opacity: Option<f32>) {
+        for s in self.space_list.iter_mut().filter(|s| s.config.name == name) {
+            s.set_opacity_override(opacity);
+        }
+    }
+
+    /// animate the gap between the named panel and the edge of its outputs to
+    /// the given gap, or back to the configured gap
+    pub fn animate_panel_gap(&mut self, name: &str, gap: Option<u16>, animation: ExpandAnimation) {
+        for s in self.space_list.iter_mut().filter(|s| s.config.name == name) {
+            s.animate_gap(gap, animation);
+        }
+    }
+
+    /// raise every panel to the overlay layer for the given duration, e.g. to
+    /// reveal them above windows
+    pub fn boost_panel_layers(&mut self, duration: Duration) {
+        for s in &mut self.space_list {
+            s.boost_layer(duration);
+        }
+    }
+
+    /// leave each corner shared by panels with exclusive zones on adjacent
+    /// edges to the panel which owns it
+    pub(crate) fn arbitrate_corners(&mut self) {
+        let ownership = self.config.corner_ownership;
+        let zones = self
+            .space_list
+            .iter()
+            .filter(|s| s.config.expand_to_edges())
+            .map(|s| {
+                (s.output.as_ref().map(|o| o.1.name()), s.config.anchor, s.current_exclusive_zone())
+            })
+            .filter(|(.., zone)| *zone > 0)
+            .collect_vec();
+        for s in &mut self.space_list {
+            let yields = match ownership {
+                CornerOwnership::Compositor => false,
+                CornerOwnership::Horizontal => !s.config.is_horizontal(),
+                CornerOwnership::Vertical => s.config.is_horizontal(),
+            };
+            let mut margins = (0, 0);
+            if yields && s.config.expand_to_edges() && s.current_exclusive_zone() > 0 {
+                let output = s.output.as_ref().map(|o| o.1.name());
+                let (start, end) = if s.config.is_horizontal() {
+                    (PanelAnchor::Left, PanelAnchor::Right)
+                } else {
+                    (PanelAnchor::Top, PanelAnchor::Bottom)
+                };
+                for (_, anchor, zone) in zones.iter().filter(|(o, ..)| *o == output) {
+                    if *anchor == start {
+                        margins.0 = margins.0.max(*zone);
+                    } else if *anchor == end {
+                        margins.1 = margins.1.max(*zone);
+                    }
+                }
+            }
+            s.set_corner_margins(margins);
+        }
+    }
+
+    /// allot each panel sharing an edge with split panels the part of the
+    /// output length which is not taken by them
+    ///
+    /// A panel keeps the part of its own range up to the start of the next
+    /// split panel on the edge, after any split panel covering its start.
+    pub(crate) fn allot_lengths(&mut self) {
+        let ranges = self
+            .space_list
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| {
+                let range = s.config.get_length_range()?;
+                Some((i, s.output.as_ref().map(|o| o.1.name()), s.config.anchor, range))
+            })
+            .collect_vec();
+        for (i, s) in self.space_list.iter_mut().enumerate() {
+            let output = s.output.as_ref().map(|o| o.1.name());
+            let mut others = ranges
+                .iter()
+                .filter(|(j, o, anchor, _)| *j != i && *o == output && *anchor == s.config.anchor)
+                .map(|(.., range)| *range)
+                .sorted_by(|a, b| a.0.total_cmp(&b.0))
+                .peekable();
+            if others.peek().is_none() {
+                s.set_length_budget(None);
+                continue;
+            }
+            let (mut start, mut end) = s.config.get_length_range().unwrap_or((0., 1.));
+            for (other_start, other_end) in others {
+                if other_start <= start && start < other_end {
+                    start = other_end;
+                } else if start < other_start && other_start < end {
+                    end = other_start;
+                }
+            }
+            s.set_length_budget(Some((end - start).max(0.)));
+        }
+    }
+
+    /// share the applets of mirrored panels with their mirrors
+    pub(crate) fn link_mirrors(&mut self) {
+        let links = self
+            .space_list
+            .iter()
+            .map(|mirror| {
+                let config = mirror.mirror_of.as_ref()?;
+                let source = self
+                    .space_list
+                    .iter()
+                    .find(|s| s.mirror_of.is_none() && &s.config == config)?;
+                let windows = source
+                    .space
+                    .elements()
+                    .filter_map(|e| match e {
+                        CosmicMappedInternal::Window(w) => Some(w.clone()),
+                        _ => None,
+                    })
+                    .collect_vec();
+                let clients = (
+                    source.clients_left.clone(),
+                    source.clients_center.clone(),
+                    source.clients_right.clone(),
+                );
+                Some((clients, windows, source.is_dirty))
+            })
+            .collect_vec();
+        for (mirror, link) in self.space_list.iter_mut().zip(links) {
+            if let Some((clients, windows, dirty)) = link {
+                mirror.mirror(clients, windows, dirty);
+            }
+        }
+    }
+
+    /// config of the mirror of a panel on the named output
+    fn mirror_config(source: &CosmicPanelConfig, output: &str) -> CosmicPanelConfig {
+        let mut config = source.clone();
+        config.name = format!("{}-mirror", source.name);
+        config.output = CosmicPanelOuput::Name(output.to_string());
+        config.mirror_to = None;
+        // a mirror can't be hovered, so it would never be revealed
+        config.autohide = None;
+        config
+    }
+
+    /// create the mirrors of panels which are configured to be mirrored to an
+    /// existing output, and drop mirrors which no longer are
+    pub(crate) fn update_mirrors(
+        &mut self,
+        compositor_state: &sctk::compositor::CompositorState,
+        fractional_scale_manager: Option<&FractionalScalingManager>,
+        viewport: Option<&ViewporterState>,
+        layer_state: &mut LayerShell,
+        qh: &QueueHandle<GlobalState>,
+    ) {
+        let Some(connection) = self.connection.clone() else {
+            return;
+        };
+        // a panel on all outputs is mirrored once, from the first output that isn't
+        // the target
+        let wanted = self
+            .space_list
+            .iter()
+            .filter(|s| s.mirror_of.is_none())
+            .filter_map(|s| {
+                let output = s.config.mirror_to.as_ref()?;
+                if s.output.as_ref().is_some_and(|o| &o.1.name() == output) {
+                    return None;
+                }
+                Some((s.config.clone(), output.clone()))
+            })
+            .unique_by(|(config, _)| config.name.clone())
+            .collect_vec();
+        self.space_list.retain(|s| {
+            let Some(source) = s.mirror_of.as_ref() else {
+                return true;
+            };
+            wanted.iter().any(|(config, output)| {
+                config == source && s.config.output == CosmicPanelOuput::Name(output.clone())
+            })
+        });
+
+        for (source, output_name) in wanted {
+            if self.space_list.iter().any(|s| s.mirror_of.as_ref() == Some(&source)) {
+                continue;
+            }
+            let Some((wl_output, output, info)) =
+                self.outputs.iter().find(|(_, o, _)| o.name() == output_name).cloned()
+            else {
+                continue;
+            };
+            let mut config = Self::mirror_config(&source, &output_name);
+            config.apply_orientation_overrides();
+            let mut space = PanelSpace::new(
+                config.clone(),
+                self.c_focused_surface.clone(),
+                self.c_hovered_surface.clone(),
+                self.applet_tx.clone(),
+                self.panel_colors(&config.background),
+                self.s_display.clone().unwrap(),
+                self.security_context_manager.clone(),
+                &connection,
+                self.panel_tx.clone(),
+                Visibility::Visible,
+                self.loop_handle.clone(),
+            );
+            space.mirror_of = Some(source);
+            if let Some(s_display) = self.s_display.as_ref() {
+                space.set_display_handle(s_display.clone());
+            }
+            if let Err(err) = space.new_output(
+                compositor_state,
+                fractional_scale_manager,
+                viewport,
+                layer_state,
+                &connection,
+                qh,
+                Some(wl_output),
+                Some(output),
+                Some(info),
+            ) {
+                error!("Failed to create mirror of {} on {}: {}", config.name, output_name, err);
+            } else {
+                self.space_list.push(space);
+            }
+        }
+    }
+
+    /// environment the named applet of the named panel was last spawned with
+    pub fn applet_env(&self, panel: &str, applet: &str) -> Option<Vec<(String, String)>> {
+        self.space_list.iter().filter(|s| s.config.name == panel).find_map(|s| s.applet_env(applet))
+    }
+
+    /// set the additional environment variables of the named applet of the
+    /// named panel, which are used the next time it is spawned
+    pub fn set_applet_env(&mut self, panel: &str, applet: &str, env: Vec<(String, String)>) {
+        for s in self.space_list.iter_mut().filter(|s| s.config.name == panel) {
+            s.set_applet_env(applet, env.clone());
+        }
+        if let Some(config) = self.config.config_list.iter_mut().find(|c| c.name == panel) {
+            if env.is_empty() {
+                config.applet_env.remove(applet);
+            } else {
+                config.applet_env.insert(applet.to_string(), env);
+            }
+            if let Err(err) = CosmicPanelConfig::cosmic_config(panel)
+                .and_then(|c| c.set("applet_env", &config.applet_env))
+            {
+                error!("Failed to persist the environment of applet {}: {:?}", applet, err);
+            }
+        }
+    }
+
+    /// start revealing the hidden panel the swipe began on
+    pub fn swipe_begin(&mut self, surface: &WlSurface) {
+        for space in &mut self.space_list {
+            space.reveal_swipe_begin(surface);
+        }
+    }
+
+    pub fn swipe_update(&mut self, dx: f64, dy: f64) {
+        for space in &mut self.space_list {
+            space.reveal_swipe_update(dx, dy);
+        }
+    }
+
+    pub fn swipe_end(&mut self, cancelled: bool) {
+        for space in &mut self.space_list {
+            space.reveal_swipe_end(cancelled);
+        }
+    }
+
+    /// the output used by panels configured for the active output
+    ///
+    /// This is the output which last had the focus, or the configured primary
+    /// output before any output had it, otherwise the first enumerated output.
+    pub(crate) fn active_output(&self) -> Option<&(WlOutput, Output, OutputInfo)> {
+        let find = |name: &String| self.outputs.iter().find(|(_, o, _)| &o.name() == name);
+        self.focused_output
+            .as_ref()
+            .and_then(find)
+            .or_else(|| self.config.primary_output.as_ref().and_then(find))
+            .or_else(|| self.outputs.first())
+    }
+
+    /// the keyboard or pointer focus moved to an output, so the panels
+    /// configured for the active output follow it
+    pub(crate) fn set_focused_output(&mut self, output: &WlOutput) {
+        let Some((_, output, _)) = self.outputs.iter().find(|(o, ..)| o == output) else {
+            return;
+        };
+        let name = output.name();
+        if self.focused_output.as_ref() == Some(&name) {
+            return;
+        }
+        self.focused_output = Some(name);
+        self.relocate_active_panels();
+    }
+
+    /// the output of the panel with the given layer surface
+    pub(crate) fn output_of_surface(&self, surface: &WlSurface) -> Option<WlOutput> {
+        self.space_list
+            .iter()
+            .find(|s| s.layer.as_ref().is_some_and(|l| l.wl_surface() == surface))
+            .and_then(|s| s.output.as_ref().map(|o| o.0.clone()))
+    }
+
+    /// set the primary output, which panels configured for the active output
+    /// use until an output has the focus
+    pub fn set_primary_output(&mut self, name: Option<String>) {
+        if self.config.primary_output == name {
+            return;
+        }
+        self.config.primary_output = name;
+        self.relocate_active_panels();
+        self.relocate_external_panels();
+    }
+
+    /// remove the panels shown on external outputs only from the primary output
+    /// and restore them on the other outputs
+    pub(crate) fn relocate_external_panels(&mut self) {
+        let config = &self.config;
+        self.space_list.retain(|s| {
+            !s.config.external_only
+                || !s.output.as_ref().is_some_and(|(_, o, _)| config.is_primary_output(&o.name()))
+        });
+        for c in self
+            .config
+            .config_list
+            .iter()
+            .filter(|c| c.external_only && matches!(c.output, CosmicPanelOuput::All))
+        {
+            for (wl_output, output, _) in &self.outputs {
+                if self.config.is_primary_output(&output.name())
+                    || self.space_list.iter().any(|s| {
+                        s.config.name == c.name
+                            && s.output.as_ref().map(|o| &o.0) == Some(wl_output)
+                    })
+                {
+                    continue;
+                }
+                _ = self.panel_tx.send(PanelCalloopMsg::RestartSpace(c.clone(), wl_output.clone()));
+            }
+        }
+    }
+
+    /// the output a panel configured for the active output is shown on
+    ///
+    /// A panel excluded from the active output returns to its home output, or
+    /// else to the first output it is not excluded from.
+    pub(crate) fn active_output_for(
+        &self,
+        config: &CosmicPanelConfig,
+    ) -> Option<&(WlOutput, Output, OutputInfo)> {
+        let allowed = |(_, o, _): &&(WlOutput, Output, OutputInfo)| {
+            !config.exclude_outputs.contains(&o.name())
+        };
+        self.active_output()
+            .filter(allowed)
+            .or_else(|| {
+                config
+                    .home_output
+                    .as_ref()
+                    .and_then(|home| self.outputs.iter().find(|(_, o, _)| &o.name() == home))
+                    .filter(allowed)
+            })
+            .or_else(|| self.outputs.iter().find(allowed))
+    }
+
+    /// recreate the panels configured for the active output which are not on
+    /// the output resolved for them
+    pub(crate) fn relocate_active_panels(&self) {
+        for c in
+            self.config.config_list.iter().filter(|c| matches!(c.output, CosmicPanelOuput::Active))
+        {
+            let Some((wl_output, ..)) = self.active_output_for(c) else {
+                continue;
+            };
+            if self.space_list.iter().any(|s| {
+                s.config.name == c.name && s.output.as_ref().map(|o| &o.0) == Some(wl_output)
+            }) {
+                continue;
+            }
+            _ = self.panel_tx.send(PanelCalloopMsg::RestartSpace(c.clone(), wl_output.clone()));
+        }
+    }
+
     pub fn stacked_spaces_by_priority(
         &mut self,
         output_id: &str,
@@ -495,7 +1243,7 @@ impl SpaceContainer {
                     && &s.config.anchor == &anchor
             })
             .collect::<Vec<_>>();
-        if spaces.last().is_some_and(|s| s.config.autohide.is_none()) {
+        if spaces.last().is_some_and(|s| s.config.autohide().is_none()) {
             spaces.remove(spaces.len() - 1);
         }
         spaces.sort_by(|a, b| a.config.get_priority().cmp(&b.config.get_priority()));
@@ -503,6 +1251,123 @@ impl SpaceContainer {
         spaces
     }
 
+    /// run the action of the start icon of a panel
+    pub fn activate_start_button(&mut self, panel_id: usize) {
+        if let Some(space) = self.space_list.iter_mut().find(|s| s.space.id() == panel_id) {
+            space.activate_start_button();
+        }
+    }
+
+    /// highlight the drop targets of the panel with the given layer surface
+    /// while something is dragged over it, clearing the highlights of the
+    /// other panels
+    pub fn set_drag_target(&mut self, surface: Option<&WlSurface>) {
+        for space in &mut self.space_list {
+            let drag_target = surface.is_some_and(|surface| {
+                space.layer.as_ref().is_some_and(|l| l.wl_surface() == surface)
+            });
+            space.set_drag_target(drag_target);
+        }
+    }
+
+    /// set the count shown in the badge of an applet in every panel which has
+    /// it
+    pub fn set_applet_badge(&mut self, applet: &str, count: u32) {
+        for space in &mut self.space_list {
+            space.set_applet_badge(applet, count);
+        }
+    }
+
+    /// give the keyboard focus to a panel, by name, to navigate among its
+    /// applets
+    ///
+    /// A panel on the active output is preferred. An empty name focuses the
+    /// first panel.
+    pub fn focus_panel(&mut self, name: &str) {
+        let active = self.active_output().map(|(o, ..)| o.clone());
+        let matches = |s: &PanelSpace| name.is_empty() || s.config.name == name;
+        let i = self
+            .space_list
+            .iter()
+            .position(|s| matches(s) && s.output.as_ref().map(|o| &o.0) == active.as_ref())
+            .or_else(|| self.space_list.iter().position(matches));
+        let Some(i) = i else {
+            error!("No panel named {} to focus", name);
+            return;
+        };
+        for (j, space) in self.space_list.iter_mut().enumerate() {
+            if j == i {
+                space.start_keyboard_navigation();
+            } else {
+                space.stop_keyboard_navigation();
+            }
+        }
+    }
+
+    /// handle a key pressed on a panel which is navigating among its applets,
+    /// returning the pointer events which activate the focused applet
+    ///
+    /// Returns `None` if the key is not used for navigation.
+    pub fn keyboard_nav_key(
+        &mut self,
+        surface: &WlSurface,
+        keysym: Keysym,
+    ) -> Option<Vec<PointerEvent>> {
+        self.space_list
+            .iter_mut()
+            .find(|s| s.layer.as_ref().is_some_and(|l| l.wl_surface() == surface))?
+            .keyboard_nav_key(keysym)
+    }
+
+    /// render each panel offscreen and save it to the directory as a PNG named
+    /// after the panel and its output, returning the paths of the saved files
+    #[cfg(feature = "headless")]
+    pub fn save_offscreen_renders(&mut self, dir: &std::path::Path) -> Vec<PathBuf> {
+        let Some(renderer) = self.renderer.as_mut() else {
+            error!("No renderer to render the panels offscreen");
+            return Vec::new();
+        };
+        let mut saved = Vec::new();
+        for space in &mut self.space_list {
+            let output = space.output.as_ref().map(|o| o.1.name()).unwrap_or_default();
+            let path = dir.join(format!("{}-{}.png", space.config.name, output));
+            match space.render_offscreen(renderer).and_then(|image| Ok(image.save(&path)?)) {
+                Ok(()) => saved.push(path),
+                Err(err) => {
+                    error!("Failed to save the render of panel {}: {:?}", space.config.name, err)
+                },
+            }
+        }
+        saved
+    }
+
+    /// show a system-wide progress from 0.0 to 1.0 along the edge of every
+    /// panel, or stop showing it
+    pub fn set_progress(&mut self, progress: Option<f32>) {
+        for space in &mut self.space_list {
+            space.set_progress(progress);
+        }
+    }
+
+    /// the cursor shown over a click zone of the panel with the surface
+    pub fn click_zone_cursor(
+        &self,
+        surface: &WlSurface,
+        pos: Point<i32, Logical>,
+    ) -> Option<CursorIcon> {
+        self.space_list
+            .iter()
+            .find(|s| s.layer.as_ref().is_some_and(|l| l.wl_surface() == surface))
+            .and_then(|s| s.click_zone_cursor(pos))
+    }
+
+    /// collapse or expand the collapsible applets of a panel
+    pub fn toggle_collapsed(&mut self, panel_id: usize) {
+        if let Some(space) = self.space_list.iter_mut().find(|s| s.space.id() == panel_id) {
+            space.set_collapsed(!space.collapsed);
+        }
+    }
+
     pub fn toggle_overflow_popup(
         &mut self,
         panel_id: usize,
@@ -534,6 +1399,56 @@ impl SpaceContainer {
         }
     }
 
+    /// the panel with the given name, preferring the one on the active output
+    fn named_panel_mut(&mut self, name: &str) -> Option<&mut PanelSpace> {
+        let active = self.active_output().map(|(o, ..)| o.clone());
+        let i = self
+            .space_list
+            .iter()
+            .position(|s| {
+                s.config.name == name && s.output.as_ref().map(|o| &o.0) == active.as_ref()
+            })
+            .or_else(|| self.space_list.iter().position(|s| s.config.name == name))?;
+        self.space_list.get_mut(i)
+    }
+
+    /// open the overflow popup of a section of a panel, by name, without a
+    /// button press
+    pub fn open_overflow_popup(
+        &mut self,
+        name: &str,
+        section: OverflowSection,
+        compositor_state: &sctk::compositor::CompositorState,
+        fractional_scale_manager: Option<&FractionalScalingManager>,
+        viewport: Option<&ViewporterState>,
+        qh: &QueueHandle<GlobalState>,
+        xdg_shell_state: &mut sctk::shell::xdg::XdgShell,
+        seat: (u32, WlSeat),
+    ) {
+        let Some(space) = self.named_panel_mut(name) else {
+            error!("No panel named {} to open the overflow popup of", name);
+            return;
+        };
+        if let Err(err) = space.open_overflow_popup(
+            section,
+            compositor_state,
+            fractional_scale_manager,
+            viewport,
+            qh,
+            xdg_shell_state,
+            seat,
+        ) {
+            error!("Failed to open overflow popup: {}", err);
+        }
+    }
+
+    /// close the overflow popup of a panel, by name
+    pub fn close_overflow_popup(&mut self, name: &str) {
+        if let Some(space) = self.named_panel_mut(name) {
+            space.close_overflow_popup();
+        }
+    }
+
     pub fn iced_request_redraw(&mut self, panel_id: usize) {
         for space in &mut self.space_list {
             if space.space.id() == panel_id {
@@ -549,6 +1464,20 @@ impl SpaceContainer {
         }
     }
 
+    /// the applet rectangles currently used as minimize targets, by output name
+    pub fn minimized_applets(&self) -> impl Iterator<Item = (&str, &MinimizeApplet)> {
+        self.minimized_applets.iter().map(|(output, info)| (output.as_str(), info))
+    }
+
+    /// whether a toplevel with the given app id should not receive a minimize
+    /// rectangle from the panel with the given layer surface
+    pub fn minimize_excluded(&self, surface: &WlSurface, app_id: &str) -> bool {
+        self.space_list
+            .iter()
+            .find(|s| s.layer.as_ref().is_some_and(|l| l.wl_surface() == surface))
+            .is_some_and(|s| s.config.minimize_exclude.iter().any(|id| id == app_id))
+    }
+
     pub fn cleanup(&mut self) {
         for space in &mut self.space_list {
             space.cleanup();
@@ -27,6 +27,7 @@ impl ToplevelInfoSpace for SpaceContainer {
     ) {
         self.toplevels.push((toplevel.clone(), info.clone()));
         self.apply_toplevel_changes();
+        self.follow_activated_toplevel(info);
         _ = self.panel_tx.send(crate::PanelCalloopMsg::UpdateToplevel(toplevel.clone()));
 
         let is_maximized = info.state.contains(&zcosmic_toplevel_handle_v1::State::Maximized);
@@ -51,6 +52,7 @@ impl ToplevelInfoSpace for SpaceContainer {
         }
         _ = self.panel_tx.send(crate::PanelCalloopMsg::UpdateToplevel(toplevel.clone()));
         self.apply_toplevel_changes();
+        self.follow_activated_toplevel(info);
 
         let is_maximized = info.state.contains(&zcosmic_toplevel_handle_v1::State::Maximized);
 
@@ -92,6 +94,16 @@ impl ToplevelManagerSpace for SpaceContainer {
 }
 
 impl SpaceContainer {
+    /// move the focused output to the output of a toplevel which was activated
+    fn follow_activated_toplevel(&mut self, info: &ToplevelInfo) {
+        if !info.state.contains(&zcosmic_toplevel_handle_v1::State::Activated) {
+            return;
+        }
+        if let Some(output) = info.output.iter().next() {
+            self.set_focused_output(output);
+        }
+    }
+
     fn add_maximized(
         &mut self,
         toplevel: &zcosmic_toplevel_handle_v1::ZcosmicToplevelHandleV1,
@@ -177,7 +189,7 @@ impl SpaceContainer {
                 let mut additional_gap = 0;
                 for s in self.stacked_spaces_by_priority(&name, anchor) {
                     s.set_additional_gap(additional_gap);
-                    if s.config.autohide.is_some()
+                    if s.config.autohide().is_some()
                         && (!has_toplevel
                             || s.c_focused_surface.borrow().iter().any(|c| {
                                 matches!(c.2, FocusStatus::Focused)
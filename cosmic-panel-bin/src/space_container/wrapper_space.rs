@@ -86,22 +86,19 @@ impl WrapperSpace for SpaceContainer {
                 .iter()
                 .filter_map(|config| {
                     if matches!(config.output, CosmicPanelOuput::Active) {
+                        let mut config = config.clone();
+                        config.apply_orientation_overrides();
                         let mut s = PanelSpace::new(
                             config.clone(),
                             self.c_focused_surface.clone(),
                             self.c_hovered_surface.clone(),
                             self.applet_tx.clone(),
-                            match config.background {
-                                CosmicPanelBackground::ThemeDefault
-                                | CosmicPanelBackground::Color(_) => self.cur_theme(),
-                                CosmicPanelBackground::Dark => self.dark_theme.clone(),
-                                CosmicPanelBackground::Light => self.light_theme.clone(),
-                            },
+                            self.panel_colors(&config.background),
                             self.s_display.clone().unwrap(),
                             self.security_context_manager.clone(),
                             conn,
                             self.panel_tx.clone(),
-                            if config.autohide.is_some() {
+                            if config.autohide().is_some() {
                                 Visibility::Hidden
                             } else {
                                 Visibility::Visible
@@ -173,10 +170,16 @@ impl WrapperSpace for SpaceContainer {
             None => anyhow::bail!("Output missing name"),
         };
         self.outputs.push((c_output.clone(), s_output.clone(), output_info.clone()));
+        if self.config.config_list.iter().any(|c| {
+            matches!(c.output, CosmicPanelOuput::Active)
+                && self.active_output_for(c).is_some_and(|(o, ..)| o == &c_output)
+        }) {
+            self.relocate_active_panels();
+        }
 
-        let cur = self.cur_theme();
-        let dark = self.dark_theme.clone();
-        let light = self.light_theme.clone();
+        let cur = self.panel_colors(&CosmicPanelBackground::ThemeDefault);
+        let dark = self.panel_colors(&CosmicPanelBackground::Dark);
+        let light = self.panel_colors(&CosmicPanelBackground::Light);
         // TODO error handling
         // create the spaces that are configured to use this output, including spaces
         // configured for All
@@ -185,7 +188,10 @@ impl WrapperSpace for SpaceContainer {
             .configs_for_output(&output_name)
             .into_iter()
             .filter_map(|config| {
-                let visible = if config.autohide.is_some() {
+                let mut config = config.clone();
+                config.apply_anchor_override(&output_name);
+                config.apply_orientation_overrides();
+                let visible = if config.autohide().is_some() {
                     Visibility::Hidden
                 } else {
                     Visibility::Visible
@@ -311,6 +317,7 @@ impl WrapperSpace for SpaceContainer {
         if self.maximized_outputs().iter().any(|o| o == &c_output) {
             self.apply_maximized(&c_output, true);
         }
+        self.update_mirrors(compositor_state, fractional_scale_manager, viewport, layer_state, qh);
         self.apply_toplevel_changes();
 
         Ok(())
@@ -348,6 +355,7 @@ impl WrapperSpace for SpaceContainer {
     ) -> anyhow::Result<()> {
         // add popup to the space with a client that matches the window
         let p_client = s_surface.wl_surface().client().map(|c| c.id());
+        let output_geometries = self.output_geometries();
 
         if let Some(space) = self.space_list.iter_mut().find(|space| {
             space
@@ -359,6 +367,7 @@ impl WrapperSpace for SpaceContainer {
                 .chain(space.clients_right.lock().unwrap().iter())
                 .any(|c| Some(c.client.id()) == p_client)
         }) {
+            space.output_geometries = output_geometries;
             space.add_popup(
                 compositor_state,
                 fractional_scale_manager,
@@ -383,6 +392,7 @@ impl WrapperSpace for SpaceContainer {
     ) -> anyhow::Result<()> {
         // add popup to the space with a client that matches the window
         let p_client = popup.wl_surface().client().map(|c| c.id());
+        let output_geometries = self.output_geometries();
 
         if let Some(space) = self.space_list.iter_mut().find(|space| {
             space
@@ -394,6 +404,7 @@ impl WrapperSpace for SpaceContainer {
                 .chain(space.clients_right.lock().unwrap().iter())
                 .any(|c| Some(c.client.id()) == p_client)
         }) {
+            space.output_geometries = output_geometries;
             space.reposition_popup(popup, positioner_state, token)?
         }
         anyhow::bail!("Failed to find popup with matching client id")
@@ -407,6 +418,9 @@ impl WrapperSpace for SpaceContainer {
         time: u32,
         throttle: Option<Duration>,
     ) -> std::time::Instant {
+        self.arbitrate_corners();
+        self.allot_lengths();
+        self.link_mirrors();
         self.space_list
             .iter_mut()
             .fold(None, |mut acc, s| {
@@ -553,7 +567,7 @@ impl WrapperSpace for SpaceContainer {
                 let Some(space_c_wl_surface) = s.layer.as_ref().map(|l| l.wl_surface()) else {
                     continue;
                 };
-                if s.config.autohide.is_none() {
+                if s.config.autohide().is_none() {
                     s.set_additional_gap(additional_gap);
                     continue;
                 }
@@ -624,11 +638,14 @@ impl WrapperSpace for SpaceContainer {
         seat_name: &str,
         surface: c_wl_surface::WlSurface,
     ) -> Option<wl_surface::WlSurface> {
+        if let Some(output) = self.output_of_surface(&surface) {
+            self.set_focused_output(&output);
+        }
         if let Some((popup_space_i, popup_space)) =
             self.space_list.iter_mut().enumerate().find(|(_, s)| !s.popups.is_empty())
         {
             if let Some(p_ret) = popup_space.keyboard_enter(seat_name, surface.clone()) {
-                Some(p_ret);
+                return Some(p_ret);
             }
             self.space_list.iter_mut().enumerate().find_map(|(i, s)| {
                 if i != popup_space_i {
@@ -680,7 +697,7 @@ impl WrapperSpace for SpaceContainer {
                     f.2 = FocusStatus::LastFocused(Instant::now());
                 }
             }
-            if s.config.autohide.is_none() {
+            if s.config.autohide().is_none() {
                 s.set_additional_gap(0);
             }
         }
@@ -692,6 +709,9 @@ impl WrapperSpace for SpaceContainer {
         seat_name: &str,
         c_wl_surface: c_wl_surface::WlSurface,
     ) -> Option<(ServerPointerFocus, Vec<PointerEvent>)> {
+        if let Some(output) = self.output_of_surface(&c_wl_surface) {
+            self.set_focused_output(&output);
+        }
         if let Some((popup_space_i, popup_space)) =
             self.space_list.iter_mut().enumerate().find(|(_, s)| !s.popups.is_empty())
         {
@@ -780,6 +800,14 @@ impl WrapperSpace for SpaceContainer {
     ) -> anyhow::Result<()> {
         self.outputs.retain(|o| o.0 != c_output);
         self.space_list.retain(|s| s.output.as_ref().map(|o| &o.0) != Some(&c_output));
+        // mirrors of panels on the output are dropped with them
+        let sources = self
+            .space_list
+            .iter()
+            .filter(|s| s.mirror_of.is_none())
+            .map(|s| s.config.clone())
+            .collect_vec();
+        self.space_list.retain(|s| s.mirror_of.as_ref().map_or(true, |c| sources.contains(c)));
         Ok(())
     }
 
@@ -789,12 +817,19 @@ impl WrapperSpace for SpaceContainer {
         s_output: Output,
         info: OutputInfo,
     ) -> anyhow::Result<bool> {
+        let current_mode = |info: &OutputInfo| info.modes.iter().find(|m| m.current).cloned();
+        let mode_changed = self.outputs.iter().find(|o| o.0 == c_output).is_some_and(|o| {
+            o.2.logical_size != info.logical_size || current_mode(&o.2) != current_mode(&info)
+        });
         self.outputs.retain(|o| o.0 != c_output);
         self.outputs.push((c_output.clone(), s_output.clone(), info.clone()));
         let mut found = false;
         for s in &mut self.space_list {
             if s.output.as_ref().map(|o| &o.0) == Some(&c_output) {
                 let _ = s.update_output(c_output.clone(), s_output.clone(), info.clone());
+                if mode_changed {
+                    s.output_mode_changed();
+                }
                 found = true;
             }
         }
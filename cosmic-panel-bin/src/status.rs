@@ -0,0 +1,206 @@
+// Session bus interface which reports the state of the panels, and changes it
+// for settings and scripts
+
+use std::time::Duration;
+
+use smithay::reexports::calloop::channel::Sender;
+use tokio::sync::oneshot;
+use zbus::{connection::Builder, fdo, interface};
+
+use crate::{
+    space::{Alignment, OverflowSection},
+    PanelCalloopMsg,
+};
+
+pub const STATUS_PATH: &str = "/com/system76/CosmicPanel/Status";
+
+struct Status {
+    panel_tx: Sender<PanelCalloopMsg>,
+}
+
+#[interface(name = "com.system76.CosmicPanel.Status")]
+impl Status {
+    /// the applets which crashed too often to be restarted, as the name and
+    /// output of their panel, and the name of the applet
+    ///
+    /// The output is empty if the panel has none. Instances after the first of
+    /// an applet are named as for badges, e.g. `com.system76.CosmicAppList#1`.
+    async fn crashed_applets(&self) -> fdo::Result<Vec<(String, String, String)>> {
+        let (tx, rx) = oneshot::channel();
+        self.panel_tx
+            .send(PanelCalloopMsg::CrashedApplets(tx))
+            .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+        rx.await.map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// the visibility of each panel, as its name and output, and `Visible`,
+    /// `Hidden` or `Transitioning`
+    ///
+    /// The output is empty if the panel has none.
+    #[zbus(property(emits_changed_signal = "false"))]
+    async fn visibility(&self) -> fdo::Result<Vec<(String, String, String)>> {
+        let (tx, rx) = oneshot::channel();
+        self.panel_tx
+            .send(PanelCalloopMsg::PanelVisibility(tx))
+            .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+        rx.await.map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// the geometry of each panel, as its name, output and anchor, its logical
+    /// width and height, its gap to the anchored edge and the exclusive zone
+    /// set on its layer surface
+    ///
+    /// The output is empty if the panel has none.
+    #[zbus(property(emits_changed_signal = "false"))]
+    async fn geometry(&self) -> fdo::Result<Vec<(String, String, String, (i32, i32), i32, i32)>> {
+        let (tx, rx) = oneshot::channel();
+        self.panel_tx
+            .send(PanelCalloopMsg::PanelGeometry(tx))
+            .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+        rx.await.map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// the applet rectangles which windows minimize to, as the output, the
+    /// position and size of the rectangle, the priority of the applet and the
+    /// protocol id of its surface
+    #[zbus(property(emits_changed_signal = "false"))]
+    async fn minimize_targets(&self) -> fdo::Result<Vec<(String, (i32, i32, i32, i32), i32, u32)>> {
+        let (tx, rx) = oneshot::channel();
+        self.panel_tx
+            .send(PanelCalloopMsg::MinimizeTargets(tx))
+            .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+        rx.await.map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// force the named panel to stay visible, or let it hide again
+    async fn pin(&self, name: String, pinned: bool) -> fdo::Result<()> {
+        self.panel_tx
+            .send(PanelCalloopMsg::PinPanel { name, pinned })
+            .map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// override the opacity of the background of the named panel, from 0.0 to
+    /// 1.0, until the panel is restored
+    ///
+    /// The override is remembered across restarts if the panel persists its
+    /// state.
+    async fn set_opacity(&self, name: String, opacity: f64) -> fdo::Result<()> {
+        if !(0.0..=1.0).contains(&opacity) {
+            return Err(fdo::Error::InvalidArgs(format!("Opacity {} is out of range", opacity)));
+        }
+        self.panel_tx
+            .send(PanelCalloopMsg::PanelOpacity { name, opacity: Some(opacity as f32) })
+            .map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// return the named panel to its configured opacity
+    async fn restore_opacity(&self, name: String) -> fdo::Result<()> {
+        self.panel_tx
+            .send(PanelCalloopMsg::PanelOpacity { name, opacity: None })
+            .map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// the environment the applet of the named panel was last spawned with
+    async fn applet_env(&self, name: String, applet: String) -> fdo::Result<Vec<(String, String)>> {
+        let (tx, rx) = oneshot::channel();
+        self.panel_tx
+            .send(PanelCalloopMsg::AppletEnv { name, applet: applet.clone(), tx })
+            .map_err(|err| fdo::Error::Failed(err.to_string()))?;
+        rx.await
+            .map_err(|err| fdo::Error::Failed(err.to_string()))?
+            .ok_or_else(|| fdo::Error::Failed(format!("Applet {} was not spawned", applet)))
+    }
+
+    /// set the additional environment of the applet of the named panel, which
+    /// is saved to its config and used the next time the applet is spawned
+    ///
+    /// An empty environment removes the additional environment.
+    async fn set_applet_env(
+        &self,
+        name: String,
+        applet: String,
+        env: Vec<(String, String)>,
+    ) -> fdo::Result<()> {
+        self.panel_tx
+            .send(PanelCalloopMsg::SetAppletEnv { name, applet, env })
+            .map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// reset the named panel to the default configuration, keeping its name
+    /// and output
+    async fn reset_panel(&self, name: String) -> fdo::Result<()> {
+        self.panel_tx
+            .send(PanelCalloopMsg::ResetPanel(name))
+            .map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// hide or show an applet of the named panel while keeping it running, or
+    /// of every panel if the name is empty
+    async fn set_applet_visible(
+        &self,
+        name: String,
+        applet: String,
+        visible: bool,
+    ) -> fdo::Result<()> {
+        self.panel_tx
+            .send(PanelCalloopMsg::AppletVisible { name, applet, visible })
+            .map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// move an applet of the named panel to the index of a section, which is
+    /// `left`, `center` or `right`, and keep it there
+    async fn move_applet(
+        &self,
+        name: String,
+        applet: String,
+        section: String,
+        index: u32,
+    ) -> fdo::Result<()> {
+        let section = match section.as_str() {
+            "left" => Alignment::Left,
+            "center" => Alignment::Center,
+            "right" => Alignment::Right,
+            _ => return Err(fdo::Error::InvalidArgs(format!("Unknown section {}", section))),
+        };
+        self.panel_tx
+            .send(PanelCalloopMsg::MoveApplet { name, applet, section, index: index as usize })
+            .map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// raise every panel above windows for the duration in milliseconds, e.g.
+    /// for a gesture which reveals the panels
+    async fn boost(&self, duration_ms: u32) -> fdo::Result<()> {
+        self.panel_tx
+            .send(PanelCalloopMsg::BoostPanels(Duration::from_millis(duration_ms.into())))
+            .map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// open the overflow popup of a section of the named panel, which is
+    /// `left`, `center` or `right`
+    async fn open_overflow(&self, name: String, section: String) -> fdo::Result<()> {
+        let section = match section.as_str() {
+            "left" => OverflowSection::Left,
+            "center" => OverflowSection::Center,
+            "right" => OverflowSection::Right,
+            _ => return Err(fdo::Error::InvalidArgs(format!("Unknown section {}", section))),
+        };
+        self.panel_tx
+            .send(PanelCalloopMsg::OverflowPopup { name, section: Some(section) })
+            .map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+
+    /// close the overflow popup of the named panel
+    async fn close_overflow(&self, name: String) -> fdo::Result<()> {
+        self.panel_tx
+            .send(PanelCalloopMsg::OverflowPopup { name, section: None })
+            .map_err(|err| fdo::Error::Failed(err.to_string()))
+    }
+}
+
+/// serve the status interface on the connection of the panel
+pub fn serve_status(
+    builder: Builder<'_>,
+    panel_tx: Sender<PanelCalloopMsg>,
+) -> zbus::Result<Builder<'_>> {
+    builder.serve_at(STATUS_PATH, Status { panel_tx })
+}
@@ -100,6 +100,7 @@ impl DataDeviceHandler for GlobalState {
             Some(offer) => offer,
             None => return,
         };
+        self.space.set_drag_target(Some(surface));
 
         {
             let mut c_hovered_surface = self.client_state.hovered_surface.borrow_mut();
@@ -169,6 +170,7 @@ impl DataDeviceHandler for GlobalState {
             Some(sp) => sp,
             None => return,
         };
+        self.space.set_drag_target(None);
         let c_ptr = seat.client.ptr.as_ref().map(|p| p.pointer().clone());
         let s_ptr = seat.server.seat.get_pointer();
         let surface = if let Some(f) =
@@ -258,6 +260,7 @@ impl DataDeviceHandler for GlobalState {
         data_device: &WlDataDevice,
     ) {
         // treat it as pointer button release
+        self.space.set_drag_target(None);
         let seat = match self
             .server_state
             .seats
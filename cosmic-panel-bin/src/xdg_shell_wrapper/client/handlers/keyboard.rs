@@ -113,7 +113,7 @@ impl KeyboardHandler for GlobalState {
 
     fn press_key(
         &mut self,
-        _conn: &sctk::reexports::client::Connection,
+        conn: &sctk::reexports::client::Connection,
         _qh: &sctk::reexports::client::QueueHandle<Self>,
         keyboard: &sctk::reexports::client::protocol::wl_keyboard::WlKeyboard,
         serial: u32,
@@ -149,6 +149,24 @@ impl KeyboardHandler for GlobalState {
             })
         };
 
+        // keys navigating among the applets of a panel are not forwarded
+        if let Some(mut events) =
+            c_kbd_focus.as_ref().and_then(|s| self.space.keyboard_nav_key(s, event.keysym))
+        {
+            let ptr = self
+                .server_state
+                .seats
+                .iter()
+                .find(|s| s.name == seat_name)
+                .and_then(|s| s.client.ptr.as_ref())
+                .map(|p| p.pointer().clone());
+            if let Some(ptr) = ptr.filter(|_| !events.is_empty()) {
+                self.update_generated_event_serial(&mut events);
+                self.pointer_frame_inner(conn, &ptr, &events);
+            }
+            return;
+        }
+
         if let Some(c_focus) = c_kbd_focus {
             self.client_state.last_key_pressed.push((
                 seat_name,
@@ -19,6 +19,7 @@ pub mod layer_shell;
 pub mod output;
 pub mod overlap;
 pub mod pointer;
+pub mod pointer_gestures;
 pub mod seat;
 pub mod shell;
 pub mod toplevel;
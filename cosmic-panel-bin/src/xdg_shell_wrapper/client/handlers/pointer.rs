@@ -32,7 +32,7 @@ impl PointerHandler for GlobalState {
 }
 
 impl GlobalState {
-    fn update_generated_event_serial(&self, events: &mut Vec<PointerEvent>) {
+    pub(crate) fn update_generated_event_serial(&self, events: &mut Vec<PointerEvent>) {
         for e in events {
             match &mut e.kind {
                 sctk::seat::pointer::PointerEventKind::Enter { serial } => {
@@ -287,11 +287,18 @@ impl GlobalState {
                             },
                         );
                         ptr.frame(self);
+                        // click zones of the panel show their own cursor
+                        let icon = self
+                            .space
+                            .click_zone_cursor(
+                                &e.surface,
+                                (surface_x as i32, surface_y as i32).into(),
+                            )
+                            .unwrap_or(sctk::seat::pointer::CursorIcon::Default);
                         if let Some(themed_pointer) =
                             &self.server_state.seats[seat_index].client.ptr
                         {
-                            _ = themed_pointer
-                                .set_cursor(conn, sctk::seat::pointer::CursorIcon::Default);
+                            _ = themed_pointer.set_cursor(conn, icon);
                         }
                     }
                 },
@@ -325,6 +332,9 @@ impl GlobalState {
                         continue;
                     }
 
+                    if self.space.intercept_button(&seat_name, true) {
+                        continue;
+                    }
                     let s = self.space.handle_button(&seat_name, true);
 
                     kbd.set_focus(self, s, SERIAL_COUNTER.next_serial());
@@ -369,6 +379,9 @@ impl GlobalState {
                         continue;
                     }
 
+                    if self.space.intercept_button(&seat_name, false) {
+                        continue;
+                    }
                     let s = self.space.handle_button(&seat_name, false);
                     kbd.set_focus(self, s, SERIAL_COUNTER.next_serial());
 
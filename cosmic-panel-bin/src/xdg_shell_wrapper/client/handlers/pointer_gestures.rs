@@ -0,0 +1,80 @@
+use sctk::{
+    globals::GlobalData,
+    reexports::{
+        client::{
+            delegate_dispatch,
+            globals::{BindError, GlobalList},
+            protocol::wl_pointer::WlPointer,
+            Connection, Dispatch, Proxy, QueueHandle,
+        },
+        protocols::wp::pointer_gestures::zv1::client::{
+            zwp_pointer_gesture_swipe_v1::{self, ZwpPointerGestureSwipeV1},
+            zwp_pointer_gestures_v1::ZwpPointerGesturesV1,
+        },
+    },
+};
+
+use crate::xdg_shell_wrapper::shared_state::GlobalState;
+
+#[derive(Debug, Clone)]
+pub struct PointerGesturesState {
+    pub(crate) gestures: ZwpPointerGesturesV1,
+}
+
+impl PointerGesturesState {
+    pub fn bind(
+        globals: &GlobalList,
+        qh: &QueueHandle<GlobalState>,
+    ) -> Result<PointerGesturesState, BindError> {
+        let gestures = globals.bind(qh, 1..=1, GlobalData)?;
+        Ok(PointerGesturesState { gestures })
+    }
+
+    /// get the swipe gesture object for a pointer
+    pub fn swipe(
+        &self,
+        pointer: &WlPointer,
+        qh: &QueueHandle<GlobalState>,
+    ) -> ZwpPointerGestureSwipeV1 {
+        self.gestures.get_swipe_gesture(pointer, qh, ())
+    }
+}
+
+impl Dispatch<ZwpPointerGesturesV1, GlobalData, GlobalState> for PointerGesturesState {
+    fn event(
+        _: &mut GlobalState,
+        _: &ZwpPointerGesturesV1,
+        _: <ZwpPointerGesturesV1 as Proxy>::Event,
+        _: &GlobalData,
+        _: &Connection,
+        _: &QueueHandle<GlobalState>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwpPointerGestureSwipeV1, (), GlobalState> for PointerGesturesState {
+    fn event(
+        state: &mut GlobalState,
+        _: &ZwpPointerGestureSwipeV1,
+        event: <ZwpPointerGestureSwipeV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<GlobalState>,
+    ) {
+        match event {
+            zwp_pointer_gesture_swipe_v1::Event::Begin { surface, .. } => {
+                state.space.swipe_begin(&surface);
+            },
+            zwp_pointer_gesture_swipe_v1::Event::Update { dx, dy, .. } => {
+                state.space.swipe_update(dx, dy);
+            },
+            zwp_pointer_gesture_swipe_v1::Event::End { cancelled, .. } => {
+                state.space.swipe_end(cancelled != 0);
+            },
+            _ => {},
+        }
+    }
+}
+
+delegate_dispatch!(GlobalState: [ZwpPointerGesturesV1: GlobalData] => PointerGesturesState);
+delegate_dispatch!(GlobalState: [ZwpPointerGestureSwipeV1: ()] => PointerGesturesState);
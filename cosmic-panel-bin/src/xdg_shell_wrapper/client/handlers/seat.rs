@@ -51,6 +51,10 @@ impl SeatHandler for GlobalState {
             } else {
                 None
             };
+            let swipe = ptr
+                .as_ref()
+                .zip(self.client_state.pointer_gestures.as_ref())
+                .map(|(ptr, gestures)| gestures.swipe(ptr.pointer(), qh));
 
             // A lot of clients bind keyboard and pointer unconditionally once on launch..
             // Initial clients might race the compositor on adding periheral and
@@ -73,6 +77,7 @@ impl SeatHandler for GlobalState {
                     _seat: seat.clone(),
                     kbd,
                     ptr,
+                    swipe,
                     data_device,
                     copy_paste_source: None,
                     dnd_source: None,
@@ -122,6 +127,7 @@ impl SeatHandler for GlobalState {
                     _seat: seat.clone(),
                     kbd: None,
                     ptr: None,
+                    swipe: None,
                     data_device: self.client_state.data_device_manager.get_data_device(qh, &seat),
                     copy_paste_source: None,
                     dnd_source: None,
@@ -163,6 +169,13 @@ impl SeatHandler for GlobalState {
                         self.client_state.compositor_state.create_surface(qh),
                         ThemeSpec::System,
                     ) {
+                        if let Some(gestures) = self.client_state.pointer_gestures.as_ref() {
+                            if let Some(old) =
+                                sp.client.swipe.replace(gestures.swipe(ptr.pointer(), qh))
+                            {
+                                old.destroy();
+                            }
+                        }
                         sp.client.ptr.replace(ptr);
                     }
                 }
@@ -191,6 +204,9 @@ impl SeatHandler for GlobalState {
                 sp.server.seat.remove_keyboard();
             },
             sctk::seat::Capability::Pointer => {
+                if let Some(swipe) = sp.client.swipe.take() {
+                    swipe.destroy();
+                }
                 sp.server.seat.remove_pointer();
             },
             sctk::seat::Capability::Touch => {}, // TODO
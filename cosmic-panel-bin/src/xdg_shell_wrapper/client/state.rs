@@ -74,12 +74,14 @@ use std::{
 use tracing::error;
 use wayland_protocols::wp::{
     fractional_scale::v1::client::wp_fractional_scale_v1::WpFractionalScaleV1,
+    pointer_gestures::zv1::client::zwp_pointer_gesture_swipe_v1::ZwpPointerGestureSwipeV1,
     viewporter::client::wp_viewport::WpViewport,
 };
 
 use super::handlers::{
-    overlap::OverlapNotifyV1, wp_fractional_scaling::FractionalScalingManager,
-    wp_security_context::SecurityContextManager, wp_viewporter::ViewporterState,
+    overlap::OverlapNotifyV1, pointer_gestures::PointerGesturesState,
+    wp_fractional_scaling::FractionalScalingManager, wp_security_context::SecurityContextManager,
+    wp_viewporter::ViewporterState,
 };
 
 #[derive(Debug)]
@@ -87,6 +89,7 @@ pub(crate) struct ClientSeat {
     pub(crate) _seat: WlSeat,
     pub(crate) kbd: Option<wl_keyboard::WlKeyboard>,
     pub(crate) ptr: Option<ThemedPointer>,
+    pub(crate) swipe: Option<ZwpPointerGestureSwipeV1>,
     pub(crate) last_enter: u32,
     pub(crate) last_key_press: (u32, u32),
     pub(crate) last_pointer_press: (u32, u32),
@@ -157,6 +160,8 @@ pub struct ClientState {
     pub security_context_manager: Option<SecurityContextManager>,
     /// overlap notifications subscription
     pub overlap_notify: Option<OverlapNotifyV1>,
+    /// pointer gestures, used to reveal hidden panels with a swipe
+    pub pointer_gestures: Option<PointerGesturesState>,
 
     pub(crate) connection: Connection,
     /// queue handle
@@ -283,6 +288,10 @@ impl ClientState {
         if let Err(err) = &overlap_notify {
             tracing::warn!("Failed to bind to overlap notify {err:?}");
         }
+        let pointer_gestures = PointerGesturesState::bind(&globals, &qh);
+        if let Err(err) = &pointer_gestures {
+            tracing::warn!("Failed to bind to pointer gestures {err:?}");
+        }
 
         let client_state = ClientState {
             focused_surface: space.get_client_focused_surface(),
@@ -302,6 +311,7 @@ impl ClientState {
             data_device_manager: DataDeviceManagerState::bind(&globals, &qh)
                 .expect("data device manager is not available"),
             overlap_notify: overlap_notify.ok(),
+            pointer_gestures: pointer_gestures.ok(),
 
             outputs: Default::default(),
             registry_state,
@@ -3,6 +3,7 @@ use sctk::shell::xdg::XdgPositioner;
 use smithay::{
     delegate_xdg_shell,
     desktop::{PopupKind, Window},
+    input::Seat,
     reexports::{
         wayland_protocols::xdg::shell::server::xdg_toplevel, wayland_server::protocol::wl_seat,
     },
@@ -80,22 +81,22 @@ impl XdgShellHandler for GlobalState {
     ) {
     }
 
-    fn grab(&mut self, _surface: PopupSurface, _seat: wl_seat::WlSeat, _serial: Serial) {
-        // FIXME
-        // for s in &self.server_state.seats {
-        //     if s.server.owns(&seat) {
-        //         let popup = PopupKind::Xdg(surface);
-        //         if let Err(e) = self.server_state.popup_manager.grab_popup(
-        //             popup.wl_surface().clone(),
-        //             popup,
-        //             &s.server,
-        //             SERIAL_COUNTER.next_serial(),
-        //         ) {
-        //             error!(self.log.clone(), "{}", e);
-        //         }
-        //         break;
-        //     }
-        // }
+    fn grab(&mut self, surface: PopupSurface, seat: wl_seat::WlSeat, _serial: Serial) {
+        // the grab is forwarded with the serial of the client seat event which
+        // opened the popup, so the compositor routes keyboard input to it
+        let Some(seat) = Seat::<GlobalState>::from_resource(&seat) else {
+            return;
+        };
+        let Some(c_seat) = self
+            .server_state
+            .seats
+            .iter()
+            .find(|s| s.server.seat == seat)
+            .map(|s| (s.client.get_serial_of_last_seat_event(), s.client._seat.clone()))
+        else {
+            return;
+        };
+        self.space.grab_popup(&surface, c_seat);
     }
 
     fn reposition_request(
@@ -1,6 +1,6 @@
 // SPDX-License-Identifier: MPL-2.0
 
-use std::rc::Rc;
+use std::{rc::Rc, time::Instant};
 
 use cctk::wayland_client::protocol::wl_surface::WlSurface;
 use sctk::{
@@ -43,6 +43,8 @@ pub struct WrapperPopup {
     pub popup: PanelPopup,
     /// the embedded popup
     pub s_surface: PopupSurface,
+    /// instant at which the popup was opened
+    pub opened: Instant,
 }
 
 #[derive(Debug)]
@@ -50,6 +52,8 @@ pub struct PanelPopup {
     // XXX implicitly drops egl_surface first to avoid segfault
     /// the egl surface
     pub egl_surface: Option<Rc<EGLSurface>>,
+    /// number of failed attempts to create the egl surface
+    pub egl_attempts: u32,
 
     /// the popup on the layer shell surface
     pub c_popup: Popup,
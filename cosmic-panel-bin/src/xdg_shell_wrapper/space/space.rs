@@ -85,6 +85,33 @@ pub enum Visibility {
         /// previously calculated value
         prev_margin: i32,
     },
+    /// partially visible, between visible and hidden
+    Peek {
+        /// instant at which the peek started
+        since: Instant,
+    },
+    /// transitioning to peek, from visible or from hidden
+    TransitionToPeek {
+        /// previous instant that was processed
+        last_instant: Instant,
+        /// duration of the transition progressed
+        progress: Duration,
+        /// previously calculated value
+        prev_margin: i32,
+        /// margin at the start of the transition
+        start_margin: i32,
+    },
+    /// transitioning from peek to visible or to hidden
+    TransitionFromPeek {
+        /// previous instant that was processed
+        last_instant: Instant,
+        /// duration of the transition progressed
+        progress: Duration,
+        /// previously calculated value
+        prev_margin: i32,
+        /// whether the transition ends visible instead of hidden
+        to_visible: bool,
+    },
 }
 
 impl Default for Visibility {
@@ -93,6 +120,32 @@ impl Default for Visibility {
     }
 }
 
+impl Visibility {
+    /// the visibility without its animation state
+    pub fn kind(&self) -> VisibilityKind {
+        match self {
+            Self::Visible => VisibilityKind::Visible,
+            // only part of the panel is shown while peeking
+            Self::Hidden | Self::Peek { .. } => VisibilityKind::Hidden,
+            Self::TransitionToHidden { .. }
+            | Self::TransitionToVisible { .. }
+            | Self::TransitionToPeek { .. }
+            | Self::TransitionFromPeek { .. } => VisibilityKind::Transitioning,
+        }
+    }
+}
+
+/// Simplified visibility of the space
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum VisibilityKind {
+    /// fully shown
+    Visible,
+    /// hidden or peeking
+    Hidden,
+    /// animating between states
+    Transitioning,
+}
+
 // TODO break this trait into several traits so that it can be better organized
 // not all "space" implementations really need all of these exact methods as
 // long as they are wrapped by a space that does see cosmic-panel for an example
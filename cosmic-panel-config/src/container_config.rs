@@ -1,4 +1,4 @@
-use crate::{CosmicPanelBackground, CosmicPanelConfig, CosmicPanelOuput};
+use crate::{BorderRadius, CosmicPanelBackground, CosmicPanelConfig, CosmicPanelOuput};
 use cosmic_config::{Config, ConfigGet, ConfigSet, CosmicConfigEntry};
 use serde::{Deserialize, Serialize};
 use tracing::warn;
@@ -9,6 +9,48 @@ use xdg_shell_wrapper_config::{Layer, WrapperConfig, WrapperOutput};
 #[serde(deny_unknown_fields)]
 pub struct CosmicPanelContainerConfig {
     pub config_list: Vec<CosmicPanelConfig>,
+    /// name of the output preferred for panels configured for the active output
+    #[serde(default)]
+    pub primary_output: Option<String>,
+    /// RGB background color of panels used in place of that of the theme if
+    /// the theme fails to load
+    #[serde(default)]
+    pub fallback_background: Option<[f32; 3]>,
+    /// which panel keeps a corner shared by panels with exclusive zones on
+    /// adjacent edges of an output
+    #[serde(default)]
+    pub corner_ownership: CornerOwnership,
+    /// time in milliseconds without further changes to a panel config after
+    /// which the change is applied, so the events of a single save coalesce
+    ///
+    /// Zero applies every change immediately.
+    #[serde(default = "default_config_debounce")]
+    pub config_debounce: u32,
+}
+
+/// default debounce of changes to panel configs, in milliseconds
+pub const DEFAULT_CONFIG_DEBOUNCE: u32 = 100;
+
+fn default_config_debounce() -> u32 {
+    DEFAULT_CONFIG_DEBOUNCE
+}
+
+/// owner of the corners shared by panels on adjacent edges
+///
+/// Only panels which expand to the edges of the output and reserve an
+/// exclusive zone compete for a corner. The panel which does not own it leaves
+/// it free with a margin along its length as wide as the exclusive zone of the
+/// owner.
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum CornerOwnership {
+    /// leave the corners to the compositor
+    #[default]
+    Compositor,
+    /// top and bottom panels own the corners
+    Horizontal,
+    /// left and right panels own the corners
+    Vertical,
 }
 
 impl WrapperConfig for CosmicPanelContainerConfig {
@@ -58,6 +100,13 @@ impl CosmicPanelContainerConfig {
                 return Err((vec![e], Self::default()));
             },
         };
+        let primary_output = config.get::<Option<String>>("primary_output").unwrap_or_default();
+        let fallback_background =
+            config.get::<Option<[f32; 3]>>("fallback_background").unwrap_or_default();
+        let corner_ownership =
+            config.get::<CornerOwnership>("corner_ownership").unwrap_or_default();
+        let config_debounce =
+            config.get::<u32>("config_debounce").unwrap_or(DEFAULT_CONFIG_DEBOUNCE);
         let mut config_list = Vec::new();
         let mut entry_errors = Vec::new();
 
@@ -84,9 +133,21 @@ impl CosmicPanelContainerConfig {
             };
         }
         if entry_errors.is_empty() {
-            Ok(Self { config_list })
+            Ok(Self {
+                config_list,
+                primary_output,
+                fallback_background,
+                corner_ownership,
+                config_debounce,
+            })
         } else {
-            Err((entry_errors, Self { config_list }))
+            Err((entry_errors, Self {
+                config_list,
+                primary_output,
+                fallback_background,
+                corner_ownership,
+                config_debounce,
+            }))
         }
     }
 
@@ -94,8 +155,10 @@ impl CosmicPanelContainerConfig {
         let mut configs: Vec<_> = self
             .config_list
             .iter()
+            .filter(|c| c.creates_surface())
+            .filter(|c| !c.exclude_outputs.iter().any(|o| o == output_name))
             .filter(|c| match &c.output {
-                CosmicPanelOuput::All => true,
+                CosmicPanelOuput::All => !c.external_only || !self.is_primary_output(output_name),
                 CosmicPanelOuput::Name(n) => n == output_name,
                 _ => false,
             })
@@ -104,6 +167,17 @@ impl CosmicPanelContainerConfig {
         configs
     }
 
+    /// whether the output is the primary output
+    ///
+    /// This is the configured primary output, or any built-in display if none
+    /// is configured.
+    pub fn is_primary_output(&self, output_name: &str) -> bool {
+        match self.primary_output.as_ref() {
+            Some(primary) => primary == output_name,
+            None => ["eDP", "LVDS", "DSI"].iter().any(|p| output_name.starts_with(p)),
+        }
+    }
+
     pub fn cosmic_config() -> Result<Config, cosmic_config::Error> {
         Config::new(NAME, VERSION)
     }
@@ -112,6 +186,10 @@ impl CosmicPanelContainerConfig {
         let config = Self::cosmic_config()?;
         let entry_names = self.config_list.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
         config.set("entries", entry_names)?;
+        config.set("primary_output", self.primary_output.clone())?;
+        config.set("fallback_background", self.fallback_background)?;
+        config.set("corner_ownership", self.corner_ownership)?;
+        config.set("config_debounce", self.config_debounce)?;
         for entry in &self.config_list {
             let config = Config::new(format!("{}.{}", NAME, entry.name).as_str(), VERSION)?;
             entry.write_entry(&config)?;
@@ -157,11 +235,74 @@ impl Default for CosmicPanelContainerConfig {
                     expand_to_edges: true,
                     padding: 0,
                     spacing: 2,
-                    border_radius: 0,
+                    border_radius: BorderRadius::Px(0),
                     exclusive_zone: true,
                     autohide: None,
                     margin: 0,
                     opacity: 1.0,
+                    popup_at_pointer: false,
+                    persist_state: false,
+                    background_vertical: None,
+                    opacity_vertical: None,
+                    popup_min_size: None,
+                    background_image: None,
+                    background_image_insets: [0; 4],
+                    anchor_overrides: Default::default(),
+                    minimize_exclude: Vec::new(),
+                    applets_dir: None,
+                    oversized_popup: Default::default(),
+                    overflow_columns: 8,
+                    overflow_popup_style: None,
+                    cutouts: Default::default(),
+                    reduced_transition_resolution: false,
+                    click_zones: Vec::new(),
+                    length_range: None,
+                    popup_min_lifetime: 0,
+                    desaturate_peek: false,
+                    popup_output_bounds: Default::default(),
+                    fit_thickness: None,
+                    expand_animation: Default::default(),
+                    applet_corner_radius: 0,
+                    external_only: false,
+                    click_through: false,
+                    start_delay_ms: Default::default(),
+                    snap_to_pixel_grid: false,
+                    double_click_action: None,
+                    empty_panel: Default::default(),
+                    start_icon: None,
+                    border: None,
+                    collapsible_applets: Vec::new(),
+                    margin_mode: None,
+                    mirror_content: false,
+                    applet_env: Default::default(),
+                    input_corner_steps: 4,
+                    drop_target_applets: Vec::new(),
+                    gaps: None,
+                    max_visible: [None; 3],
+                    reorder_animation: Default::default(),
+                    wait_for_applets: None,
+                    oversized_applet: Default::default(),
+                    section_divider: None,
+                    badge_corner: Default::default(),
+                    mirror_to: None,
+                    minimize_target_size: None,
+                    crash_restart: Default::default(),
+                    opacity_bounds: None,
+                    center_outward: false,
+                    applet_alignment: Default::default(),
+                    duplicate_applets: Default::default(),
+                    home_output: None,
+                    exclude_outputs: Default::default(),
+                    hover_opacity: None,
+                    fractional_compensation: true,
+                    sandboxed_applets: Default::default(),
+                    popup_backdrop: None,
+                    popup_surface_attempts: 3,
+                    cache_background: true,
+                    empty_output_opacity: None,
+                    max_applet_popups: None,
+                    double_click_interval: 400,
+                    pinned_applets: Vec::new(),
                 },
                 CosmicPanelConfig {
                     name: "Dock".to_string(),
@@ -186,17 +327,86 @@ impl Default for CosmicPanelContainerConfig {
                     expand_to_edges: false,
                     padding: 0,
                     spacing: 4,
-                    border_radius: 160,
+                    border_radius: BorderRadius::Px(160),
                     exclusive_zone: false,
                     autohide: Some(crate::AutoHide {
                         wait_time: 500,
                         transition_time: 200,
                         handle_size: 2,
+                        peek_size: None,
+                        hysteresis: 0,
                     }),
                     margin: 0,
                     opacity: 1.0,
+                    popup_at_pointer: false,
+                    persist_state: false,
+                    background_vertical: None,
+                    opacity_vertical: None,
+                    popup_min_size: None,
+                    background_image: None,
+                    background_image_insets: [0; 4],
+                    anchor_overrides: Default::default(),
+                    minimize_exclude: Vec::new(),
+                    applets_dir: None,
+                    oversized_popup: Default::default(),
+                    overflow_columns: 8,
+                    overflow_popup_style: None,
+                    cutouts: Default::default(),
+                    reduced_transition_resolution: false,
+                    click_zones: Vec::new(),
+                    length_range: None,
+                    popup_min_lifetime: 0,
+                    desaturate_peek: false,
+                    popup_output_bounds: Default::default(),
+                    fit_thickness: None,
+                    expand_animation: Default::default(),
+                    applet_corner_radius: 0,
+                    external_only: false,
+                    click_through: false,
+                    start_delay_ms: Default::default(),
+                    snap_to_pixel_grid: false,
+                    double_click_action: None,
+                    empty_panel: Default::default(),
+                    start_icon: None,
+                    border: None,
+                    collapsible_applets: Vec::new(),
+                    margin_mode: None,
+                    mirror_content: false,
+                    applet_env: Default::default(),
+                    input_corner_steps: 4,
+                    drop_target_applets: Vec::new(),
+                    gaps: None,
+                    max_visible: [None; 3],
+                    reorder_animation: Default::default(),
+                    wait_for_applets: None,
+                    oversized_applet: Default::default(),
+                    section_divider: None,
+                    badge_corner: Default::default(),
+                    mirror_to: None,
+                    minimize_target_size: None,
+                    crash_restart: Default::default(),
+                    opacity_bounds: None,
+                    center_outward: false,
+                    applet_alignment: Default::default(),
+                    duplicate_applets: Default::default(),
+                    home_output: None,
+                    exclude_outputs: Default::default(),
+                    hover_opacity: None,
+                    fractional_compensation: true,
+                    sandboxed_applets: Default::default(),
+                    popup_backdrop: None,
+                    popup_surface_attempts: 3,
+                    cache_background: true,
+                    empty_output_opacity: None,
+                    max_applet_popups: None,
+                    double_click_interval: 400,
+                    pinned_applets: Vec::new(),
                 },
             ],
+            primary_output: None,
+            fallback_background: None,
+            corner_ownership: CornerOwnership::default(),
+            config_debounce: DEFAULT_CONFIG_DEBOUNCE,
         }
     }
 }
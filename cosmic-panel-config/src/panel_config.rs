@@ -1,6 +1,8 @@
 //! Config for cosmic-panel
 
-use std::{fmt::Display, ops::Range, str::FromStr, time::Duration};
+use std::{
+    collections::HashMap, fmt::Display, ops::Range, path::PathBuf, str::FromStr, time::Duration,
+};
 
 use anyhow::bail;
 use cosmic_config::{cosmic_config_derive::CosmicConfigEntry, Config, CosmicConfigEntry};
@@ -222,6 +224,182 @@ pub enum CosmicPanelBackground {
     Color([f32; 3]),
 }
 
+/// easing curve of an animation
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum Easing {
+    Linear,
+    /// cubic, starting slowly
+    EaseIn,
+    /// cubic, ending slowly
+    EaseOut,
+    #[default]
+    Smootherstep,
+}
+
+/// animation of the panel when its configuration changes, e.g. when it
+/// expands to the edges or contracts into a dock
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ExpandAnimation {
+    /// duration in milliseconds, the animation is disabled if 0
+    pub duration: u32,
+    pub easing: Easing,
+}
+
+impl Default for ExpandAnimation {
+    fn default() -> Self {
+        Self { duration: 300, easing: Easing::default() }
+    }
+}
+
+/// restarting of applets which crash, with exponential backoff
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct CrashRestart {
+    /// delay in milliseconds before restarting an applet after its first
+    /// crash, doubled for each further rapid crash
+    pub delay: u32,
+    /// number of rapid crashes after which an applet is no longer restarted
+    pub max_crashes: u32,
+    /// time in milliseconds within which crashes count as rapid
+    pub window: u32,
+}
+
+impl Default for CrashRestart {
+    fn default() -> Self {
+        Self { delay: 500, max_crashes: 5, window: 60_000 }
+    }
+}
+
+/// radius of the corners of the panel
+///
+/// Serialized as `Px(8)` for a radius in logical pixels, or as `Full`.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum BorderRadius {
+    /// radius in logical pixels, clamped to half the size of the panel
+    Px(u32),
+    /// as round as possible, so the panel stays a pill as it grows
+    Full,
+}
+
+impl From<u32> for BorderRadius {
+    fn from(radius: u32) -> Self {
+        Self::Px(radius)
+    }
+}
+
+/// corner of an applet at which its badge is drawn
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum BadgeCorner {
+    TopLeft,
+    #[default]
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// whether popups are kept on the output of the panel
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum PopupOutputBounds {
+    /// keep popups on the output of the panel as the applet requested
+    #[default]
+    Clamp,
+    /// let popups extend onto an output adjacent to the panel's output along
+    /// the panel
+    Span,
+}
+
+/// behavior for popups which are larger than the output
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum OversizedPopup {
+    /// shrink the popup to fit the output, clipping content which the applet
+    /// does not fit into the smaller size
+    #[default]
+    Clamp,
+    /// keep the size requested by the applet
+    Unconstrained,
+}
+
+/// behavior for applets which are thicker than the panel
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum OversizedApplet {
+    /// grow the panel to fit the applet
+    #[default]
+    Grow,
+    /// clip the applet to the thickness of the panel
+    Crop,
+    /// scale the applet down to the thickness of the panel
+    Scale,
+}
+
+/// alignment of applets across the thickness of the panel
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum AppletAlignment {
+    /// align applets to the top edge of a horizontal panel, or the left edge
+    /// of a vertical panel
+    Start,
+    #[default]
+    Center,
+    /// align applets to the bottom edge of a horizontal panel, or the right
+    /// edge of a vertical panel
+    End,
+}
+
+/// handling of an applet which is listed more than once in a panel
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum DuplicateApplets {
+    /// start only the first occurrence of the applet, with a warning
+    Dedup,
+    /// start every occurrence as a separate instance of the applet
+    #[default]
+    Instances,
+}
+
+/// appearance of a panel without any configured applets
+#[derive(Debug, Default, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum EmptyPanel {
+    /// show the background of the panel as an empty bar
+    #[default]
+    Background,
+    /// keep the panel surface, but hide it and reserve no space until applets
+    /// are added
+    Hidden,
+    /// do not create a surface for the panel
+    Omit,
+}
+
+/// margin between the panel and the edge of the output
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub enum GapMode {
+    /// margin in logical pixels
+    Pixels(u16),
+    /// margin as a fraction of the logical length of the output along the
+    /// panel
+    Fraction(f32),
+}
+
+impl GapMode {
+    /// the margin in logical pixels for an output of the given length
+    pub fn resolve(&self, output_length: u32) -> u16 {
+        match self {
+            GapMode::Pixels(p) => *p,
+            GapMode::Fraction(f) => {
+                (f.clamp(0., 1.) * output_length as f32).round().min(u16::MAX as f32) as u16
+            },
+        }
+    }
+}
+
 const fn _default_true() -> bool {
     true
 }
@@ -238,14 +416,97 @@ pub struct AutoHide {
     /// size of the handle in pixels
     /// should be > 0
     pub handle_size: u32,
+    /// size in pixels of the part of the panel which is exposed while peeking
+    /// the panel peeks on its way between visible and hidden if set
+    #[serde(default)]
+    pub peek_size: Option<u32>,
+    /// distance in pixels the pointer must move into the handle before the
+    /// hidden panel is revealed, and past the ends of a dock before it hides
+    #[serde(default)]
+    pub hysteresis: u32,
 }
 
 impl Default for AutoHide {
     fn default() -> Self {
-        Self { wait_time: 1000, transition_time: 200, handle_size: 4 }
+        Self {
+            wait_time: 1000,
+            transition_time: 200,
+            handle_size: 4,
+            peek_size: None,
+            hysteresis: 0,
+        }
     }
 }
 
+/// style of the overflow popup, unset values are taken from the theme
+#[derive(Debug, Default, Deserialize, Serialize, Clone, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct OverflowPopupStyle {
+    /// RGB background color
+    pub background: Option<[f32; 3]>,
+    /// opacity of the background
+    pub opacity: Option<f32>,
+    /// corner radius in pixels
+    pub border_radius: Option<u32>,
+}
+
+/// outline drawn around the panel
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct Border {
+    /// width of the outline in logical pixels
+    pub width: f32,
+    /// RGBA color of the outline
+    pub color: [f32; 4],
+}
+
+/// divider drawn between the wings and the center of the panel
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SectionDivider {
+    /// width of the divider in logical pixels
+    pub width: f32,
+    /// RGBA color of the divider, the divider color of the theme if unset
+    pub color: Option<[f32; 4]>,
+}
+
+/// action of a click zone of the panel
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum ClickAction {
+    /// run a command with `sh -c`
+    RunCommand(String),
+    /// hide or show a panel with autohide
+    ToggleVisibility,
+    /// emit the named signal on the session bus
+    EmitSignal(String),
+    /// collapse or expand the collapsible applets
+    ToggleCollapsed,
+}
+
+/// region of the panel which runs an action when clicked
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ClickZone {
+    /// x, y, width, and height in logical pixels relative to the panel
+    pub rect: (i32, i32, i32, i32),
+    /// action run when the zone is clicked
+    pub action: ClickAction,
+    /// name of the cursor shown over the zone, e.g. `default`, `pointer`
+    #[serde(default)]
+    pub cursor: Option<String>,
+}
+
+/// icon shown by the panel itself at the start of the panel
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct StartIcon {
+    /// name of the icon
+    pub icon: String,
+    /// action run when the icon is clicked
+    pub action: ClickAction,
+}
+
 /// Configuration for the panel's ouput
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
 #[serde(deny_unknown_fields)]
@@ -332,7 +593,7 @@ pub struct CosmicPanelConfig {
     pub padding: u32,
     /// space between panel plugins
     pub spacing: u32,
-    pub border_radius: u32,
+    pub border_radius: BorderRadius,
     // TODO autohide & exclusive zone should not be able to both be enabled at once
     /// exclusive zone
     pub exclusive_zone: bool,
@@ -343,6 +604,226 @@ pub struct CosmicPanelConfig {
     pub margin: u16,
     /// opacity of the panel
     pub opacity: f32,
+    /// anchor applet popups to the last pointer position instead of the applet
+    pub popup_at_pointer: bool,
+    /// remember runtime state such as visibility and pinning on each output
+    /// across restarts
+    pub persist_state: bool,
+    /// background used instead of `background` when the panel is vertical
+    pub background_vertical: Option<CosmicPanelBackground>,
+    /// opacity used instead of `opacity` when the panel is vertical
+    pub opacity_vertical: Option<f32>,
+    /// minimum logical width and height of applet popups
+    pub popup_min_size: Option<(u32, u32)>,
+    /// optional png image drawn as the background of the panel using 9-slice
+    /// scaling
+    pub background_image: Option<PathBuf>,
+    /// top, right, bottom, and left insets of the 9-slice background image in
+    /// pixels
+    pub background_image_insets: [u32; 4],
+    /// anchor to use instead of `anchor` on the named outputs
+    pub anchor_overrides: HashMap<String, PanelAnchor>,
+    /// app ids of toplevels which should not receive a minimize rectangle from
+    /// this panel
+    pub minimize_exclude: Vec<String>,
+    /// directory of applet desktop entries which are added to the panel as they
+    /// are found, see `X-CosmicPanelSection` for choosing the section of an
+    /// applet
+    pub applets_dir: Option<PathBuf>,
+    /// behavior for popups larger than the output
+    pub oversized_popup: OversizedPopup,
+    /// number of columns of applets in the overflow popup
+    pub overflow_columns: u32,
+    /// style of the overflow popup, which otherwise follows the theme
+    pub overflow_popup_style: Option<OverflowPopupStyle>,
+    /// width in logical pixels of a display cutout, like a camera notch,
+    /// centered on the panel by output name
+    /// applets are placed around the cutout, and it does not accept input
+    pub cutouts: HashMap<String, u32>,
+    /// render the panel at a reduced resolution while it is transitioning
+    /// between hidden and visible, which is upscaled by the compositor
+    pub reduced_transition_resolution: bool,
+    /// regions of the panel which run an action when clicked outside of applets
+    pub click_zones: Vec<ClickZone>,
+    /// start and end of the part of the output length occupied by the panel, as
+    /// fractions of the output length
+    pub length_range: Option<(f32, f32)>,
+    /// minimum time in milliseconds that a popup stays open before a press
+    /// outside of it or a loss of keyboard focus may dismiss it
+    pub popup_min_lifetime: u32,
+    /// render applets desaturated while the panel is peeking
+    pub desaturate_peek: bool,
+    /// whether popups may extend onto adjacent outputs
+    pub popup_output_bounds: PopupOutputBounds,
+    /// minimum and maximum thickness of the panel, if it should grow to fit its
+    /// applets instead of being limited by the size
+    pub fit_thickness: Option<(u32, u32)>,
+    /// animation of the panel when its configuration changes
+    pub expand_animation: ExpandAnimation,
+    /// radius of the rounded clip applied to applet surfaces, 0 disables it.
+    /// Applets may opt out with `X-CosmicRoundedClip=false` in their desktop
+    /// entry.
+    pub applet_corner_radius: u32,
+    /// when the output is `All`, do not show the panel on the primary output.
+    /// Without a configured primary output, built-in displays are considered
+    /// primary.
+    pub external_only: bool,
+    /// let all pointer events pass through the panel to the windows below.
+    /// Applets are still shown, but receive no pointer input, and autohide is
+    /// disabled.
+    pub click_through: bool,
+    /// delay in milliseconds before starting an applet, by applet name.
+    /// Heavy applets can be delayed so lighter ones are shown first.
+    pub start_delay_ms: HashMap<String, u32>,
+    /// round the margins and size of the panel to whole physical pixels, which
+    /// keeps its edges crisp at fractional scales
+    pub snap_to_pixel_grid: bool,
+    /// action run when empty space of the panel is double clicked
+    pub double_click_action: Option<ClickAction>,
+    /// behavior of the panel when no applets are configured
+    pub empty_panel: EmptyPanel,
+    /// icon shown before the applets at the start of the panel, which runs an
+    /// action when clicked
+    pub start_icon: Option<StartIcon>,
+    /// outline drawn inside the edges of the panel, following its rounded
+    /// corners
+    pub border: Option<Border>,
+    /// applets moved behind the overflow button of their section while the
+    /// panel is collapsed
+    ///
+    /// While they are expanded, pressing the overflow button of a section with
+    /// at least two of them collapses them, and right clicking it expands them
+    /// again.
+    pub collapsible_applets: Vec<String>,
+    /// margin used in place of `margin`, which may be a fraction of the length
+    /// of the output
+    pub margin_mode: Option<GapMode>,
+    /// mirror the content of applets on a right or bottom panel, so it faces
+    /// the same way as on a left or top panel
+    pub mirror_content: bool,
+    /// additional environment variables passed to applets, by applet name
+    pub applet_env: HashMap<String, Vec<(String, String)>>,
+    /// number of rectangles approximating each rounded corner of the input
+    /// region
+    ///
+    /// More rectangles follow the rounded corners of the panel more closely.
+    /// The value is clamped to the range 1..=16.
+    pub input_corner_steps: u32,
+    /// applets highlighted as drop targets while something is dragged over the
+    /// panel
+    pub drop_target_applets: Vec<String>,
+    /// gaps between the panel and the top, right, bottom, and left edges of the
+    /// output, in place of `margin` and `anchor_gap`
+    ///
+    /// The gap on the anchored edge is drawn by the panel, and the gaps on the
+    /// edges at its start and end are margins of the layer surface. The gap on
+    /// the opposite edge is unused.
+    pub gaps: Option<[u16; 4]>,
+    /// maximum number of applets shown in the start, center, and end sections
+    /// before the rest are moved to the overflow popup of the section
+    ///
+    /// The overflow button takes the place of one applet. Panels which do not
+    /// expand to the edges have a single section, limited by the center value.
+    pub max_visible: [Option<u32>; 3],
+    /// animation of applets gliding to their new positions when they are
+    /// reordered
+    pub reorder_animation: ExpandAnimation,
+    /// withhold the first frame of the panel until every applet has mapped a
+    /// window, or until this many milliseconds passed
+    pub wait_for_applets: Option<u32>,
+    /// behavior for applets which are thicker than the panel
+    pub oversized_applet: OversizedApplet,
+    /// divider drawn where a wing ends and the center begins
+    ///
+    /// It is only drawn between non-empty sections with enough space between
+    /// them for the divider and the spacing on either side.
+    pub section_divider: Option<SectionDivider>,
+    /// corner of an applet at which the count it reports is drawn
+    pub badge_corner: BadgeCorner,
+    /// name of a second output on which the panel is mirrored
+    ///
+    /// The mirror shows the same applets as the panel, but doesn't accept
+    /// input.
+    pub mirror_to: Option<String>,
+    /// size of the square which minimized windows animate into, centered on the
+    /// minimize applet
+    ///
+    /// The crosswise size of the applet is used if unset.
+    pub minimize_target_size: Option<u32>,
+    /// restarting of applets which crash
+    pub crash_restart: CrashRestart,
+    /// lowest and highest opacity of the background
+    ///
+    /// Applied to the opacity after runtime overrides, vertical overrides and
+    /// animations, so the panel never becomes invisible or unexpectedly opaque.
+    pub opacity_bounds: Option<(f32, f32)>,
+    /// lay out the applets of the center section from the center outward
+    ///
+    /// The first applet is placed in the middle, and the others alternate to
+    /// its left and right.
+    pub center_outward: bool,
+    /// alignment of applets across the thickness of the panel
+    pub applet_alignment: AppletAlignment,
+    /// handling of applets listed more than once in the panel
+    ///
+    /// By default every occurrence keeps its place in the panel and is started,
+    /// and each instance after the first gets its index in the
+    /// `COSMIC_PANEL_APPLET_INSTANCE` environment variable. With `Dedup` only
+    /// the first occurrence is started.
+    pub duplicate_applets: DuplicateApplets,
+    /// output a panel configured for the active output returns to when it is
+    /// not wanted on the active output
+    pub home_output: Option<String>,
+    /// names of outputs the panel is never shown on
+    ///
+    /// A panel configured for the active output moves to its home output
+    /// instead, or else to the first output which is not excluded.
+    pub exclude_outputs: Vec<String>,
+    /// opacity of applets while not hovered and while hovered, faded between
+    /// when the pointer enters or leaves an applet. `None` draws applets
+    /// opaque.
+    pub hover_opacity: Option<(f32, f32)>,
+    /// at a fractional scale, move applets which would end past the panel once
+    /// their placement is rounded to physical pixels back within it
+    pub fractional_compensation: bool,
+    /// names of applets whose connection to the host compositor is made through
+    /// an unprivileged security context, so they cannot use the protocols
+    /// granted to the panel
+    pub sandboxed_applets: Vec<String>,
+    /// opacity of a dark backdrop faded in over the panel while a popup is
+    /// open. A press on another applet only dismisses the popup.
+    pub popup_backdrop: Option<f32>,
+    /// number of attempts to create the surface of a popup before it is given
+    /// up
+    ///
+    /// Failed attempts are retried on the following configure events of the
+    /// popup, or on a timer if the compositor can't reposition it.
+    pub popup_surface_attempts: u32,
+    /// reuse the background of the panel across relayouts while its size,
+    /// corner radius, position and colors are unchanged
+    ///
+    /// Disabling it rebuilds the background on every relayout during
+    /// animations.
+    pub cache_background: bool,
+    /// opacity of the panel background while no window is shown on its output
+    ///
+    /// The background fades to it when the last window on the output is closed,
+    /// minimized or moved away, and back when a window appears.
+    pub empty_output_opacity: Option<f32>,
+    /// maximum number of popups an applet may have open at once, or `None` for
+    /// no limit
+    ///
+    /// New popups of an applet at the limit are dismissed right away.
+    pub max_applet_popups: Option<u32>,
+    /// maximum time in milliseconds between the clicks of a double click on
+    /// empty space of the panel
+    pub double_click_interval: u32,
+    /// names of applets which are kept on the panel instead of being moved to
+    /// an overflow popup
+    ///
+    /// Instances after the first of an applet listed more than once are named
+    /// with their index, e.g. `com.system76.CosmicAppList#1`.
+    pub pinned_applets: Vec<String>,
 }
 
 impl PartialEq for CosmicPanelConfig {
@@ -368,6 +849,72 @@ impl PartialEq for CosmicPanelConfig {
             && self.margin == other.margin
             && self.size_center == other.size_center
             && self.size_wings == other.size_wings
+            && self.popup_at_pointer == other.popup_at_pointer
+            && self.persist_state == other.persist_state
+            && self.background_vertical == other.background_vertical
+            && match (self.opacity_vertical, other.opacity_vertical) {
+                (Some(a), Some(b)) => (a - b).abs() < 0.01,
+                (a, b) => a.is_none() && b.is_none(),
+            }
+            && self.popup_min_size == other.popup_min_size
+            && self.background_image == other.background_image
+            && self.background_image_insets == other.background_image_insets
+            && self.anchor_overrides == other.anchor_overrides
+            && self.minimize_exclude == other.minimize_exclude
+            && self.applets_dir == other.applets_dir
+            && self.oversized_popup == other.oversized_popup
+            && self.overflow_columns == other.overflow_columns
+            && self.overflow_popup_style == other.overflow_popup_style
+            && self.cutouts == other.cutouts
+            && self.reduced_transition_resolution == other.reduced_transition_resolution
+            && self.click_zones == other.click_zones
+            && self.length_range == other.length_range
+            && self.popup_min_lifetime == other.popup_min_lifetime
+            && self.desaturate_peek == other.desaturate_peek
+            && self.popup_output_bounds == other.popup_output_bounds
+            && self.fit_thickness == other.fit_thickness
+            && self.expand_animation == other.expand_animation
+            && self.applet_corner_radius == other.applet_corner_radius
+            && self.external_only == other.external_only
+            && self.click_through == other.click_through
+            && self.start_delay_ms == other.start_delay_ms
+            && self.snap_to_pixel_grid == other.snap_to_pixel_grid
+            && self.double_click_action == other.double_click_action
+            && self.empty_panel == other.empty_panel
+            && self.start_icon == other.start_icon
+            && self.border == other.border
+            && self.collapsible_applets == other.collapsible_applets
+            && self.margin_mode == other.margin_mode
+            && self.mirror_content == other.mirror_content
+            && self.applet_env == other.applet_env
+            && self.input_corner_steps == other.input_corner_steps
+            && self.drop_target_applets == other.drop_target_applets
+            && self.gaps == other.gaps
+            && self.max_visible == other.max_visible
+            && self.reorder_animation == other.reorder_animation
+            && self.wait_for_applets == other.wait_for_applets
+            && self.oversized_applet == other.oversized_applet
+            && self.section_divider == other.section_divider
+            && self.badge_corner == other.badge_corner
+            && self.mirror_to == other.mirror_to
+            && self.minimize_target_size == other.minimize_target_size
+            && self.crash_restart == other.crash_restart
+            && self.opacity_bounds == other.opacity_bounds
+            && self.center_outward == other.center_outward
+            && self.applet_alignment == other.applet_alignment
+            && self.duplicate_applets == other.duplicate_applets
+            && self.home_output == other.home_output
+            && self.exclude_outputs == other.exclude_outputs
+            && self.hover_opacity == other.hover_opacity
+            && self.fractional_compensation == other.fractional_compensation
+            && self.sandboxed_applets == other.sandboxed_applets
+            && self.popup_backdrop == other.popup_backdrop
+            && self.popup_surface_attempts == other.popup_surface_attempts
+            && self.cache_background == other.cache_background
+            && self.empty_output_opacity == other.empty_output_opacity
+            && self.max_applet_popups == other.max_applet_popups
+            && self.double_click_interval == other.double_click_interval
+            && self.pinned_applets == other.pinned_applets
             && (self.opacity - other.opacity).abs() < 0.01
     }
 }
@@ -393,9 +940,72 @@ impl Default for CosmicPanelConfig {
             spacing: 4,
             exclusive_zone: true,
             autohide: Some(AutoHide::default()),
-            border_radius: 8,
+            border_radius: BorderRadius::Px(8),
             margin: 4,
             opacity: 0.8,
+            popup_at_pointer: false,
+            persist_state: false,
+            background_vertical: None,
+            opacity_vertical: None,
+            popup_min_size: None,
+            background_image: None,
+            background_image_insets: [0; 4],
+            anchor_overrides: Default::default(),
+            minimize_exclude: Vec::new(),
+            applets_dir: None,
+            oversized_popup: OversizedPopup::default(),
+            overflow_columns: 8,
+            overflow_popup_style: None,
+            cutouts: Default::default(),
+            reduced_transition_resolution: false,
+            click_zones: Vec::new(),
+            length_range: None,
+            popup_min_lifetime: 0,
+            desaturate_peek: false,
+            popup_output_bounds: PopupOutputBounds::Clamp,
+            fit_thickness: None,
+            expand_animation: ExpandAnimation::default(),
+            applet_corner_radius: 0,
+            external_only: false,
+            click_through: false,
+            start_delay_ms: HashMap::new(),
+            snap_to_pixel_grid: false,
+            double_click_action: None,
+            empty_panel: EmptyPanel::default(),
+            start_icon: None,
+            border: None,
+            collapsible_applets: Vec::new(),
+            margin_mode: None,
+            mirror_content: false,
+            applet_env: Default::default(),
+            input_corner_steps: 4,
+            drop_target_applets: Vec::new(),
+            gaps: None,
+            max_visible: [None; 3],
+            reorder_animation: Default::default(),
+            wait_for_applets: None,
+            oversized_applet: OversizedApplet::default(),
+            section_divider: None,
+            badge_corner: Default::default(),
+            mirror_to: None,
+            minimize_target_size: None,
+            crash_restart: CrashRestart::default(),
+            opacity_bounds: None,
+            center_outward: false,
+            applet_alignment: Default::default(),
+            duplicate_applets: Default::default(),
+            home_output: None,
+            exclude_outputs: Vec::new(),
+            hover_opacity: None,
+            fractional_compensation: true,
+            sandboxed_applets: Vec::new(),
+            popup_backdrop: None,
+            popup_surface_attempts: 3,
+            cache_background: true,
+            empty_output_opacity: None,
+            max_applet_popups: None,
+            double_click_interval: 400,
+            pinned_applets: Vec::new(),
         }
     }
 }
@@ -469,15 +1079,58 @@ impl CosmicPanelConfig {
         self.margin
     }
 
+    /// the thickest an applet may be without growing the panel
+    pub fn get_applet_thickness(&self) -> u32 {
+        match self.fit_thickness {
+            Some((_, max)) => max.saturating_sub(2 * self.padding).max(1),
+            None => {
+                self.size.get_applet_icon_size(false)
+                    + 2 * self.size.get_applet_padding(false) as u32
+            },
+        }
+    }
+
+    /// the thickness an applet takes in the panel, and the factor it is
+    /// scaled by, for an applet of the given thickness
+    pub fn fit_applet_thickness(&self, thickness: f64) -> (f64, f64) {
+        let max = self.get_applet_thickness() as f64;
+        match self.oversized_applet {
+            _ if thickness <= max => (thickness, 1.),
+            OversizedApplet::Grow => (thickness, 1.),
+            OversizedApplet::Crop => (max, 1.),
+            OversizedApplet::Scale => (max, max / thickness),
+        }
+    }
+
     /// get the effective anchor gap margin
     pub fn get_effective_anchor_gap(&self) -> u32 {
-        if self.anchor_gap {
+        if let Some(gaps) = self.gaps {
+            let side = match self.anchor {
+                PanelAnchor::Top => 0,
+                PanelAnchor::Right => 1,
+                PanelAnchor::Bottom => 2,
+                PanelAnchor::Left => 3,
+            };
+            gaps[side] as u32
+        } else if self.anchor_gap {
             self.margin as u32
         } else {
             0
         }
     }
 
+    /// get the margins at the start and end of the panel along its length
+    ///
+    /// These are the configured gaps on the edges at the start and end of the
+    /// panel, or the given margin on both if no gaps are configured.
+    pub fn get_length_gaps(&self, margin: u32) -> (i32, i32) {
+        match self.gaps {
+            Some([_, right, _, left]) if self.is_horizontal() => (left as i32, right as i32),
+            Some([top, _, bottom, _]) => (top as i32, bottom as i32),
+            None => (margin as i32, margin as i32),
+        }
+    }
+
     /// if autohide is configured, returns the duration of time which the panel
     /// should wait to hide when it has lost focus
     pub fn get_hide_wait(&self) -> Option<Duration> {
@@ -500,6 +1153,23 @@ impl CosmicPanelConfig {
         self.autohide.as_ref().map(|AutoHide { handle_size, .. }| *handle_size)
     }
 
+    /// `input_corner_steps`, clamped to a usable range
+    pub fn get_input_corner_steps(&self) -> u32 {
+        self.input_corner_steps.clamp(1, 16)
+    }
+
+    pub fn get_hide_hysteresis(&self) -> Option<u32> {
+        self.autohide.as_ref().map(|AutoHide { hysteresis, .. }| *hysteresis)
+    }
+
+    /// if autohide is configured with peeking, returns the size of the part of
+    /// the panel which should be exposed while peeking
+    pub fn get_peek_size(&self) -> Option<u32> {
+        self.autohide.as_ref().and_then(|AutoHide { peek_size, handle_size, .. }| {
+            peek_size.filter(|peek_size| peek_size > handle_size)
+        })
+    }
+
     pub fn background(&self) -> CosmicPanelBackground {
         self.background.clone()
     }
@@ -513,7 +1183,8 @@ impl CosmicPanelConfig {
     }
 
     pub fn autohide(&self) -> Option<AutoHide> {
-        self.autohide.clone()
+        // without pointer input, the panel could never be revealed
+        self.autohide.clone().filter(|_| !self.click_through)
     }
 
     /// get whether the panel should expand to cover the edges of the output
@@ -521,6 +1192,22 @@ impl CosmicPanelConfig {
         self.expand_to_edges
     }
 
+    /// whether any applets are configured for the panel
+    pub fn has_applets(&self) -> bool {
+        self.plugins_wings.as_ref().is_some_and(|(l, r)| !l.is_empty() || !r.is_empty())
+            || self.plugins_center.as_ref().is_some_and(|c| !c.is_empty())
+    }
+
+    /// whether the panel is hidden because it has no applets
+    pub fn hidden_while_empty(&self) -> bool {
+        self.empty_panel == EmptyPanel::Hidden && !self.has_applets()
+    }
+
+    /// whether a surface should be created for the panel
+    pub fn creates_surface(&self) -> bool {
+        self.empty_panel != EmptyPanel::Omit || self.has_applets()
+    }
+
     pub fn plugins_left(&self) -> Option<Vec<String>> {
         self.plugins_wings.as_ref().map(|w| w.0.clone())
     }
@@ -556,6 +1243,36 @@ impl CosmicPanelConfig {
         }
     }
 
+    /// apply the anchor override for the given output
+    pub fn apply_anchor_override(&mut self, output_name: &str) {
+        if let Some(anchor) = self.anchor_overrides.get(output_name) {
+            self.anchor = *anchor;
+        }
+    }
+
+    /// apply the orientation specific overrides for background and opacity
+    pub fn apply_orientation_overrides(&mut self) {
+        if self.is_horizontal() {
+            return;
+        }
+        if let Some(background) = self.background_vertical.clone() {
+            self.background = background;
+        }
+        if let Some(opacity) = self.opacity_vertical {
+            self.opacity = opacity;
+        }
+    }
+
+    /// resolve the configured margin mode against the logical size of the
+    /// output, replacing `margin`
+    pub fn resolve_margin(&mut self, output_size: Option<(i32, i32)>) {
+        let (Some(mode), Some((w, h))) = (self.margin_mode, output_size) else {
+            return;
+        };
+        let len = if self.is_horizontal() { w } else { h };
+        self.margin = mode.resolve(len.max(0) as u32);
+    }
+
     pub fn bg_color_override(&self) -> Option<[f32; 4]> {
         match self.background {
             CosmicPanelBackground::Color(c) => Some([c[0], c[1], c[2], self.opacity]),
@@ -571,12 +1288,16 @@ impl CosmicPanelConfig {
         gap: Option<u32>,
     ) -> (Option<Range<u32>>, Option<Range<u32>>) {
         let gap = gap.unwrap_or_else(|| self.get_effective_anchor_gap());
-        let bar_thickness = match &self.size {
-            PanelSize::XS => 8 + gap..61 + gap,
-            PanelSize::S => 8 + gap..81 + gap,
-            PanelSize::M => 8 + gap..101 + gap,
-            PanelSize::L => 8 + gap..121 + gap,
-            PanelSize::XL => 8 + gap..141 + gap,
+        let bar_thickness = match (&self.size, self.fit_thickness) {
+            (_, Some((min, max))) => {
+                let max = max.max(min).max(2 * self.padding + 1);
+                min + gap..max + 1 + gap
+            },
+            (PanelSize::XS, None) => 8 + gap..61 + gap,
+            (PanelSize::S, None) => 8 + gap..81 + gap,
+            (PanelSize::M, None) => 8 + gap..101 + gap,
+            (PanelSize::L, None) => 8 + gap..121 + gap,
+            (PanelSize::XL, None) => 8 + gap..141 + gap,
         };
         assert!(2 * self.padding + gap < bar_thickness.end);
         let o_h = suggested_length.unwrap_or_else(|| output_dims.unwrap_or_default().1);
@@ -588,6 +1309,21 @@ impl CosmicPanelConfig {
         }
     }
 
+    /// the valid part of the output length occupied by the panel, if the panel
+    /// does not span the whole output
+    pub fn get_length_range(&self) -> Option<(f32, f32)> {
+        self.length_range
+            .map(|(start, end)| (start.clamp(0., 1.), end.clamp(0., 1.)))
+            .filter(|(start, end)| start < end && (*start > 0. || *end < 1.))
+    }
+
+    /// whether both panels may occupy the same part of an edge
+    pub fn overlaps_lengthwise(&self, other: &Self) -> bool {
+        let (start, end) = self.get_length_range().unwrap_or((0., 1.));
+        let (other_start, other_end) = other.get_length_range().unwrap_or((0., 1.));
+        start < other_end && other_start < end
+    }
+
     pub fn cosmic_config(name: &str) -> Result<Config, cosmic_config::Error> {
         let entry_name = format!("{NAME}.{}", name);
         Config::new(&entry_name, VERSION)
@@ -600,7 +1336,7 @@ impl CosmicPanelConfig {
         }
         self.expand_to_edges = true;
         self.margin = 0;
-        self.border_radius = 0;
+        self.border_radius = BorderRadius::Px(0);
         self.anchor_gap = false;
     }
 }
@@ -615,3 +1351,25 @@ impl WrapperConfig for CosmicPanelConfig {
         &self.name
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn border_radius_round_trips_through_ron() {
+        for radius in [BorderRadius::Px(0), BorderRadius::Px(160), BorderRadius::Full] {
+            let serialized = ron::to_string(&radius).unwrap();
+            assert_eq!(ron::from_str::<BorderRadius>(&serialized).unwrap(), radius);
+        }
+        assert_eq!(ron::to_string(&BorderRadius::Px(8)).unwrap(), "Px(8)");
+        assert_eq!(ron::to_string(&BorderRadius::Full).unwrap(), "Full");
+    }
+
+    #[test]
+    fn border_radius_rejects_other_identifiers() {
+        assert!(ron::from_str::<BorderRadius>("Round").is_err());
+        assert!(ron::from_str::<BorderRadius>("()").is_err());
+        assert!(ron::from_str::<BorderRadius>("Full(8)").is_err());
+    }
+}